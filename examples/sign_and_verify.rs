@@ -60,7 +60,8 @@ async fn main() -> Result<()> {
 
     // verify the VPC Credential
 
-    tdk.verify_data(&unsigned_vpc, None, &proof).await?;
+    tdk.verify_data(&unsigned_vpc, None, &proof.try_into()?)
+        .await?;
     println!("*************************************************************************");
     println!("Successfully verified the Persona Credential");
     println!(
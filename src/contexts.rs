@@ -0,0 +1,470 @@
+/*!
+*   Bundled JSON-LD context documents and an offline document loader.
+*
+*   The W3C credential contexts and the DTG context ship with the crate, each pinned to the
+*   digest of its canonical (JCS) form. Applications can register additional contexts, a context
+*   URL can only ever resolve to one document so a registered context can't be replaced.
+*
+*   The shared loader, see [register_context], is used when canonicalizing credentials with
+*   [crate::rdfc].
+*/
+
+use crate::DTGCredentialError;
+use serde_json::{Map, Value};
+use serde_json_canonicalizer::to_string;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::{LazyLock, RwLock},
+};
+
+/// W3C Verifiable Credentials 1.1 context
+pub const CREDENTIALS_V1: &str = "https://www.w3.org/2018/credentials/v1";
+
+/// W3C Verifiable Credentials 2.0 context
+pub const CREDENTIALS_V2: &str = "https://www.w3.org/ns/credentials/v2";
+
+/// W3C Verifiable Credentials 2.0 examples context
+pub const CREDENTIALS_EXAMPLES_V2: &str = "https://www.w3.org/ns/credentials/examples/v2";
+
+/// W3C Data Integrity 2.0 context
+pub const DATA_INTEGRITY_V2: &str = "https://w3id.org/security/data-integrity/v2";
+
+/// DTG context
+pub const DTG_CONTEXT_V1: &str = "https://firstperson.network/credentials/dtg/v1";
+
+/// Bundled context documents (url, document, pinned digest)
+const BUNDLED: [(&str, &str, &str); 5] = [
+    (
+        CREDENTIALS_V1,
+        include_str!("contexts/credentials-v1.jsonld"),
+        "sha256:b01e671e873981f19a9102a9a57f666dbcaeb31b99e3e124378d143e71549247",
+    ),
+    (
+        CREDENTIALS_V2,
+        include_str!("contexts/credentials-v2.jsonld"),
+        "sha256:b463c8d6a066214123ddd9827b135e1b50e1fc73322cc52a9b12a4f1fc7d86cf",
+    ),
+    (
+        CREDENTIALS_EXAMPLES_V2,
+        include_str!("contexts/credentials-examples-v2.jsonld"),
+        "sha256:8a675bead391be98e6054c4e07c41f73337508d398e07047344c5d349aa2dc58",
+    ),
+    (
+        DATA_INTEGRITY_V2,
+        include_str!("contexts/data-integrity-v2.jsonld"),
+        "sha256:7ba3c50acf2689d5e07927267343eb353249c7c22a9f2d00307cc970e855e305",
+    ),
+    (
+        DTG_CONTEXT_V1,
+        include_str!("contexts/dtg-v1.jsonld"),
+        "sha256:058c8c2d8d43b993948283bea8abc5280a1316341967ef4cd3a227cd890ff794",
+    ),
+];
+
+/// Limit on contexts referencing other contexts, guards against reference cycles
+const MAX_CONTEXT_DEPTH: usize = 8;
+
+fn context_error(msg: impl Display) -> DTGCredentialError {
+    DTGCredentialError::Context(msg.to_string())
+}
+
+/// Digest of a context document
+/// Format: `sha256:<hex of the JCS canonical document>`
+pub fn context_digest(document: &Value) -> Result<String, DTGCredentialError> {
+    let canonical = to_string(document)
+        .map_err(|e| context_error(format!("Couldn't canonicalize context: {e}")))?;
+    Ok(format!(
+        "sha256:{}",
+        Sha256::digest(canonical.as_bytes())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>()
+    ))
+}
+
+/// A JSON-LD context document
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContextDocument {
+    url: String,
+    document: Value,
+    digest: String,
+}
+
+impl ContextDocument {
+    /// URL the document is resolved from
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The full document, including the wrapping `@context`
+    pub fn document(&self) -> &Value {
+        &self.document
+    }
+
+    /// The context definition, the value of `@context`
+    pub fn context(&self) -> &Value {
+        &self.document["@context"]
+    }
+
+    /// Digest of the document, see [context_digest]
+    pub fn digest(&self) -> &str {
+        &self.digest
+    }
+}
+
+/// Resolves JSON-LD context URLs without network access
+/// The default loader holds the bundled contexts
+#[derive(Clone, Debug)]
+pub struct DocumentLoader {
+    documents: HashMap<String, ContextDocument>,
+}
+
+impl Default for DocumentLoader {
+    fn default() -> Self {
+        let mut loader = DocumentLoader::empty();
+        for (url, document, digest) in BUNDLED {
+            let document = serde_json::from_str(document).expect("bundled context is valid JSON");
+            loader
+                .register(url, document, Some(digest))
+                .expect("bundled context matches its pinned digest");
+        }
+        loader
+    }
+}
+
+impl DocumentLoader {
+    /// Loader with the bundled contexts
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loader without any contexts
+    pub fn empty() -> Self {
+        DocumentLoader {
+            documents: HashMap::new(),
+        }
+    }
+
+    /// Register a context document
+    /// url: URL the context is referenced by
+    /// document: The context document, must contain `@context`
+    /// digest: Optional expected digest, see [context_digest]
+    ///
+    /// Registering the same document twice is a no-op, a different document for a known URL is
+    /// an error
+    pub fn register(
+        &mut self,
+        url: &str,
+        document: Value,
+        digest: Option<&str>,
+    ) -> Result<(), DTGCredentialError> {
+        if !document
+            .get("@context")
+            .is_some_and(|c| c.is_object() || c.is_array())
+        {
+            return Err(context_error(format!(
+                "Context document ({url}) must contain an @context object"
+            )));
+        }
+
+        let computed = context_digest(&document)?;
+        if let Some(digest) = digest
+            && digest != computed
+        {
+            return Err(context_error(format!(
+                "Context document ({url}) digest mismatch: expected {digest}, got {computed}"
+            )));
+        }
+
+        if let Some(existing) = self.documents.get(url) {
+            return if existing.digest == computed {
+                Ok(())
+            } else {
+                Err(context_error(format!(
+                    "Context ({url}) is already registered with a different document"
+                )))
+            };
+        }
+
+        self.documents.insert(
+            url.to_string(),
+            ContextDocument {
+                url: url.to_string(),
+                document,
+                digest: computed,
+            },
+        );
+        Ok(())
+    }
+
+    /// Is a context registered for this URL?
+    pub fn contains(&self, url: &str) -> bool {
+        self.documents.contains_key(url)
+    }
+
+    /// URLs of all registered contexts
+    pub fn urls(&self) -> impl Iterator<Item = &str> {
+        self.documents.keys().map(|url| url.as_str())
+    }
+
+    /// Resolve a context URL
+    pub fn load(&self, url: &str) -> Result<&ContextDocument, DTGCredentialError> {
+        self.documents
+            .get(url)
+            .ok_or_else(|| context_error(format!("Unknown context ({url})")))
+    }
+
+    /// Resolve a context URL, the document must match the expected digest
+    pub fn load_pinned(
+        &self,
+        url: &str,
+        digest: &str,
+    ) -> Result<&ContextDocument, DTGCredentialError> {
+        let document = self.load(url)?;
+        if document.digest != digest {
+            return Err(context_error(format!(
+                "Context document ({url}) digest mismatch: expected {digest}, got {}",
+                document.digest
+            )));
+        }
+        Ok(document)
+    }
+
+    /// Replaces context URLs with their definitions so a document can be expanded offline
+    /// Compact IRIs in the definitions are expanded, so terms don't depend on definition order
+    pub fn inline(&self, context: &Value) -> Result<Value, DTGCredentialError> {
+        self.inline_nested(context, 0)
+    }
+
+    fn inline_nested(&self, context: &Value, depth: usize) -> Result<Value, DTGCredentialError> {
+        if depth > MAX_CONTEXT_DEPTH {
+            return Err(context_error("Context references are nested too deeply"));
+        }
+
+        match context {
+            Value::String(url) => self.inline_nested(self.load(url)?.context(), depth + 1),
+            Value::Array(contexts) => Ok(Value::Array(
+                contexts
+                    .iter()
+                    .map(|context| self.inline_nested(context, depth))
+                    .collect::<Result<_, _>>()?,
+            )),
+            Value::Object(_) => Ok(expand_compact_iris(context, &HashMap::new())),
+            Value::Null => Ok(Value::Null),
+            other => Err(context_error(format!("Invalid @context value: {other}"))),
+        }
+    }
+}
+
+/// Can this IRI be used as a prefix in a compact IRI?
+fn is_prefix_iri(iri: &str) -> bool {
+    iri.contains(':') && iri.ends_with(['/', '#', ':', '?', '[', ']', '@'])
+}
+
+/// Expands `prefix:suffix` using prefixes defined in this or an enclosing context definition
+fn expand_iri(value: &str, prefixes: &HashMap<String, String>) -> String {
+    match value.split_once(':') {
+        Some((prefix, suffix)) if !suffix.starts_with("//") => prefixes
+            .get(prefix)
+            .map(|iri| format!("{iri}{suffix}"))
+            .unwrap_or_else(|| value.to_string()),
+        _ => value.to_string(),
+    }
+}
+
+fn expand_compact_iris(context: &Value, inherited: &HashMap<String, String>) -> Value {
+    let Value::Object(definitions) = context else {
+        return context.clone();
+    };
+
+    let mut prefixes = inherited.clone();
+    for (term, definition) in definitions {
+        if let Value::String(iri) = definition
+            && !term.starts_with('@')
+            && is_prefix_iri(iri)
+        {
+            prefixes.insert(term.clone(), iri.clone());
+        }
+    }
+
+    let mut expanded = Map::new();
+    for (term, definition) in definitions {
+        let definition = match definition {
+            Value::String(iri) if !term.starts_with('@') => {
+                Value::String(expand_iri(iri, &prefixes))
+            }
+            Value::Object(fields) => Value::Object(
+                fields
+                    .iter()
+                    .map(|(key, value)| {
+                        let value = match (key.as_str(), value) {
+                            ("@id" | "@type", Value::String(iri)) => {
+                                Value::String(expand_iri(iri, &prefixes))
+                            }
+                            ("@context", context) => expand_compact_iris(context, &prefixes),
+                            _ => value.clone(),
+                        };
+                        (key.clone(), value)
+                    })
+                    .collect(),
+            ),
+            other => other.clone(),
+        };
+        expanded.insert(term.clone(), definition);
+    }
+    Value::Object(expanded)
+}
+
+static SHARED_LOADER: LazyLock<RwLock<DocumentLoader>> =
+    LazyLock::new(|| RwLock::new(DocumentLoader::default()));
+
+/// Register a context with the shared loader, see [DocumentLoader::register]
+pub fn register_context(
+    url: &str,
+    document: Value,
+    digest: Option<&str>,
+) -> Result<(), DTGCredentialError> {
+    SHARED_LOADER
+        .write()
+        .map_err(|_| context_error("Shared document loader is poisoned"))?
+        .register(url, document, digest)
+}
+
+/// Snapshot of the shared loader, bundled contexts plus any registered with [register_context]
+pub fn document_loader() -> Result<DocumentLoader, DTGCredentialError> {
+    SHARED_LOADER
+        .read()
+        .map(|loader| loader.clone())
+        .map_err(|_| context_error("Shared document loader is poisoned"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        BUNDLED, CREDENTIALS_V1, DTG_CONTEXT_V1, DocumentLoader, context_digest, document_loader,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn test_bundled_digests() {
+        for (url, document, digest) in BUNDLED {
+            let document = serde_json::from_str(document).unwrap();
+            assert_eq!(context_digest(&document).unwrap(), digest, "{url}");
+        }
+
+        let loader = DocumentLoader::default();
+        assert_eq!(loader.urls().count(), BUNDLED.len());
+    }
+
+    #[test]
+    fn test_load_pinned() {
+        let loader = DocumentLoader::default();
+        let digest = loader.load(DTG_CONTEXT_V1).unwrap().digest().to_string();
+
+        assert!(loader.load_pinned(DTG_CONTEXT_V1, &digest).is_ok());
+        assert!(loader.load_pinned(DTG_CONTEXT_V1, "sha256:00").is_err());
+        assert!(loader.load("https://example.com/unknown/v1").is_err());
+    }
+
+    #[test]
+    fn test_register() {
+        let mut loader = DocumentLoader::empty();
+        let document = json!({"@context": {"name": "https://schema.org/name"}});
+        let digest = context_digest(&document).unwrap();
+
+        assert!(
+            loader
+                .register(
+                    "https://example.com/v1",
+                    document.clone(),
+                    Some("sha256:00")
+                )
+                .is_err()
+        );
+        assert!(
+            loader
+                .register("https://example.com/v1", document.clone(), Some(&digest))
+                .is_ok()
+        );
+        // Same document again is fine, a different one is not
+        assert!(
+            loader
+                .register("https://example.com/v1", document, None)
+                .is_ok()
+        );
+        assert!(
+            loader
+                .register(
+                    "https://example.com/v1",
+                    json!({"@context": {"name": "https://example.com/name"}}),
+                    None
+                )
+                .is_err()
+        );
+        assert!(
+            loader
+                .register("https://example.com/v2", json!({"name": "x"}), None)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_bundled_contexts_cant_be_replaced() {
+        let mut loader = DocumentLoader::default();
+        assert!(
+            loader
+                .register(
+                    DTG_CONTEXT_V1,
+                    json!({"@context": {"@vocab": "https://evil.example/"}}),
+                    None
+                )
+                .is_err()
+        );
+
+        let url = "https://example.com/contexts/shared/v1";
+        loader
+            .register(
+                url,
+                json!({"@context": {"name": "https://schema.org/name"}}),
+                None,
+            )
+            .unwrap();
+        assert!(loader.contains(url));
+        assert!(loader.contains(DTG_CONTEXT_V1));
+
+        // The shared loader starts with the bundled contexts
+        assert!(document_loader().unwrap().contains(DTG_CONTEXT_V1));
+    }
+
+    #[test]
+    fn test_context_cycle() {
+        let mut loader = DocumentLoader::empty();
+        loader
+            .register(
+                "https://example.com/a",
+                json!({"@context": ["https://example.com/a"]}),
+                None,
+            )
+            .unwrap();
+
+        assert!(loader.inline(&json!("https://example.com/a")).is_err());
+    }
+
+    #[test]
+    fn test_inline_expands_compact_iris() {
+        let loader = DocumentLoader::default();
+        let inlined = loader.inline(&json!([CREDENTIALS_V1])).unwrap();
+
+        let issuance_date = &inlined[0]["VerifiableCredential"]["@context"]["issuanceDate"];
+        assert_eq!(
+            issuance_date["@id"],
+            "https://www.w3.org/2018/credentials#issuanceDate"
+        );
+        assert_eq!(
+            issuance_date["@type"],
+            "http://www.w3.org/2001/XMLSchema#dateTime"
+        );
+    }
+}
@@ -0,0 +1,774 @@
+/*!
+*   Compact CBOR encoding and COSE_Sign1 secured DTG Credentials (`application/vc+cose`).
+*
+*   Intended for constrained transports (QR codes, BLE) where JSON plus a Data Integrity proof is
+*   too large.
+*
+*   The credential is mapped to deterministic CBOR (RFC 8949 section 4.2) using integer keys, and
+*   well known `@context` and `type` values are replaced with integer codes:
+*
+*   | Key | Credential        | Credential Subject | Witness Context |
+*   |-----|-------------------|--------------------|-----------------|
+*   | 1   | `@context`        | `id`               | `event`         |
+*   | 2   | `type`            | `endorsement`      | `sessionId`     |
+*   | 3   | `issuer`          | `card`             | `method`        |
+*   | 4   | `validFrom`       | `digest`           |                 |
+*   | 5   | `validUntil`      | `witnessContext`   |                 |
+*   | 6   | `credentialSubject` | `sequence`       |                 |
+*   | 7   | `credentialSchema` | `replaces`        |                 |
+*
+*   Timestamps are encoded as seconds since the UNIX epoch. Any Data Integrity proof is dropped,
+*   the COSE signature secures the credential instead.
+*/
+
+use crate::{
+    CredentialSubject, CredentialSubjectBasic, CredentialSubjectEndorsement,
+    CredentialSubjectRCard, CredentialSubjectWitness, DTGCommon, DTGCredential, DTGCredentialError,
+    WitnessContext,
+    crypto::Algorithm,
+    signer::{Signer, SigningKey},
+};
+use chrono::{DateTime, Utc};
+use ciborium::value::Value as Cbor;
+use serde_json::{Map, Number, Value};
+use std::fmt::Display;
+
+/// Media type of a COSE secured credential
+pub const VC_COSE_MEDIA_TYPE: &str = "application/vc+cose";
+
+/// Content type of the CBOR credential payload
+pub const DTG_CBOR_CONTENT_TYPE: &str = "application/dtg-vc+cbor";
+
+/// CBOR tag of COSE_Sign1
+const COSE_SIGN1_TAG: u64 = 18;
+
+// COSE header labels
+const HEADER_ALG: i64 = 1;
+const HEADER_CONTENT_TYPE: i64 = 3;
+const HEADER_KID: i64 = 4;
+const HEADER_TYP: i64 = 16;
+
+// Credential keys
+const CONTEXT: i64 = 1;
+const TYPE: i64 = 2;
+const ISSUER: i64 = 3;
+const VALID_FROM: i64 = 4;
+const VALID_UNTIL: i64 = 5;
+const CREDENTIAL_SUBJECT: i64 = 6;
+const CREDENTIAL_SCHEMA: i64 = 7;
+
+// Credential Subject keys
+const SUBJECT_ID: i64 = 1;
+const ENDORSEMENT: i64 = 2;
+const CARD: i64 = 3;
+const DIGEST: i64 = 4;
+const WITNESS_CONTEXT: i64 = 5;
+const SEQUENCE: i64 = 6;
+const REPLACES: i64 = 7;
+
+// Witness Context keys
+const EVENT: i64 = 1;
+const SESSION_ID: i64 = 2;
+const METHOD: i64 = 3;
+
+/// `@context` values encoded as their index
+const KNOWN_CONTEXTS: [&str; 3] = [
+    "https://www.w3.org/ns/credentials/v2",
+    "https://firstperson.network/credentials/dtg/v1",
+    "https://www.w3.org/2018/credentials/v1",
+];
+
+/// `type` values encoded as their index
+const KNOWN_TYPES: [&str; 10] = [
+    "VerifiableCredential",
+    "DTGCredential",
+    "MembershipCredential",
+    "RelationshipCredential",
+    "InvitationCredential",
+    "PersonaCredential",
+    "EndorsementCredential",
+    "WitnessCredential",
+    "RCardCredential",
+    "PersonhoodCredential",
+];
+
+fn cbor_error(msg: impl Display) -> DTGCredentialError {
+    DTGCredentialError::Cbor(msg.to_string())
+}
+
+fn encode(value: &Cbor) -> Result<Vec<u8>, DTGCredentialError> {
+    let mut bytes = Vec::new();
+    ciborium::ser::into_writer(value, &mut bytes).map_err(cbor_error)?;
+    Ok(bytes)
+}
+
+fn decode(bytes: &[u8]) -> Result<Cbor, DTGCredentialError> {
+    ciborium::de::from_reader(bytes).map_err(cbor_error)
+}
+
+/// Map with integer keys, entries must be supplied in ascending key order
+fn int_map(entries: Vec<(i64, Cbor)>) -> Cbor {
+    Cbor::Map(
+        entries
+            .into_iter()
+            .map(|(key, value)| (Cbor::Integer(key.into()), value))
+            .collect(),
+    )
+}
+
+/// Reads an integer keyed map, rejecting unknown keys
+fn read_int_map(value: &Cbor, known: &[i64]) -> Result<Vec<(i64, Cbor)>, DTGCredentialError> {
+    let Cbor::Map(entries) = value else {
+        return Err(cbor_error("Expected a map"));
+    };
+
+    entries
+        .iter()
+        .map(|(key, value)| {
+            let key = key
+                .as_integer()
+                .and_then(|key| i64::try_from(key).ok())
+                .filter(|key| known.contains(key))
+                .ok_or_else(|| cbor_error(format!("Unexpected map key {key:?}")))?;
+            Ok((key, value.clone()))
+        })
+        .collect()
+}
+
+fn get(entries: &[(i64, Cbor)], key: i64) -> Option<&Cbor> {
+    entries.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+}
+
+fn text(value: Option<&Cbor>) -> Result<Option<String>, DTGCredentialError> {
+    match value {
+        None => Ok(None),
+        Some(Cbor::Text(text)) => Ok(Some(text.clone())),
+        Some(_) => Err(cbor_error("Expected a text string")),
+    }
+}
+
+fn required_text(value: Option<&Cbor>, name: &str) -> Result<String, DTGCredentialError> {
+    text(value)?.ok_or_else(|| cbor_error(format!("Missing {name}")))
+}
+
+fn encode_timestamp(timestamp: &DateTime<Utc>) -> Cbor {
+    Cbor::Integer(timestamp.timestamp().into())
+}
+
+fn decode_timestamp(value: Option<&Cbor>) -> Result<Option<DateTime<Utc>>, DTGCredentialError> {
+    match value {
+        None => Ok(None),
+        Some(value) => value
+            .as_integer()
+            .and_then(|ts| i64::try_from(ts).ok())
+            .and_then(|ts| DateTime::from_timestamp(ts, 0))
+            .map(Some)
+            .ok_or_else(|| cbor_error("Invalid timestamp")),
+    }
+}
+
+/// Encodes known values as their index, otherwise as text
+fn encode_codes(values: &[String], known: &[&str]) -> Cbor {
+    Cbor::Array(
+        values
+            .iter()
+            .map(|value| match known.iter().position(|k| k == value) {
+                Some(index) => Cbor::Integer((index as i64).into()),
+                None => Cbor::Text(value.clone()),
+            })
+            .collect(),
+    )
+}
+
+fn decode_codes(value: Option<&Cbor>, known: &[&str]) -> Result<Vec<String>, DTGCredentialError> {
+    let Some(Cbor::Array(values)) = value else {
+        return Err(cbor_error("Expected an array"));
+    };
+
+    values
+        .iter()
+        .map(|value| match value {
+            Cbor::Text(text) => Ok(text.clone()),
+            Cbor::Integer(index) => usize::try_from(i128::from(*index))
+                .ok()
+                .and_then(|index| known.get(index))
+                .map(|known| known.to_string())
+                .ok_or_else(|| cbor_error("Unknown code")),
+            _ => Err(cbor_error("Expected a text string or code")),
+        })
+        .collect()
+}
+
+/// Converts generic JSON to CBOR, map keys are sorted by their encoded bytes
+fn json_to_cbor(value: &Value) -> Result<Cbor, DTGCredentialError> {
+    Ok(match value {
+        Value::Null => Cbor::Null,
+        Value::Bool(b) => Cbor::Bool(*b),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Cbor::Integer(i.into())
+            } else if let Some(u) = n.as_u64() {
+                Cbor::Integer(u.into())
+            } else {
+                Cbor::Float(n.as_f64().unwrap_or_default())
+            }
+        }
+        Value::String(s) => Cbor::Text(s.clone()),
+        Value::Array(items) => Cbor::Array(
+            items
+                .iter()
+                .map(json_to_cbor)
+                .collect::<Result<Vec<Cbor>, _>>()?,
+        ),
+        Value::Object(items) => {
+            let mut entries = items
+                .iter()
+                .map(|(key, value)| {
+                    let key = Cbor::Text(key.clone());
+                    Ok((encode(&key)?, key, json_to_cbor(value)?))
+                })
+                .collect::<Result<Vec<(Vec<u8>, Cbor, Cbor)>, DTGCredentialError>>()?;
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            Cbor::Map(entries.into_iter().map(|(_, k, v)| (k, v)).collect())
+        }
+    })
+}
+
+fn cbor_to_json(value: &Cbor) -> Result<Value, DTGCredentialError> {
+    Ok(match value {
+        Cbor::Null => Value::Null,
+        Cbor::Bool(b) => Value::Bool(*b),
+        Cbor::Integer(i) => {
+            let i = i128::from(*i);
+            if let Ok(i) = i64::try_from(i) {
+                Value::Number(i.into())
+            } else {
+                Value::Number(
+                    u64::try_from(i)
+                        .map_err(|_| cbor_error("Integer out of range"))?
+                        .into(),
+                )
+            }
+        }
+        Cbor::Float(f) => {
+            Value::Number(Number::from_f64(*f).ok_or_else(|| cbor_error("Non-finite float"))?)
+        }
+        Cbor::Text(s) => Value::String(s.clone()),
+        Cbor::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(cbor_to_json)
+                .collect::<Result<Vec<Value>, _>>()?,
+        ),
+        Cbor::Map(entries) => {
+            let mut map = Map::new();
+            for (key, value) in entries {
+                let Cbor::Text(key) = key else {
+                    return Err(cbor_error("Map keys must be text strings"));
+                };
+                map.insert(key.clone(), cbor_to_json(value)?);
+            }
+            Value::Object(map)
+        }
+        _ => return Err(cbor_error("Unsupported CBOR type")),
+    })
+}
+
+fn encode_subject(subject: &CredentialSubject) -> Result<Cbor, DTGCredentialError> {
+    Ok(match subject {
+        CredentialSubject::Basic(subject) => {
+            int_map(vec![(SUBJECT_ID, Cbor::Text(subject.id.clone()))])
+        }
+        CredentialSubject::Endorsement(subject) => int_map(vec![
+            (SUBJECT_ID, Cbor::Text(subject.id.clone())),
+            (ENDORSEMENT, json_to_cbor(&subject.endorsement)?),
+        ]),
+        CredentialSubject::RCard(subject) => {
+            let mut entries = vec![
+                (SUBJECT_ID, Cbor::Text(subject.id.clone())),
+                (CARD, json_to_cbor(&subject.card)?),
+            ];
+            if let Some(sequence) = subject.sequence {
+                entries.push((SEQUENCE, Cbor::Integer(sequence.into())));
+            }
+            if let Some(replaces) = &subject.replaces {
+                entries.push((REPLACES, Cbor::Text(replaces.clone())));
+            }
+            int_map(entries)
+        }
+        CredentialSubject::Witness(subject) => {
+            let mut entries = vec![(SUBJECT_ID, Cbor::Text(subject.id.clone()))];
+            if let Some(digest) = &subject.digest {
+                entries.push((DIGEST, Cbor::Text(digest.clone())));
+            }
+            if let Some(context) = &subject.witness_context {
+                let mut context_entries = Vec::new();
+                for (key, value) in [
+                    (EVENT, &context.event),
+                    (SESSION_ID, &context.session_id),
+                    (METHOD, &context.method),
+                ] {
+                    if let Some(value) = value {
+                        context_entries.push((key, Cbor::Text(value.clone())));
+                    }
+                }
+                entries.push((WITNESS_CONTEXT, int_map(context_entries)));
+            }
+            int_map(entries)
+        }
+    })
+}
+
+fn decode_subject(value: Option<&Cbor>) -> Result<CredentialSubject, DTGCredentialError> {
+    let entries = read_int_map(
+        value.ok_or_else(|| cbor_error("Missing credentialSubject"))?,
+        &[
+            SUBJECT_ID,
+            ENDORSEMENT,
+            CARD,
+            DIGEST,
+            WITNESS_CONTEXT,
+            SEQUENCE,
+            REPLACES,
+        ],
+    )?;
+    let id = required_text(get(&entries, SUBJECT_ID), "credentialSubject id")?;
+
+    if let Some(endorsement) = get(&entries, ENDORSEMENT) {
+        return Ok(CredentialSubject::Endorsement(
+            CredentialSubjectEndorsement {
+                id,
+                endorsement: cbor_to_json(endorsement)?,
+            },
+        ));
+    }
+    if let Some(card) = get(&entries, CARD) {
+        return Ok(CredentialSubject::RCard(CredentialSubjectRCard {
+            id,
+            card: cbor_to_json(card)?,
+            sequence: match get(&entries, SEQUENCE) {
+                Some(sequence) => Some(
+                    sequence
+                        .as_integer()
+                        .and_then(|s| u64::try_from(s).ok())
+                        .ok_or_else(|| cbor_error("Invalid sequence"))?,
+                ),
+                None => None,
+            },
+            replaces: text(get(&entries, REPLACES))?,
+        }));
+    }
+
+    let digest = text(get(&entries, DIGEST))?;
+    let witness_context = match get(&entries, WITNESS_CONTEXT) {
+        Some(context) => {
+            let context = read_int_map(context, &[EVENT, SESSION_ID, METHOD])?;
+            Some(WitnessContext {
+                event: text(get(&context, EVENT))?,
+                session_id: text(get(&context, SESSION_ID))?,
+                method: text(get(&context, METHOD))?,
+            })
+        }
+        None => None,
+    };
+
+    if digest.is_some() || witness_context.is_some() {
+        Ok(CredentialSubject::Witness(CredentialSubjectWitness {
+            id,
+            digest,
+            witness_context,
+        }))
+    } else {
+        Ok(CredentialSubject::Basic(CredentialSubjectBasic { id }))
+    }
+}
+
+/// Builds the COSE Sig_structure for COSE_Sign1
+fn sig_structure(protected: &[u8], payload: &[u8]) -> Result<Vec<u8>, DTGCredentialError> {
+    encode(&Cbor::Array(vec![
+        Cbor::Text("Signature1".to_string()),
+        Cbor::Bytes(protected.to_vec()),
+        Cbor::Bytes(Vec::new()),
+        Cbor::Bytes(payload.to_vec()),
+    ]))
+}
+
+/// Decoded COSE_Sign1 parts
+struct CoseSign1 {
+    protected: Vec<u8>,
+    unprotected: Vec<(Cbor, Cbor)>,
+    payload: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl CoseSign1 {
+    fn decode(bytes: &[u8]) -> Result<Self, DTGCredentialError> {
+        let value = match decode(bytes)? {
+            Cbor::Tag(COSE_SIGN1_TAG, value) => *value,
+            _ => return Err(DTGCredentialError::Cose("Expected COSE_Sign1".to_string())),
+        };
+
+        match value {
+            Cbor::Array(items) => match <[Cbor; 4]>::try_from(items) {
+                Ok(
+                    [
+                        Cbor::Bytes(protected),
+                        Cbor::Map(unprotected),
+                        Cbor::Bytes(payload),
+                        Cbor::Bytes(signature),
+                    ],
+                ) => Ok(CoseSign1 {
+                    protected,
+                    unprotected,
+                    payload,
+                    signature,
+                }),
+                _ => Err(DTGCredentialError::Cose(
+                    "Malformed COSE_Sign1 structure".to_string(),
+                )),
+            },
+            _ => Err(DTGCredentialError::Cose(
+                "Malformed COSE_Sign1 structure".to_string(),
+            )),
+        }
+    }
+
+    fn protected_header(&self, label: i64) -> Result<Option<Cbor>, DTGCredentialError> {
+        let Cbor::Map(entries) = decode(&self.protected)? else {
+            return Err(DTGCredentialError::Cose(
+                "Protected header must be a map".to_string(),
+            ));
+        };
+        Ok(entries
+            .into_iter()
+            .find(|(key, _)| key.as_integer() == Some(label.into()))
+            .map(|(_, value)| value))
+    }
+}
+
+impl DTGCredential {
+    /// Encode the credential as deterministic CBOR
+    /// Any Data Integrity proof is not included, members the crate doesn't model can't be encoded
+    pub fn to_cbor(&self) -> Result<Vec<u8>, DTGCredentialError> {
+        let credential = &self.credential;
+        if let Some(member) = credential.extra.keys().next() {
+            return Err(cbor_error(format!("Member {member} has no CBOR encoding")));
+        }
+
+        let mut entries = vec![
+            (CONTEXT, encode_codes(&credential.context, &KNOWN_CONTEXTS)),
+            (TYPE, encode_codes(&credential.type_, &KNOWN_TYPES)),
+            (ISSUER, Cbor::Text(credential.issuer.clone())),
+            (VALID_FROM, encode_timestamp(&credential.valid_from)),
+        ];
+        if let Some(valid_until) = &credential.valid_until {
+            entries.push((VALID_UNTIL, encode_timestamp(valid_until)));
+        }
+        entries.push((
+            CREDENTIAL_SUBJECT,
+            encode_subject(&credential.credential_subject)?,
+        ));
+        if let Some(schemas) = &credential.credential_schema {
+            entries.push((
+                CREDENTIAL_SCHEMA,
+                json_to_cbor(&serde_json::to_value(schemas).map_err(cbor_error)?)?,
+            ));
+        }
+
+        encode(&int_map(entries))
+    }
+
+    /// Decode a credential created by [DTGCredential::to_cbor]
+    pub fn from_cbor(bytes: &[u8]) -> Result<DTGCredential, DTGCredentialError> {
+        let entries = read_int_map(
+            &decode(bytes)?,
+            &[
+                CONTEXT,
+                TYPE,
+                ISSUER,
+                VALID_FROM,
+                VALID_UNTIL,
+                CREDENTIAL_SUBJECT,
+                CREDENTIAL_SCHEMA,
+            ],
+        )?;
+
+        DTGCommon {
+            context: decode_codes(get(&entries, CONTEXT), &KNOWN_CONTEXTS)?,
+            type_: decode_codes(get(&entries, TYPE), &KNOWN_TYPES)?,
+            issuer: required_text(get(&entries, ISSUER), "issuer")?,
+            valid_from: decode_timestamp(get(&entries, VALID_FROM))?
+                .ok_or_else(|| cbor_error("Missing validFrom"))?,
+            valid_until: decode_timestamp(get(&entries, VALID_UNTIL))?,
+            credential_subject: decode_subject(get(&entries, CREDENTIAL_SUBJECT))?,
+            credential_schema: match get(&entries, CREDENTIAL_SCHEMA) {
+                Some(schemas) => {
+                    Some(serde_json::from_value(cbor_to_json(schemas)?).map_err(cbor_error)?)
+                }
+                None => None,
+            },
+            proof: None,
+            extra: Map::new(),
+        }
+        .try_into()
+    }
+
+    /// Secure the credential as COSE_Sign1 over the CBOR encoded credential
+    /// signing_secret: The key to sign with, see [SigningKey]
+    pub fn to_vc_cose(
+        &self,
+        signing_secret: &impl SigningKey,
+    ) -> Result<Vec<u8>, DTGCredentialError> {
+        let signer = signing_secret.signer()?;
+        let algorithm = signer.algorithm();
+
+        let protected = encode(&int_map(vec![
+            (HEADER_ALG, Cbor::Integer(algorithm.cose_id().into())),
+            (
+                HEADER_CONTENT_TYPE,
+                Cbor::Text(DTG_CBOR_CONTENT_TYPE.to_string()),
+            ),
+            (HEADER_TYP, Cbor::Text(VC_COSE_MEDIA_TYPE.to_string())),
+        ]))?;
+        let payload = self.to_cbor()?;
+
+        let signature = signer.sign(&sig_structure(&protected, &payload)?)?;
+
+        encode(&Cbor::Tag(
+            COSE_SIGN1_TAG,
+            Box::new(Cbor::Array(vec![
+                Cbor::Bytes(protected),
+                int_map(vec![(
+                    HEADER_KID,
+                    Cbor::Bytes(signer.verification_method().into_bytes()),
+                )]),
+                Cbor::Bytes(payload),
+                Cbor::Bytes(signature),
+            ])),
+        ))
+    }
+
+    /// Verify a COSE secured credential and return the DTG Credential it contains
+    /// public_key_bytes: The public key bytes of the issuer
+    pub fn from_vc_cose(
+        bytes: &[u8],
+        public_key_bytes: &[u8],
+    ) -> Result<DTGCredential, DTGCredentialError> {
+        let cose = CoseSign1::decode(bytes)?;
+
+        if cose.protected_header(HEADER_CONTENT_TYPE)?
+            != Some(Cbor::Text(DTG_CBOR_CONTENT_TYPE.to_string()))
+        {
+            return Err(DTGCredentialError::Cose(
+                "Unexpected content type".to_string(),
+            ));
+        }
+
+        let algorithm = cose
+            .protected_header(HEADER_ALG)?
+            .and_then(|alg| alg.as_integer())
+            .and_then(|alg| i64::try_from(alg).ok())
+            .ok_or_else(|| DTGCredentialError::Cose("Missing alg header".to_string()))?;
+        Algorithm::from_cose_id(algorithm)
+            .and_then(|algorithm| {
+                algorithm.verify(
+                    public_key_bytes,
+                    &sig_structure(&cose.protected, &cose.payload).map_err(|e| e.to_string())?,
+                    &cose.signature,
+                )
+            })
+            .map_err(DTGCredentialError::Cose)?;
+
+        DTGCredential::from_cbor(&cose.payload)
+    }
+
+    /// Returns the `kid` header of a COSE secured credential without verifying it
+    /// Use this to find the issuer key before calling [DTGCredential::from_vc_cose]
+    pub fn vc_cose_key_id(bytes: &[u8]) -> Result<Option<String>, DTGCredentialError> {
+        let cose = CoseSign1::decode(bytes)?;
+        let kid = match cose.protected_header(HEADER_KID)? {
+            Some(kid) => Some(kid),
+            None => cose
+                .unprotected
+                .into_iter()
+                .find(|(key, _)| key.as_integer() == Some(HEADER_KID.into()))
+                .map(|(_, value)| value),
+        };
+
+        match kid {
+            Some(Cbor::Bytes(kid)) => {
+                Ok(Some(String::from_utf8(kid).map_err(|_| {
+                    DTGCredentialError::Cose("Invalid kid".to_string())
+                })?))
+            }
+            Some(_) => Err(DTGCredentialError::Cose("Invalid kid".to_string())),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "affinidi-signing"))]
+mod tests {
+    use super::{Cbor, decode, encode};
+    use crate::{CredentialSubject, DTGCredential, DTGCredentialType, WitnessContext};
+    use affinidi_secrets_resolver::secrets::Secret;
+    use chrono::{DateTime, Utc};
+    use serde_json::json;
+
+    fn valid_from() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2025-12-11T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn round_trip(credential: &DTGCredential) -> DTGCredential {
+        let decoded = DTGCredential::from_cbor(&credential.to_cbor().unwrap()).unwrap();
+        assert_eq!(
+            serde_json::to_value(credential).unwrap(),
+            serde_json::to_value(&decoded).unwrap()
+        );
+        decoded
+    }
+
+    #[test]
+    fn test_round_trip_all_subjects() {
+        let vmc = DTGCredential::new_vmc(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            valid_from(),
+            Some(valid_from() + chrono::Duration::days(365)),
+            true,
+        );
+        assert!(round_trip(&vmc).is_personhood_credential());
+
+        let vec = DTGCredential::new_vec(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            valid_from(),
+            None,
+            json!({"type": "SkillEndorsement", "level": 5, "score": 0.5, "tags": ["a", null, true]}),
+        );
+        assert!(matches!(
+            round_trip(&vec).type_(),
+            DTGCredentialType::Endorsement
+        ));
+
+        let vwc = DTGCredential::new_vwc(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            valid_from(),
+            None,
+            Some("sha256:test1234".to_string()),
+            Some(WitnessContext {
+                event: Some("EthDenver 2024".to_string()),
+                session_id: None,
+                method: Some("in-person-proximity".to_string()),
+            }),
+        );
+        assert!(matches!(
+            round_trip(&vwc).credential().credential_subject,
+            CredentialSubject::Witness(_)
+        ));
+
+        let rcard = DTGCredential::new_rcard(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            valid_from(),
+            None,
+            json!(["vcard", [["fn", {}, "text", "Alice Smith"]]]),
+        );
+        round_trip(&rcard);
+    }
+
+    #[test]
+    fn test_deterministic_encoding() {
+        let vec1 = DTGCredential::new_vec(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            valid_from(),
+            None,
+            json!({"b": 1, "a": 2, "aa": 3}),
+        );
+        let vec2 = DTGCredential::new_vec(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            valid_from(),
+            None,
+            json!({"aa": 3, "b": 1, "a": 2}),
+        );
+
+        assert_eq!(vec1.to_cbor().unwrap(), vec2.to_cbor().unwrap());
+        // Smaller than JSON
+        assert!(vec1.to_cbor().unwrap().len() < serde_json::to_vec(&vec1).unwrap().len() / 2);
+    }
+
+    #[test]
+    fn test_cose_round_trip() {
+        let secret = Secret::generate_ed25519(Some("did:example:issuer#key-1"), None);
+        let vic = DTGCredential::new_vic(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            valid_from(),
+            None,
+        );
+
+        let cose = vic.to_vc_cose(&secret).unwrap();
+        assert_eq!(
+            DTGCredential::vc_cose_key_id(&cose).unwrap().as_deref(),
+            Some("did:example:issuer#key-1")
+        );
+
+        let decoded = DTGCredential::from_vc_cose(&cose, secret.get_public_bytes()).unwrap();
+        assert!(matches!(decoded.type_(), DTGCredentialType::Invitation));
+        assert_eq!(decoded.valid_from(), valid_from());
+
+        let secret2 = Secret::generate_ed25519(None, None);
+        assert!(DTGCredential::from_vc_cose(&cose, secret2.get_public_bytes()).is_err());
+
+        // Swap the payload for another credential, then flip a signature bit
+        let Cbor::Tag(tag, sign1) = decode(&cose).unwrap() else {
+            panic!("Not a COSE_Sign1");
+        };
+        let Cbor::Array(mut parts) = *sign1 else {
+            panic!("Not a COSE_Sign1");
+        };
+        let forged = DTGCredential::new_vic(
+            "did:example:issuer".to_string(),
+            "did:example:mallory".to_string(),
+            valid_from(),
+            None,
+        );
+        let original = std::mem::replace(&mut parts[2], Cbor::Bytes(forged.to_cbor().unwrap()));
+        let tampered = encode(&Cbor::Tag(tag, Box::new(Cbor::Array(parts.clone())))).unwrap();
+        assert!(DTGCredential::from_vc_cose(&tampered, secret.get_public_bytes()).is_err());
+
+        // Re-encoding the untouched parts still verifies
+        parts[2] = original;
+        let reencoded = encode(&Cbor::Tag(tag, Box::new(Cbor::Array(parts.clone())))).unwrap();
+        assert!(DTGCredential::from_vc_cose(&reencoded, secret.get_public_bytes()).is_ok());
+
+        let Cbor::Bytes(signature) = &mut parts[3] else {
+            panic!("Signature must be a byte string");
+        };
+        signature[0] ^= 0x01;
+        let tampered = encode(&Cbor::Tag(tag, Box::new(Cbor::Array(parts)))).unwrap();
+        assert!(DTGCredential::from_vc_cose(&tampered, secret.get_public_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_bad_cbor() {
+        assert!(DTGCredential::from_cbor(&[0xa1, 0x18, 0x63, 0x01]).is_err());
+        assert!(DTGCredential::from_vc_cose(&[0x01], &[0; 32]).is_err());
+
+        let mut vrc = DTGCredential::new_vrc(
+            "did:example:alice".to_string(),
+            "did:example:bob".to_string(),
+            valid_from(),
+            None,
+        );
+        vrc.credential
+            .extra
+            .insert("id".to_string(), json!("urn:uuid:alice-bob"));
+        assert!(vrc.to_cbor().is_err());
+    }
+}
@@ -135,13 +135,13 @@ impl DTGCredential {
     /// subject: The DID of the subject of this credential
     /// valid_from: The datetime from which this credential is valid
     /// valid_until: Optional: The datetime this credential is valid until
-    /// endorsement: The endorsement details for this credential
+    /// endorsement: The endorsement details for this credential, see [crate::endorsement]
     pub fn new_vec(
         issuer: String,
         subject: String,
         valid_from: DateTime<Utc>,
         valid_until: Option<DateTime<Utc>>,
-        endorsement: Value,
+        endorsement: impl Into<Value>,
     ) -> Self {
         let mut vec = DTGCommon {
             issuer,
@@ -149,7 +149,7 @@ impl DTGCredential {
             valid_until,
             credential_subject: CredentialSubject::Endorsement(CredentialSubjectEndorsement {
                 id: subject,
-                endorsement,
+                endorsement: endorsement.into(),
             }),
             ..Default::default()
         };
@@ -204,13 +204,13 @@ impl DTGCredential {
     /// subject: The DID of the subject of this credential
     /// valid_from: The datetime from which this credential is valid
     /// valid_until: Optional: The datetime this credential is valid until
-    /// card: JSON Value representing a Jcard (RFC 7095) format
+    /// card: Jcard (RFC 7095), either a [crate::jcard::JCard] or a raw JSON Value
     pub fn new_rcard(
         issuer: String,
         subject: String,
         valid_from: DateTime<Utc>,
         valid_until: Option<DateTime<Utc>>,
-        card: Value,
+        card: impl Into<Value>,
     ) -> Self {
         let mut rcard = DTGCommon {
             issuer,
@@ -218,7 +218,9 @@ impl DTGCredential {
             valid_until,
             credential_subject: CredentialSubject::RCard(CredentialSubjectRCard {
                 id: subject,
-                card,
+                card: card.into(),
+                sequence: None,
+                replaces: None,
             }),
             ..Default::default()
         };
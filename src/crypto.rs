@@ -0,0 +1,236 @@
+/*!
+*   Signature algorithms shared by the JOSE, COSE and Data Integrity encodings.
+*/
+
+#[cfg(feature = "signing")]
+use ed25519_dalek::{Signature, VerifyingKey};
+#[cfg(any(feature = "rust-crypto", feature = "affinidi-signing"))]
+use ed25519_dalek::{Signer, SigningKey};
+use sha2::{Digest, Sha256, Sha384};
+
+/// Supported signature algorithms
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Algorithm {
+    /// Ed25519
+    EdDSA,
+
+    /// ECDSA using P-256 and SHA-256
+    ES256,
+
+    /// ECDSA using P-384 and SHA-384
+    ES384,
+}
+
+impl Algorithm {
+    /// Name of the key type used with this algorithm
+    pub fn key_type(&self) -> &'static str {
+        match self {
+            Algorithm::EdDSA => "Ed25519",
+            Algorithm::ES256 => "P-256",
+            Algorithm::ES384 => "P-384",
+        }
+    }
+
+    /// ECDSA algorithm matching the SEC1 encoded public key
+    #[cfg(feature = "signing")]
+    pub(crate) fn for_ecdsa_public_key(public_key: &[u8]) -> Result<Self, String> {
+        match public_key.len() {
+            33 | 65 => Ok(Algorithm::ES256),
+            49 | 97 => Ok(Algorithm::ES384),
+            _ => Err("Invalid public key bytes".to_string()),
+        }
+    }
+
+    /// JOSE `alg` identifier
+    #[cfg(feature = "signing")]
+    pub(crate) fn jose_name(&self) -> &'static str {
+        match self {
+            Algorithm::EdDSA => "EdDSA",
+            Algorithm::ES256 => "ES256",
+            Algorithm::ES384 => "ES384",
+        }
+    }
+
+    #[cfg(feature = "signing")]
+    pub(crate) fn from_jose_name(name: &str) -> Result<Self, String> {
+        match name {
+            "EdDSA" => Ok(Algorithm::EdDSA),
+            "ES256" => Ok(Algorithm::ES256),
+            "ES384" => Ok(Algorithm::ES384),
+            _ => Err(format!("Unsupported algorithm: {name}")),
+        }
+    }
+
+    /// COSE `alg` identifier
+    #[cfg(feature = "signing")]
+    pub(crate) fn cose_id(&self) -> i64 {
+        match self {
+            Algorithm::EdDSA => -8,
+            Algorithm::ES256 => -7,
+            Algorithm::ES384 => -35,
+        }
+    }
+
+    #[cfg(feature = "signing")]
+    pub(crate) fn from_cose_id(id: i64) -> Result<Self, String> {
+        match id {
+            -8 => Ok(Algorithm::EdDSA),
+            -7 => Ok(Algorithm::ES256),
+            -35 => Ok(Algorithm::ES384),
+            _ => Err(format!("Unsupported algorithm: {id}")),
+        }
+    }
+
+    /// Hash used with this algorithm by the Data Integrity cryptosuites
+    pub(crate) fn hash(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Algorithm::EdDSA | Algorithm::ES256 => Sha256::digest(data).to_vec(),
+            Algorithm::ES384 => Sha384::digest(data).to_vec(),
+        }
+    }
+
+    /// Sign with the raw private key bytes
+    #[cfg(any(feature = "rust-crypto", feature = "affinidi-signing"))]
+    pub(crate) fn sign(&self, private_key: &[u8], data: &[u8]) -> Result<Vec<u8>, String> {
+        match self {
+            Algorithm::EdDSA => {
+                let signing_key = SigningKey::from_bytes(
+                    private_key
+                        .try_into()
+                        .map_err(|_| "Invalid Ed25519 secret".to_string())?,
+                );
+                Ok(signing_key.sign(data).to_vec())
+            }
+            Algorithm::ES256 => {
+                let signing_key = p256::ecdsa::SigningKey::from_slice(private_key)
+                    .map_err(|_| "Invalid P-256 secret".to_string())?;
+                let signature: p256::ecdsa::Signature = signing_key.sign(data);
+                Ok(signature.to_vec())
+            }
+            Algorithm::ES384 => {
+                let signing_key = p384::ecdsa::SigningKey::from_slice(private_key)
+                    .map_err(|_| "Invalid P-384 secret".to_string())?;
+                let signature: p384::ecdsa::Signature = signing_key.sign(data);
+                Ok(signature.to_vec())
+            }
+        }
+    }
+
+    #[cfg(feature = "rust-crypto")]
+    /// Generate a random key pair, returns (private key, public key) bytes
+    pub(crate) fn generate_key_pair(&self) -> (Vec<u8>, Vec<u8>) {
+        use p256::elliptic_curve::sec1::ToEncodedPoint;
+        use rand::RngCore;
+
+        let mut rng = rand::rngs::OsRng;
+        match self {
+            Algorithm::EdDSA => {
+                let mut bytes = [0u8; 32];
+                rng.fill_bytes(&mut bytes);
+                let signing_key = SigningKey::from_bytes(&bytes);
+                (
+                    bytes.to_vec(),
+                    signing_key.verifying_key().to_bytes().to_vec(),
+                )
+            }
+            Algorithm::ES256 => {
+                let secret_key = p256::SecretKey::random(&mut rng);
+                (
+                    secret_key.to_bytes().to_vec(),
+                    secret_key
+                        .public_key()
+                        .to_encoded_point(false)
+                        .as_bytes()
+                        .to_vec(),
+                )
+            }
+            Algorithm::ES384 => {
+                let secret_key = p384::SecretKey::random(&mut rng);
+                (
+                    secret_key.to_bytes().to_vec(),
+                    secret_key
+                        .public_key()
+                        .to_encoded_point(false)
+                        .as_bytes()
+                        .to_vec(),
+                )
+            }
+        }
+    }
+
+    #[cfg(feature = "rust-crypto")]
+    /// Public key for the raw private key bytes
+    /// Ed25519 keys are 32 bytes, P-256/P-384 keys are SEC1 uncompressed
+    pub(crate) fn public_key(&self, private_key: &[u8]) -> Result<Vec<u8>, String> {
+        use p256::elliptic_curve::sec1::ToEncodedPoint;
+
+        match self {
+            Algorithm::EdDSA => {
+                let signing_key = SigningKey::from_bytes(
+                    private_key
+                        .try_into()
+                        .map_err(|_| "Invalid Ed25519 secret".to_string())?,
+                );
+                Ok(signing_key.verifying_key().to_bytes().to_vec())
+            }
+            Algorithm::ES256 => {
+                let secret_key = p256::SecretKey::from_slice(private_key)
+                    .map_err(|_| "Invalid P-256 secret".to_string())?;
+                Ok(secret_key
+                    .public_key()
+                    .to_encoded_point(false)
+                    .as_bytes()
+                    .to_vec())
+            }
+            Algorithm::ES384 => {
+                let secret_key = p384::SecretKey::from_slice(private_key)
+                    .map_err(|_| "Invalid P-384 secret".to_string())?;
+                Ok(secret_key
+                    .public_key()
+                    .to_encoded_point(false)
+                    .as_bytes()
+                    .to_vec())
+            }
+        }
+    }
+
+    #[cfg(feature = "signing")]
+    pub(crate) fn verify(
+        &self,
+        public_key: &[u8],
+        data: &[u8],
+        signature: &[u8],
+    ) -> Result<(), String> {
+        let invalid_key = |_| "Invalid public key bytes".to_string();
+        let invalid_signature = |_| "Invalid signature format".to_string();
+        let failed = |_| "Signature verification failed".to_string();
+
+        match self {
+            Algorithm::EdDSA => {
+                let verifying_key = VerifyingKey::try_from(public_key).map_err(invalid_key)?;
+                let signature = Signature::from_slice(signature).map_err(invalid_signature)?;
+                verifying_key
+                    .verify_strict(data, &signature)
+                    .map_err(failed)
+            }
+            Algorithm::ES256 => {
+                use p256::ecdsa::signature::Verifier;
+
+                let verifying_key =
+                    p256::ecdsa::VerifyingKey::from_sec1_bytes(public_key).map_err(invalid_key)?;
+                let signature =
+                    p256::ecdsa::Signature::from_slice(signature).map_err(invalid_signature)?;
+                verifying_key.verify(data, &signature).map_err(failed)
+            }
+            Algorithm::ES384 => {
+                use p384::ecdsa::signature::Verifier;
+
+                let verifying_key =
+                    p384::ecdsa::VerifyingKey::from_sec1_bytes(public_key).map_err(invalid_key)?;
+                let signature =
+                    p384::ecdsa::Signature::from_slice(signature).map_err(invalid_signature)?;
+                verifying_key.verify(data, &signature).map_err(failed)
+            }
+        }
+    }
+}
@@ -0,0 +1,655 @@
+/*!
+*   Digital Credentials Query Language (DCQL) for DTG Credentials.
+*
+*   <https://openid.net/specs/openid-4-verifiable-presentations-1_0.html#name-digital-credentials-query-l>
+*
+*   A verifier's [DcqlQuery] is matched against the serialized credentials:
+*   - `format` must be `ldp_vc`
+*   - `meta.type_values`: the credential `type` must contain all types of one of the alternatives
+*   - `claims`: each path selects values from the credential, `values` restricts what they may be.
+*     Paths reach into `credentialSubject.endorsement` like any other JSON. Inside an RCard jCard
+*     a string component selects the values of the properties with that name, e.g.
+*     `["credentialSubject", "card", "email"]`
+*   - `claim_sets`: the first set (in order of preference) whose claims all match is used
+*   - `credential_sets`: the first option whose credential queries are all satisfied is used,
+*     without them every credential query must be satisfied
+*
+*   A path that doesn't fit a credential, e.g. a key into an array, doesn't match it.
+*   `trusted_authorities` and `require_cryptographic_holder_binding` are not evaluated.
+*/
+
+use crate::{DTGCredential, DTGCredentialError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+fn dcql_error(msg: impl Into<String>) -> DTGCredentialError {
+    DTGCredentialError::Dcql(msg.into())
+}
+
+/// Format identifier of DTG Credentials
+const FORMAT: &str = "ldp_vc";
+
+/// What a verifier asks to be presented
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DcqlQuery {
+    pub credentials: Vec<CredentialQuery>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub credential_sets: Vec<CredentialSetQuery>,
+}
+
+/// A credential the verifier asks for
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CredentialQuery {
+    pub id: String,
+
+    pub format: String,
+
+    /// More than one credential may be presented for this query
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub multiple: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub meta: Option<CredentialQueryMeta>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub trusted_authorities: Option<Value>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub require_cryptographic_holder_binding: Option<bool>,
+
+    /// Claims requested, all claims of the credential if empty
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub claims: Vec<ClaimsQuery>,
+
+    /// Alternative sets of claim ids, in order of preference
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub claim_sets: Vec<Vec<String>>,
+}
+
+/// Format specific constraints, only `type_values` applies to `ldp_vc`
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CredentialQueryMeta {
+    /// Alternative sets of types, the credential must have all types of one of them
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub type_values: Option<Vec<Vec<String>>>,
+}
+
+/// A claim the verifier asks for
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClaimsQuery {
+    /// Required when the credential query has claim_sets
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub id: Option<String>,
+
+    pub path: Vec<ClaimPathElement>,
+
+    /// Accepted values, any value if empty
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub values: Vec<Value>,
+}
+
+/// Component of a claims path
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ClaimPathElement {
+    /// Key of an object, or jCard property name
+    Key(String),
+    /// Index into an array
+    Index(usize),
+    /// All elements of an array (null)
+    Wildcard,
+}
+
+/// Alternative combinations of credential queries
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CredentialSetQuery {
+    /// Lists of credential query ids, in order of preference
+    pub options: Vec<Vec<String>>,
+
+    #[serde(default = "default_required")]
+    pub required: bool,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+/// A claim satisfied by a credential
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClaimMatch {
+    pub id: Option<String>,
+    pub path: Vec<ClaimPathElement>,
+    /// Values selected by the path
+    pub values: Vec<Value>,
+}
+
+/// A credential satisfying a credential query
+#[derive(Clone, Debug, PartialEq)]
+pub struct CredentialMatch {
+    /// Index of the credential
+    pub index: usize,
+    /// Claims to disclose, empty if the query asks for all claims
+    pub claims: Vec<ClaimMatch>,
+}
+
+/// Credentials matching a credential query
+#[derive(Clone, Debug)]
+pub struct CredentialQueryMatch {
+    pub id: String,
+    pub credentials: Vec<CredentialMatch>,
+}
+
+/// Result of a credential set
+#[derive(Clone, Debug)]
+pub struct CredentialSetMatch {
+    pub required: bool,
+    /// First option that can be satisfied
+    pub option: Option<Vec<String>>,
+}
+
+/// Result of matching a [DcqlQuery]
+#[derive(Clone, Debug)]
+pub struct DcqlMatch {
+    /// Per credential query, in query order
+    pub credential_queries: Vec<CredentialQueryMatch>,
+    /// Per credential set, in query order
+    pub credential_sets: Vec<CredentialSetMatch>,
+}
+
+impl DcqlMatch {
+    /// Credentials matching a credential query
+    pub fn matches_for(&self, id: &str) -> &[CredentialMatch] {
+        self.credential_queries
+            .iter()
+            .find(|query| query.id == id)
+            .map(|query| query.credentials.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Can the query be satisfied?
+    pub fn is_satisfied(&self) -> bool {
+        if self.credential_sets.is_empty() {
+            self.credential_queries
+                .iter()
+                .all(|query| !query.credentials.is_empty())
+        } else {
+            self.credential_sets
+                .iter()
+                .all(|set| !set.required || set.option.is_some())
+        }
+    }
+
+    /// Credentials to present per credential query id, None if the query can't be satisfied
+    /// The first matching credential is presented unless the credential query allows multiple
+    pub fn selected(&self, query: &DcqlQuery) -> Option<Vec<(String, Vec<CredentialMatch>)>> {
+        if !self.is_satisfied() {
+            return None;
+        }
+
+        let ids: BTreeSet<&str> = if self.credential_sets.is_empty() {
+            self.credential_queries
+                .iter()
+                .map(|query| query.id.as_str())
+                .collect()
+        } else {
+            self.credential_sets
+                .iter()
+                .filter_map(|set| set.option.as_ref())
+                .flatten()
+                .map(String::as_str)
+                .collect()
+        };
+
+        Some(
+            query
+                .credentials
+                .iter()
+                .filter(|credential_query| ids.contains(credential_query.id.as_str()))
+                .map(|credential_query| {
+                    let matches = self.matches_for(&credential_query.id);
+                    let take = if credential_query.multiple {
+                        matches.len()
+                    } else {
+                        1
+                    };
+                    (
+                        credential_query.id.clone(),
+                        matches.iter().take(take).cloned().collect(),
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Query ids are non-empty and only use alphanumerics, underscore and hyphen
+fn valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Is this a jCard, `["vcard", [properties]]`?
+fn is_jcard(value: &Value) -> bool {
+    value
+        .as_array()
+        .is_some_and(|items| items.len() == 2 && items[0] == "vcard" && items[1].is_array())
+}
+
+/// Values selected by a claims path, empty if the path doesn't fit the credential
+pub fn select_claim(credential: &Value, path: &[ClaimPathElement]) -> Vec<Value> {
+    let mut selected = vec![credential];
+    for component in path {
+        let mut next = Vec::new();
+        for value in selected {
+            match (component, value) {
+                (ClaimPathElement::Key(key), Value::Object(object)) => next.extend(object.get(key)),
+                (ClaimPathElement::Key(key), value) if is_jcard(value) => {
+                    // Values of the jCard properties with this name
+                    for property in value[1].as_array().into_iter().flatten() {
+                        let Some(items) = property.as_array() else {
+                            continue;
+                        };
+                        if items.len() >= 4 && items[0] == key.to_lowercase().as_str() {
+                            next.extend(&items[3..]);
+                        }
+                    }
+                }
+                (ClaimPathElement::Index(index), Value::Array(items)) => {
+                    next.extend(items.get(*index))
+                }
+                (ClaimPathElement::Wildcard, Value::Array(items)) => next.extend(items),
+                _ => return Vec::new(),
+            }
+        }
+        selected = next;
+    }
+    selected.into_iter().cloned().collect()
+}
+
+impl DcqlQuery {
+    /// Parses and validates a DCQL query
+    pub fn parse(query: &str) -> Result<Self, DTGCredentialError> {
+        let query: DcqlQuery = serde_json::from_str(query)
+            .map_err(|e| dcql_error(format!("Couldn't parse query: {e}")))?;
+        query.validate()?;
+        Ok(query)
+    }
+
+    /// Checks the query is well formed
+    pub fn validate(&self) -> Result<(), DTGCredentialError> {
+        if self.credentials.is_empty() {
+            return Err(dcql_error(
+                "Query must contain at least one credential query",
+            ));
+        }
+
+        let mut ids = BTreeSet::new();
+        for credential in &self.credentials {
+            if !valid_id(&credential.id) {
+                return Err(dcql_error(format!(
+                    "Invalid credential query id: {}",
+                    credential.id
+                )));
+            }
+            if !ids.insert(credential.id.as_str()) {
+                return Err(dcql_error(format!(
+                    "Duplicate credential query id: {}",
+                    credential.id
+                )));
+            }
+
+            let mut claim_ids = BTreeSet::new();
+            for claim in &credential.claims {
+                if claim.path.is_empty() {
+                    return Err(dcql_error(format!(
+                        "Empty claims path in credential query {}",
+                        credential.id
+                    )));
+                }
+                if let Some(value) = claim.values.iter().find(|value| {
+                    !(value.is_string() || value.is_i64() || value.is_u64() || value.is_boolean())
+                }) {
+                    return Err(dcql_error(format!(
+                        "Claim values must be strings, integers or booleans: {value}"
+                    )));
+                }
+                match &claim.id {
+                    Some(id) if !valid_id(id) || !claim_ids.insert(id.as_str()) => {
+                        return Err(dcql_error(format!(
+                            "Invalid or duplicate claim id {id} in credential query {}",
+                            credential.id
+                        )));
+                    }
+                    None if !credential.claim_sets.is_empty() => {
+                        return Err(dcql_error(format!(
+                            "Claims of credential query {} need ids for claim_sets",
+                            credential.id
+                        )));
+                    }
+                    _ => {}
+                }
+            }
+
+            if !credential.claim_sets.is_empty() && credential.claims.is_empty() {
+                return Err(dcql_error(format!(
+                    "claim_sets without claims in credential query {}",
+                    credential.id
+                )));
+            }
+            for id in credential.claim_sets.iter().flatten() {
+                if !claim_ids.contains(id.as_str()) {
+                    return Err(dcql_error(format!(
+                        "Unknown claim id {id} in claim_sets of credential query {}",
+                        credential.id
+                    )));
+                }
+            }
+        }
+
+        for set in &self.credential_sets {
+            if set.options.is_empty() || set.options.iter().any(Vec::is_empty) {
+                return Err(dcql_error("Credential set options must not be empty"));
+            }
+            if let Some(id) = set
+                .options
+                .iter()
+                .flatten()
+                .find(|id| !ids.contains(id.as_str()))
+            {
+                return Err(dcql_error(format!(
+                    "Unknown credential query id {id} in credential_sets"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Matches the credentials against each credential query and credential set
+    pub fn evaluate(&self, credentials: &[DTGCredential]) -> Result<DcqlMatch, DTGCredentialError> {
+        self.validate()?;
+
+        let values = credentials
+            .iter()
+            .map(|credential| {
+                serde_json::to_value(credential)
+                    .map_err(|e| dcql_error(format!("Couldn't serialize credential: {e}")))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let credential_queries: Vec<CredentialQueryMatch> = self
+            .credentials
+            .iter()
+            .map(|query| CredentialQueryMatch {
+                id: query.id.clone(),
+                credentials: values
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, value)| {
+                        query
+                            .matches(value)
+                            .map(|claims| CredentialMatch { index, claims })
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let satisfied: BTreeSet<&str> = credential_queries
+            .iter()
+            .filter(|query| !query.credentials.is_empty())
+            .map(|query| query.id.as_str())
+            .collect();
+        let credential_sets = self
+            .credential_sets
+            .iter()
+            .map(|set| CredentialSetMatch {
+                required: set.required,
+                option: set
+                    .options
+                    .iter()
+                    .find(|option| option.iter().all(|id| satisfied.contains(id.as_str())))
+                    .cloned(),
+            })
+            .collect();
+
+        Ok(DcqlMatch {
+            credential_queries,
+            credential_sets,
+        })
+    }
+}
+
+impl CredentialQuery {
+    /// Claims to disclose if the credential satisfies this query
+    fn matches(&self, credential: &Value) -> Option<Vec<ClaimMatch>> {
+        if self.format != FORMAT {
+            return None;
+        }
+
+        if let Some(type_values) = self
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.type_values.as_ref())
+        {
+            let types: Vec<&str> = credential["type"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .collect();
+            if !type_values
+                .iter()
+                .any(|alternative| alternative.iter().all(|t| types.contains(&t.as_str())))
+            {
+                return None;
+            }
+        }
+
+        let claims: Vec<Option<ClaimMatch>> = self
+            .claims
+            .iter()
+            .map(|claim| claim.matches(credential))
+            .collect();
+
+        if self.claim_sets.is_empty() {
+            return claims.into_iter().collect();
+        }
+
+        self.claim_sets.iter().find_map(|claim_set| {
+            claim_set
+                .iter()
+                .map(|id| {
+                    claims
+                        .iter()
+                        .flatten()
+                        .find(|claim| claim.id.as_deref() == Some(id))
+                        .cloned()
+                })
+                .collect()
+        })
+    }
+}
+
+impl ClaimsQuery {
+    fn matches(&self, credential: &Value) -> Option<ClaimMatch> {
+        let values: Vec<Value> = select_claim(credential, &self.path)
+            .into_iter()
+            .filter(|value| self.values.is_empty() || self.values.contains(value))
+            .collect();
+
+        (!values.is_empty()).then(|| ClaimMatch {
+            id: self.id.clone(),
+            path: self.path.clone(),
+            values,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClaimPathElement, DcqlQuery};
+    use crate::{DTGCredential, DTGCredentialError, jcard::JCard};
+    use chrono::Utc;
+    use serde_json::json;
+
+    fn credentials() -> Vec<DTGCredential> {
+        vec![
+            DTGCredential::new_vrc(
+                "did:example:bob".to_string(),
+                "did:example:alice".to_string(),
+                Utc::now(),
+                None,
+            ),
+            DTGCredential::new_vec(
+                "did:example:carol".to_string(),
+                "did:example:alice".to_string(),
+                Utc::now(),
+                None,
+                json!({"type": "SkillEndorsement", "skills": ["rust", "cryptography"]}),
+            ),
+            DTGCredential::new_rcard(
+                "did:example:alice".to_string(),
+                "did:example:alice".to_string(),
+                Utc::now(),
+                None,
+                JCard::builder("Alice Smith")
+                    .email("alice@example.com")
+                    .build(),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_claims() {
+        let query = DcqlQuery::parse(
+            &json!({
+                "credentials": [
+                    {"id": "relationship", "format": "ldp_vc",
+                     "meta": {"type_values": [["RelationshipCredential"]]},
+                     "claims": [{"path": ["issuer"], "values": ["did:example:bob"]}]},
+                    {"id": "skills", "format": "ldp_vc",
+                     "claims": [{"path": ["credentialSubject", "endorsement", "skills", null], "values": ["rust"]}]},
+                    {"id": "card", "format": "ldp_vc",
+                     "meta": {"type_values": [["RCardCredential"]]},
+                     "claims": [
+                        {"id": "name", "path": ["credentialSubject", "card", "fn"]},
+                        {"id": "email", "path": ["credentialSubject", "card", "email"]},
+                        {"id": "tel", "path": ["credentialSubject", "card", "tel"]}
+                     ],
+                     "claim_sets": [["name", "tel"], ["name", "email"]]},
+                    {"id": "jwt", "format": "jwt_vc_json"}
+                ]
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let result = query.evaluate(&credentials()).unwrap();
+        let relationship = result.matches_for("relationship");
+        assert_eq!(relationship.len(), 1);
+        assert_eq!(relationship[0].index, 0);
+        assert_eq!(relationship[0].claims[0].values, [json!("did:example:bob")]);
+
+        let skills = result.matches_for("skills");
+        assert_eq!(skills.len(), 1);
+        assert_eq!(skills[0].index, 1);
+        assert_eq!(skills[0].claims[0].values, [json!("rust")]);
+
+        // tel is missing, falls back to the second claim set
+        let card = result.matches_for("card");
+        assert_eq!(card[0].index, 2);
+        let claims: Vec<_> = card[0].claims.iter().map(|c| c.id.as_deref()).collect();
+        assert_eq!(claims, [Some("name"), Some("email")]);
+        assert_eq!(card[0].claims[1].values, [json!("alice@example.com")]);
+
+        assert!(result.matches_for("jwt").is_empty());
+        assert!(!result.is_satisfied());
+        assert!(result.selected(&query).is_none());
+    }
+
+    #[test]
+    fn test_credential_sets() {
+        let query = DcqlQuery::parse(
+            &json!({
+                "credentials": [
+                    {"id": "any", "format": "ldp_vc", "multiple": true},
+                    {"id": "persona", "format": "ldp_vc",
+                     "meta": {"type_values": [["PersonaCredential"]]}},
+                    {"id": "relationship", "format": "ldp_vc",
+                     "meta": {"type_values": [["VerifiableCredential", "RelationshipCredential"]]}}
+                ],
+                "credential_sets": [
+                    {"options": [["persona"], ["relationship"]]},
+                    {"options": [["any"]], "required": false}
+                ]
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let result = query.evaluate(&credentials()).unwrap();
+        assert!(result.is_satisfied());
+        assert_eq!(
+            result.credential_sets[0].option,
+            Some(vec!["relationship".to_string()])
+        );
+
+        let selected = result.selected(&query).unwrap();
+        assert_eq!(selected.len(), 2);
+        assert_eq!(selected[0].0, "any");
+        assert_eq!(selected[0].1.len(), 3);
+        assert!(selected[0].1[0].claims.is_empty());
+        assert_eq!(selected[1].0, "relationship");
+        assert_eq!(selected[1].1.len(), 1);
+
+        // Only the optional set can be satisfied
+        let result = query.evaluate(&credentials()[1..]).unwrap();
+        assert!(!result.is_satisfied());
+    }
+
+    #[test]
+    fn test_invalid_queries() {
+        for query in [
+            json!({"credentials": []}),
+            json!({"credentials": [{"id": "a b", "format": "ldp_vc"}]}),
+            json!({"credentials": [{"id": "a", "format": "ldp_vc"}, {"id": "a", "format": "ldp_vc"}]}),
+            json!({"credentials": [{"id": "a", "format": "ldp_vc", "claims": [{"path": []}]}]}),
+            json!({"credentials": [{"id": "a", "format": "ldp_vc",
+                "claims": [{"path": ["issuer"], "values": [{"nested": true}]}]}]}),
+            json!({"credentials": [{"id": "a", "format": "ldp_vc",
+                "claims": [{"path": ["issuer"]}], "claim_sets": [["x"]]}]}),
+            json!({"credentials": [{"id": "a", "format": "ldp_vc"}],
+                "credential_sets": [{"options": [["b"]]}]}),
+        ] {
+            assert!(matches!(
+                DcqlQuery::parse(&query.to_string()),
+                Err(DTGCredentialError::Dcql(_))
+            ));
+        }
+
+        let query: DcqlQuery = serde_json::from_value(json!({
+            "credentials": [{"id": "a", "format": "ldp_vc", "claims": [{"path": ["type", 0, null]}]}]
+        }))
+        .unwrap();
+        assert_eq!(
+            query.credentials[0].claims[0].path,
+            [
+                ClaimPathElement::Key("type".to_string()),
+                ClaimPathElement::Index(0),
+                ClaimPathElement::Wildcard
+            ]
+        );
+        // A key into a string doesn't match
+        assert!(
+            query
+                .evaluate(&credentials())
+                .unwrap()
+                .matches_for("a")
+                .is_empty()
+        );
+    }
+}
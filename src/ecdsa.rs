@@ -0,0 +1,298 @@
+/*!
+*   The `ecdsa-jcs-2019` cryptosuite for P-256 and P-384 issuers.
+*
+*   <https://www.w3.org/TR/vc-di-ecdsa/#ecdsa-jcs-2019>
+*
+*   The credential and proof configuration are canonicalized with JCS and hashed with SHA-256
+*   (P-256) or SHA-384 (P-384). The signature is the fixed size `r || s` encoding, base58btc
+*   multibase encoded in `proofValue`.
+*/
+
+#[cfg(feature = "signing")]
+use crate::proof::CryptoSuite;
+use crate::{
+    DTGCommon, DTGCredential, DTGCredentialError, crypto::Algorithm, proof::DataIntegrityProof,
+};
+#[cfg(feature = "signing")]
+use chrono::{DateTime, Utc};
+#[cfg(feature = "signing")]
+use multibase::Base;
+use serde::Serialize;
+use serde_json::Value;
+use serde_json_canonicalizer::to_string;
+
+fn ecdsa_error(msg: impl Into<String>) -> DTGCredentialError {
+    DTGCredentialError::Ecdsa(msg.into())
+}
+
+fn canonicalize(value: &impl Serialize) -> Result<String, DTGCredentialError> {
+    to_string(value).map_err(|e| ecdsa_error(format!("Couldn't canonicalize: {e}")))
+}
+
+/// hash(proofConfig) || hash(credential)
+pub(crate) fn jcs_hash_data(
+    algorithm: Algorithm,
+    credential: &DTGCredential,
+    proof: &DataIntegrityProof,
+) -> Result<Vec<u8>, DTGCredentialError> {
+    jcs_hash_document(
+        algorithm,
+        &DTGCommon {
+            proof: None,
+            ..credential.credential.clone()
+        },
+        &credential.credential.context,
+        proof,
+    )
+}
+
+/// hash(proofConfig) || hash(document)
+/// document: The unsecured document, without a proof
+/// context: `@context` of the document, copied to the proof configuration
+pub(crate) fn jcs_hash_document(
+    algorithm: Algorithm,
+    document: &impl Serialize,
+    context: &[String],
+    proof: &DataIntegrityProof,
+) -> Result<Vec<u8>, DTGCredentialError> {
+    let mut config = serde_json::to_value(DataIntegrityProof {
+        proof_value: None,
+        context: None,
+        ..proof.clone()
+    })
+    .map_err(|e| ecdsa_error(format!("Couldn't serialize proof: {e}")))?;
+    config["@context"] = Value::from(context);
+
+    let mut data = algorithm.hash(canonicalize(&config)?.as_bytes());
+    data.extend(algorithm.hash(canonicalize(document)?.as_bytes()));
+    Ok(data)
+}
+
+#[cfg(feature = "signing")]
+/// Verifies an ecdsa-jcs-2019 proof
+/// The curve is detected from the SEC1 encoded public key
+pub(crate) fn verify_proof(
+    credential: &DTGCredential,
+    proof: &DataIntegrityProof,
+    public_key_bytes: &[u8],
+) -> Result<(), DTGCredentialError> {
+    verify_document(
+        &DTGCommon {
+            proof: None,
+            ..credential.credential.clone()
+        },
+        &credential.credential.context,
+        proof,
+        "assertionMethod",
+        public_key_bytes,
+    )
+}
+
+#[cfg(feature = "signing")]
+/// Verifies an ecdsa-jcs-2019 proof of a document
+/// See [jcs_hash_document]
+/// purpose: The expected proofPurpose, `assertionMethod` for credentials
+pub(crate) fn verify_document(
+    document: &impl Serialize,
+    context: &[String],
+    proof: &DataIntegrityProof,
+    purpose: &str,
+    public_key_bytes: &[u8],
+) -> Result<(), DTGCredentialError> {
+    if proof.type_ != "DataIntegrityProof" {
+        return Err(ecdsa_error(
+            "Invalid proof type, expected 'DataIntegrityProof'",
+        ));
+    }
+    if proof.cryptosuite != CryptoSuite::EcdsaJcs2019 {
+        return Err(ecdsa_error(format!(
+            "Invalid cryptosuite {}, expected 'ecdsa-jcs-2019'",
+            proof.cryptosuite
+        )));
+    }
+    if proof.proof_purpose != purpose {
+        return Err(ecdsa_error(format!(
+            "Invalid proofPurpose {}, expected '{purpose}'",
+            proof.proof_purpose
+        )));
+    }
+    if let Some(created) = &proof.created {
+        let created = created
+            .parse::<DateTime<Utc>>()
+            .map_err(|e| ecdsa_error(format!("Invalid created date: {e}")))?;
+        if created > Utc::now() {
+            return Err(ecdsa_error("Created date is in the future"));
+        }
+    }
+
+    let Some(proof_value) = &proof.proof_value else {
+        return Err(ecdsa_error("Proof has no proofValue"));
+    };
+    let (base, signature) = multibase::decode(proof_value)
+        .map_err(|e| ecdsa_error(format!("Invalid proofValue: {e}")))?;
+    if base != Base::Base58Btc {
+        return Err(ecdsa_error("proofValue must be base58btc encoded"));
+    }
+
+    let algorithm = Algorithm::for_ecdsa_public_key(public_key_bytes).map_err(ecdsa_error)?;
+    algorithm
+        .verify(
+            public_key_bytes,
+            &jcs_hash_document(algorithm, document, context, proof)?,
+            &signature,
+        )
+        .map_err(ecdsa_error)
+}
+
+#[cfg(all(test, feature = "affinidi-signing"))]
+mod tests {
+    use crate::{
+        DTGCommon, DTGCredential, DTGCredentialError,
+        proof::{CryptoSuite, DataIntegrityProof},
+    };
+    use affinidi_secrets_resolver::secrets::Secret;
+    use chrono::{Duration, Utc};
+
+    fn vrc() -> DTGCredential {
+        DTGCredential::new_vrc(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            Utc::now(),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_sign_p256_and_p384() {
+        for secret in [
+            Secret::generate_p256(None, None).unwrap(),
+            Secret::generate_p384(None, None).unwrap(),
+        ] {
+            let mut cred = vrc();
+            let proof = cred.sign(&secret, None).unwrap();
+            assert_eq!(proof.cryptosuite, CryptoSuite::EcdsaJcs2019);
+
+            let received: DTGCredential =
+                serde_json::from_str(&serde_json::to_string(&cred).unwrap()).unwrap();
+            assert!(
+                received
+                    .verify_proof_with_public_key(secret.get_public_bytes())
+                    .is_ok()
+            );
+
+            let mut tampered = received.clone();
+            tampered.credential_mut().issuer = "did:example:other".to_string();
+            assert!(
+                tampered
+                    .verify_proof_with_public_key(secret.get_public_bytes())
+                    .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_wrong_key() {
+        let secret = Secret::generate_p256(None, None).unwrap();
+        let mut cred = vrc();
+        cred.sign(&secret, None).unwrap();
+
+        for other in [
+            Secret::generate_p256(None, None).unwrap(),
+            Secret::generate_p384(None, None).unwrap(),
+        ] {
+            assert!(
+                cred.verify_proof_with_public_key(other.get_public_bytes())
+                    .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_proof_checks() {
+        let secret = Secret::generate_p256(None, None).unwrap();
+        let mut cred = vrc();
+        let proof = cred.sign(&secret, None).unwrap();
+        let verify = |proof: &DataIntegrityProof| {
+            super::verify_proof(&cred, proof, secret.get_public_bytes())
+        };
+        assert!(verify(&proof).is_ok());
+
+        let wrong_type = DataIntegrityProof {
+            type_: "Ed25519Signature2020".to_string(),
+            ..proof.clone()
+        };
+        assert!(matches!(
+            verify(&wrong_type),
+            Err(DTGCredentialError::Ecdsa(_))
+        ));
+
+        let wrong_suite = DataIntegrityProof {
+            cryptosuite: CryptoSuite::EddsaJcs2022,
+            ..proof.clone()
+        };
+        assert!(matches!(
+            verify(&wrong_suite),
+            Err(DTGCredentialError::Ecdsa(_))
+        ));
+
+        let wrong_purpose = DataIntegrityProof {
+            proof_purpose: "authentication".to_string(),
+            ..proof.clone()
+        };
+        assert!(matches!(
+            verify(&wrong_purpose),
+            Err(DTGCredentialError::Ecdsa(_))
+        ));
+
+        // A credential proof can't authenticate a presentation
+        assert!(
+            super::verify_document(
+                &DTGCommon {
+                    proof: None,
+                    ..cred.credential().clone()
+                },
+                &cred.credential().context,
+                &proof,
+                "authentication",
+                secret.get_public_bytes(),
+            )
+            .is_err()
+        );
+
+        let future = DataIntegrityProof {
+            created: Some((Utc::now() + Duration::days(1)).to_rfc3339()),
+            ..proof
+        };
+        assert!(matches!(verify(&future), Err(DTGCredentialError::Ecdsa(_))));
+    }
+
+    #[test]
+    fn test_incompatible_key_type() {
+        let mut cred = vrc();
+
+        assert!(matches!(
+            cred.sign_with_cryptosuite(
+                &Secret::generate_ed25519(None, None),
+                CryptoSuite::EcdsaJcs2019,
+                None
+            ),
+            Err(DTGCredentialError::IncompatibleKeyType(_, _))
+        ));
+        assert!(matches!(
+            cred.sign_with_cryptosuite(
+                &Secret::generate_p256(None, None).unwrap(),
+                CryptoSuite::EddsaJcs2022,
+                None
+            ),
+            Err(DTGCredentialError::IncompatibleKeyType(_, _))
+        ));
+        assert!(matches!(
+            cred.sign_with_cryptosuite(
+                &Secret::generate_p384(None, None).unwrap(),
+                CryptoSuite::EddsaRdfc2022,
+                None
+            ),
+            Err(DTGCredentialError::IncompatibleKeyType(_, _))
+        ));
+    }
+}
@@ -0,0 +1,1143 @@
+/*!
+*   The `ecdsa-sd-2023` selective disclosure cryptosuite for P-256 issuers.
+*
+*   <https://www.w3.org/TR/vc-di-ecdsa/#ecdsa-sd-2023>
+*
+*   Claims are chosen with JSON Pointers into the credential and disclosed as RDF statements,
+*   blank nodes are relabelled with an HMAC so the labels don't reveal the withheld statements.
+*   The issuer, validity period and subject id are always disclosed so that a derived credential
+*   is still a DTG Credential.
+*
+*   `card` and `endorsement` are `@json` literals in the DTG context, a single statement each, so
+*   an RCard or Endorsement always discloses its whole jCard or endorsement. Use
+*   [crate::selective_disclosure] to disclose individual jCard properties or endorsement
+*   attributes.
+*
+*   1. Issuer signs the credential with [DTGCredential::sign_ecdsa_sd] creating a base proof
+*   2. Holder derives a credential revealing only chosen claims with
+*      [DTGCredential::derive_ecdsa_sd]
+*   3. Verifier checks the derived credential with [DTGCredential::verify_proof_with_public_key]
+*/
+
+use crate::{
+    CredentialSubject, DTGCommon, DTGCredential, DTGCredentialError,
+    contexts::document_loader,
+    crypto::Algorithm,
+    proof::{CryptoSuite, DataIntegrityProof},
+    rdfc, signer,
+};
+use affinidi_rdf_encoding::{
+    BlankNode, Dataset, GraphLabel, NamedNode, Object, Quad, Subject, jsonld, nquads,
+};
+use chrono::{DateTime, Utc};
+use ciborium::value::Value as Cbor;
+use hmac::{Hmac, Mac};
+use multibase::Base;
+use p256::ecdsa::{
+    Signature, SigningKey, VerifyingKey,
+    signature::{Signer, Verifier},
+};
+use rand::RngCore;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt::Display,
+};
+
+/// proofValue header of a base proof
+const BASE_HEADER: [u8; 3] = [0xd9, 0x5d, 0x00];
+
+/// proofValue header of a derived proof
+const DERIVED_HEADER: [u8; 3] = [0xd9, 0x5d, 0x01];
+
+/// Multikey prefix of a compressed P-256 public key
+const P256_MULTIKEY: [u8; 2] = [0x80, 0x24];
+
+/// Stands in for blank node labels while claims are selected
+const SKOLEM_PREFIX: &str = "urn:bnid:";
+
+fn sd_error(msg: impl Display) -> DTGCredentialError {
+    DTGCredentialError::SelectiveDisclosure(msg.to_string())
+}
+
+/// Decoded proofValue
+enum ProofValue {
+    Base {
+        base_signature: Vec<u8>,
+        public_key: Vec<u8>,
+        hmac_key: Vec<u8>,
+        signatures: Vec<Vec<u8>>,
+        mandatory_pointers: Vec<String>,
+    },
+    Derived {
+        base_signature: Vec<u8>,
+        public_key: Vec<u8>,
+        signatures: Vec<Vec<u8>>,
+        /// HMAC of each canonical blank node label, by label number
+        label_map: BTreeMap<u64, Vec<u8>>,
+        /// Positions of the mandatory statements among the disclosed statements
+        mandatory_indexes: Vec<usize>,
+    },
+}
+
+fn cbor_bytes(value: &Cbor) -> Result<Vec<u8>, DTGCredentialError> {
+    value
+        .as_bytes()
+        .cloned()
+        .ok_or_else(|| sd_error("Invalid proofValue, expected bytes"))
+}
+
+fn cbor_array(value: &Cbor) -> Result<&[Cbor], DTGCredentialError> {
+    value
+        .as_array()
+        .map(Vec::as_slice)
+        .ok_or_else(|| sd_error("Invalid proofValue, expected an array"))
+}
+
+fn cbor_uint(value: &Cbor) -> Result<u64, DTGCredentialError> {
+    value
+        .as_integer()
+        .and_then(|integer| u64::try_from(integer).ok())
+        .ok_or_else(|| sd_error("Invalid proofValue, expected an unsigned integer"))
+}
+
+impl ProofValue {
+    fn encode(&self) -> Result<String, DTGCredentialError> {
+        let bytes = |bytes: &[u8]| Cbor::Bytes(bytes.to_vec());
+        let signatures =
+            |signatures: &[Vec<u8>]| Cbor::Array(signatures.iter().map(|s| bytes(s)).collect());
+
+        let (header, components) = match self {
+            ProofValue::Base {
+                base_signature,
+                public_key,
+                hmac_key,
+                signatures: claim_signatures,
+                mandatory_pointers,
+            } => (
+                BASE_HEADER,
+                vec![
+                    bytes(base_signature),
+                    bytes(public_key),
+                    bytes(hmac_key),
+                    signatures(claim_signatures),
+                    Cbor::Array(
+                        mandatory_pointers
+                            .iter()
+                            .map(|pointer| Cbor::Text(pointer.clone()))
+                            .collect(),
+                    ),
+                ],
+            ),
+            ProofValue::Derived {
+                base_signature,
+                public_key,
+                signatures: claim_signatures,
+                label_map,
+                mandatory_indexes,
+            } => (
+                DERIVED_HEADER,
+                vec![
+                    bytes(base_signature),
+                    bytes(public_key),
+                    signatures(claim_signatures),
+                    Cbor::Map(
+                        label_map
+                            .iter()
+                            .map(|(label, hmac)| (Cbor::Integer((*label).into()), bytes(hmac)))
+                            .collect(),
+                    ),
+                    Cbor::Array(
+                        mandatory_indexes
+                            .iter()
+                            .map(|index| Cbor::Integer((*index as u64).into()))
+                            .collect(),
+                    ),
+                ],
+            ),
+        };
+
+        let mut data = header.to_vec();
+        ciborium::ser::into_writer(&Cbor::Array(components), &mut data)
+            .map_err(|e| sd_error(format!("Couldn't encode proofValue: {e}")))?;
+        Ok(multibase::encode(Base::Base64Url, data))
+    }
+
+    fn decode(proof: &DataIntegrityProof) -> Result<Self, DTGCredentialError> {
+        let Some(proof_value) = &proof.proof_value else {
+            return Err(DTGCredentialError::NotSigned);
+        };
+        let (base, data) = multibase::decode(proof_value)
+            .map_err(|e| sd_error(format!("Invalid proofValue: {e}")))?;
+        if base != Base::Base64Url {
+            return Err(sd_error("proofValue must be base64url encoded"));
+        }
+        let (header, cbor) = data
+            .split_at_checked(BASE_HEADER.len())
+            .ok_or_else(|| sd_error("Invalid proofValue, too short"))?;
+        let components: Cbor = ciborium::de::from_reader(cbor)
+            .map_err(|e| sd_error(format!("Invalid proofValue: {e}")))?;
+
+        let signatures = |value: &Cbor| -> Result<Vec<Vec<u8>>, DTGCredentialError> {
+            cbor_array(value)?.iter().map(cbor_bytes).collect()
+        };
+        match (header, cbor_array(&components)?) {
+            (
+                header,
+                [
+                    base_signature,
+                    public_key,
+                    hmac_key,
+                    claim_signatures,
+                    pointers,
+                ],
+            ) if header == BASE_HEADER => Ok(ProofValue::Base {
+                base_signature: cbor_bytes(base_signature)?,
+                public_key: cbor_bytes(public_key)?,
+                hmac_key: cbor_bytes(hmac_key)?,
+                signatures: signatures(claim_signatures)?,
+                mandatory_pointers: cbor_array(pointers)?
+                    .iter()
+                    .map(|pointer| {
+                        pointer
+                            .as_text()
+                            .map(str::to_string)
+                            .ok_or_else(|| sd_error("Invalid proofValue, expected a JSON Pointer"))
+                    })
+                    .collect::<Result<_, _>>()?,
+            }),
+            (
+                header,
+                [
+                    base_signature,
+                    public_key,
+                    claim_signatures,
+                    label_map,
+                    indexes,
+                ],
+            ) if header == DERIVED_HEADER => Ok(ProofValue::Derived {
+                base_signature: cbor_bytes(base_signature)?,
+                public_key: cbor_bytes(public_key)?,
+                signatures: signatures(claim_signatures)?,
+                label_map: label_map
+                    .as_map()
+                    .ok_or_else(|| sd_error("Invalid proofValue, expected a label map"))?
+                    .iter()
+                    .map(|(label, hmac)| Ok((cbor_uint(label)?, cbor_bytes(hmac)?)))
+                    .collect::<Result<_, DTGCredentialError>>()?,
+                mandatory_indexes: cbor_array(indexes)?
+                    .iter()
+                    .map(|index| Ok(cbor_uint(index)? as usize))
+                    .collect::<Result<_, DTGCredentialError>>()?,
+            }),
+            _ => Err(sd_error("Invalid proofValue, unknown proof format")),
+        }
+    }
+}
+
+/// HMAC label of a canonical blank node label
+fn hmac_label(hmac_key: &[u8], canonical: &str) -> Result<String, DTGCredentialError> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(hmac_key).map_err(|_| sd_error("Invalid HMAC key"))?;
+    mac.update(canonical.as_bytes());
+    Ok(multibase::encode(
+        Base::Base64Url,
+        mac.finalize().into_bytes(),
+    ))
+}
+
+/// Number of a canonical blank node label, `c14n3` is 3
+fn label_number(canonical: &str) -> Result<u64, DTGCredentialError> {
+    canonical
+        .strip_prefix("c14n")
+        .and_then(|number| number.parse().ok())
+        .ok_or_else(|| sd_error(format!("Invalid canonical label ({canonical})")))
+}
+
+/// Multikey bytes of the ephemeral public key
+fn multikey(key: &VerifyingKey) -> Vec<u8> {
+    [&P256_MULTIKEY, key.to_encoded_point(true).as_bytes()].concat()
+}
+
+fn ephemeral_key(multikey: &[u8]) -> Result<VerifyingKey, DTGCredentialError> {
+    multikey
+        .strip_prefix(&P256_MULTIKEY)
+        .and_then(|key| VerifyingKey::from_sec1_bytes(key).ok())
+        .ok_or_else(|| sd_error("Invalid ephemeral public key"))
+}
+
+/// Unescaped segments of a JSON Pointer
+fn parse_pointer(pointer: &str) -> Result<Vec<String>, DTGCredentialError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(segments) = pointer.strip_prefix('/') else {
+        return Err(sd_error(format!("Invalid JSON Pointer ({pointer})")));
+    };
+    Ok(segments
+        .split('/')
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+/// Part of a JSON document chosen by JSON Pointers
+enum Selection {
+    /// The whole value
+    Full(Value),
+
+    Object(BTreeMap<String, Selection>),
+
+    /// Selected items by index, the unselected items are dropped
+    Array(BTreeMap<usize, Selection>),
+}
+
+impl Selection {
+    /// Nothing of the value is selected yet, but objects keep their id and type
+    fn initial(value: &Value) -> Self {
+        match value {
+            Value::Object(members) => Selection::Object(
+                members
+                    .iter()
+                    .filter(|(key, value)| match key.as_str() {
+                        "id" | "@id" => !value.as_str().is_some_and(|id| id.starts_with("_:")),
+                        "type" | "@type" => true,
+                        _ => false,
+                    })
+                    .map(|(key, value)| (key.clone(), Selection::Full(value.clone())))
+                    .collect(),
+            ),
+            Value::Array(_) => Selection::Array(BTreeMap::new()),
+            value => Selection::Full(value.clone()),
+        }
+    }
+
+    /// Selects the value at the pointer
+    fn add(&mut self, document: &Value, pointer: &str) -> Result<(), DTGCredentialError> {
+        let mismatch = || {
+            sd_error(format!(
+                "JSON Pointer ({pointer}) does not match the credential"
+            ))
+        };
+
+        let mut value = document;
+        let mut selected = self;
+        for segment in parse_pointer(pointer)? {
+            if value.get("@value").is_some() {
+                return Err(sd_error(format!(
+                    "JSON Pointer ({pointer}) is inside a JSON literal, which is disclosed whole"
+                )));
+            }
+            value = match value {
+                Value::Object(members) => members.get(&segment),
+                Value::Array(items) => segment
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| items.get(index)),
+                _ => None,
+            }
+            .ok_or_else(mismatch)?;
+
+            selected = match selected {
+                // Already selected whole
+                Selection::Full(_) => return Ok(()),
+                Selection::Object(members) => members
+                    .entry(segment)
+                    .or_insert_with(|| Selection::initial(value)),
+                Selection::Array(items) => items
+                    .entry(segment.parse().map_err(|_| mismatch())?)
+                    .or_insert_with(|| Selection::initial(value)),
+            };
+        }
+        *selected = Selection::Full(value.clone());
+        Ok(())
+    }
+
+    fn into_value(self) -> Value {
+        match self {
+            Selection::Full(value) => value,
+            Selection::Object(members) => Value::Object(
+                members
+                    .into_iter()
+                    .map(|(key, selection)| (key, selection.into_value()))
+                    .collect(),
+            ),
+            Selection::Array(items) => {
+                Value::Array(items.into_values().map(Selection::into_value).collect())
+            }
+        }
+    }
+}
+
+/// The values at the pointers, along with the `@context` and the ids and types of the nodes
+/// on the way to them. None if there are no pointers
+fn select(document: &Value, pointers: &[String]) -> Result<Option<Value>, DTGCredentialError> {
+    if pointers.is_empty() {
+        return Ok(None);
+    }
+
+    let mut selection = Selection::initial(document);
+    if let (Selection::Object(members), Some(context)) = (&mut selection, document.get("@context"))
+    {
+        members.insert("@context".to_string(), Selection::Full(context.clone()));
+    }
+    for pointer in pointers {
+        selection.add(document, pointer)?;
+    }
+    Ok(Some(selection.into_value()))
+}
+
+/// Gives every node without an id a skolem IRI, so that the statements of a selection use the
+/// same blank nodes as the statements of the whole document
+fn skolemize(value: &mut Value, counter: &mut usize) {
+    match value {
+        Value::Array(items) => items.iter_mut().for_each(|item| skolemize(item, counter)),
+        Value::Object(members) => {
+            // Literals, including `@json` values, aren't nodes
+            if members.contains_key("@value") {
+                return;
+            }
+            let key = ["id", "@id"]
+                .into_iter()
+                .find(|key| members.contains_key(*key));
+            match key {
+                Some(key) => {
+                    if let Some(Value::String(id)) = members.get_mut(key)
+                        && let Some(label) = id.strip_prefix("_:")
+                    {
+                        *id = format!("{SKOLEM_PREFIX}{label}");
+                    }
+                }
+                None if !members.contains_key("@list") && !members.contains_key("@set") => {
+                    members.insert(
+                        "@id".to_string(),
+                        Value::String(format!("{SKOLEM_PREFIX}s{counter}")),
+                    );
+                    *counter += 1;
+                }
+                None => {}
+            }
+            members
+                .iter_mut()
+                .filter(|(key, _)| *key != "@context")
+                .for_each(|(_, value)| skolemize(value, counter));
+        }
+        _ => {}
+    }
+}
+
+fn deskolemize(node: &NamedNode) -> Option<BlankNode> {
+    node.iri.strip_prefix(SKOLEM_PREFIX).map(BlankNode::new)
+}
+
+/// Statements of a prepared document, with skolem IRIs turned back into blank nodes
+fn to_dataset(prepared: &Value) -> Result<Dataset, DTGCredentialError> {
+    let mut dataset = jsonld::expand_and_to_rdf(prepared).map_err(sd_error)?;
+    for quad in &mut dataset.quads {
+        if let Subject::Named(node) = &quad.subject
+            && let Some(blank) = deskolemize(node)
+        {
+            quad.subject = Subject::Blank(blank);
+        }
+        if let Object::Named(node) = &quad.object
+            && let Some(blank) = deskolemize(node)
+        {
+            quad.object = Object::Blank(blank);
+        }
+        if let GraphLabel::Named(node) = &quad.graph
+            && let Some(blank) = deskolemize(node)
+        {
+            quad.graph = GraphLabel::Blank(blank);
+        }
+    }
+    Ok(dataset)
+}
+
+/// Sorted N-Quads of the dataset with every blank node relabelled
+fn relabel(
+    dataset: &Dataset,
+    labels: &HashMap<String, String>,
+) -> Result<Vec<String>, DTGCredentialError> {
+    let label = |node: &BlankNode| {
+        labels
+            .get(&node.id)
+            .map(BlankNode::new)
+            .ok_or_else(|| sd_error(format!("No label for blank node _:{}", node.id)))
+    };
+
+    let mut lines = dataset
+        .quads()
+        .iter()
+        .map(|quad| {
+            let quad = Quad {
+                subject: match &quad.subject {
+                    Subject::Blank(node) => Subject::Blank(label(node)?),
+                    subject => subject.clone(),
+                },
+                predicate: quad.predicate.clone(),
+                object: match &quad.object {
+                    Object::Blank(node) => Object::Blank(label(node)?),
+                    object => object.clone(),
+                },
+                graph: match &quad.graph {
+                    GraphLabel::Blank(node) => GraphLabel::Blank(label(node)?),
+                    graph => graph.clone(),
+                },
+            };
+            Ok(format!("{}\n", nquads::serialize_quad(&quad)))
+        })
+        .collect::<Result<Vec<String>, DTGCredentialError>>()?;
+    lines.sort();
+    lines.dedup();
+    Ok(lines)
+}
+
+/// Canonical statements of a credential, grouped by JSON Pointer selections
+struct Groups {
+    /// Canonical N-Quads, with relabelled blank nodes
+    nquads: Vec<String>,
+
+    /// Indexes into nquads of the statements each selection matches
+    matching: Vec<BTreeSet<usize>>,
+
+    /// Statements of each selection, with their skolem blank node labels
+    selections: Vec<Dataset>,
+
+    /// Relabelled blank node of each skolem blank node label
+    labels: HashMap<String, String>,
+}
+
+/// Canonicalizes a document, relabelling its blank nodes, then finds the statements each group
+/// of JSON Pointers selects
+/// label: Relabels a canonical blank node label such as `c14n0`
+fn canonicalize_and_group(
+    document: &Value,
+    label: impl Fn(&str) -> Result<String, DTGCredentialError>,
+    groups: &[&[String]],
+) -> Result<Groups, DTGCredentialError> {
+    let mut skolemized = rdfc::prepare_document(document, &document_loader()?)?;
+    skolemize(&mut skolemized, &mut 0);
+
+    let dataset = to_dataset(&skolemized)?;
+    let labels = rdfc::canonical_labels(&dataset)?
+        .into_iter()
+        .map(|(id, canonical)| Ok((id, label(&canonical)?)))
+        .collect::<Result<HashMap<String, String>, DTGCredentialError>>()?;
+    let nquads = relabel(&dataset, &labels)?;
+
+    let mut matching = Vec::new();
+    let mut selections = Vec::new();
+    for pointers in groups {
+        let selection = match select(&skolemized, pointers)? {
+            Some(selection) => to_dataset(&selection)?,
+            None => Dataset::new(),
+        };
+        let selected: HashSet<String> = relabel(&selection, &labels)?.into_iter().collect();
+        matching.push(
+            nquads
+                .iter()
+                .enumerate()
+                .filter(|(_, nquad)| selected.contains(*nquad))
+                .map(|(index, _)| index)
+                .collect(),
+        );
+        selections.push(selection);
+    }
+
+    Ok(Groups {
+        nquads,
+        matching,
+        selections,
+        labels,
+    })
+}
+
+/// (statements at the indexes, the other statements), in order
+fn split<'a>(nquads: &'a [String], indexes: &BTreeSet<usize>) -> (Vec<&'a str>, Vec<&'a str>) {
+    let (selected, others): (Vec<_>, Vec<_>) = nquads
+        .iter()
+        .enumerate()
+        .partition(|(index, _)| indexes.contains(index));
+    let strip = |nquads: Vec<(usize, &'a String)>| {
+        nquads
+            .into_iter()
+            .map(|(_, nquad)| nquad.as_str())
+            .collect()
+    };
+    (strip(selected), strip(others))
+}
+
+/// Data signed by the issuer: proofHash || ephemeral public key || mandatoryHash
+fn base_sign_data(
+    document: &Value,
+    proof: &DataIntegrityProof,
+    public_key: &[u8],
+    mandatory: &[&str],
+) -> Result<Vec<u8>, DTGCredentialError> {
+    Ok([
+        rdfc::proof_config_hash(document, proof)?.as_slice(),
+        public_key,
+        &Sha256::digest(mandatory.concat()),
+    ]
+    .concat())
+}
+
+fn unsigned_document(credential: &DTGCommon) -> Result<Value, DTGCredentialError> {
+    serde_json::to_value(DTGCommon {
+        proof: None,
+        ..credential.clone()
+    })
+    .map_err(|e| sd_error(format!("Couldn't serialize: {e}")))
+}
+
+/// Claims a derived credential needs to still be a DTG Credential
+fn required_pointers(credential: &DTGCommon) -> Vec<String> {
+    let mut pointers = vec!["/issuer", "/validFrom", "/credentialSubject/id"];
+    if credential.valid_until.is_some() {
+        pointers.push("/validUntil");
+    }
+    match &credential.credential_subject {
+        CredentialSubject::RCard(_) => pointers.push("/credentialSubject/card"),
+        CredentialSubject::Endorsement(_) => pointers.push("/credentialSubject/endorsement"),
+        _ => {}
+    }
+    pointers.into_iter().map(str::to_string).collect()
+}
+
+impl DTGCredential {
+    /// Sign the credential with an ecdsa-sd-2023 base proof
+    /// signing_secret: The P-256 key to sign with, see [signer::SigningKey]
+    /// mandatory: JSON Pointers of claims that are always disclosed
+    ///            The issuer, validity period and subject id are always disclosed
+    /// create_time: Optional creation time for the proof, defaults to now if None
+    pub fn sign_ecdsa_sd(
+        &mut self,
+        signing_secret: &impl signer::SigningKey,
+        mandatory: &[&str],
+        create_time: Option<DateTime<Utc>>,
+    ) -> Result<DataIntegrityProof, DTGCredentialError> {
+        let issuer = signing_secret.signer()?;
+        let algorithm = signer::Signer::algorithm(&issuer);
+        if algorithm != Algorithm::ES256 {
+            return Err(DTGCredentialError::IncompatibleKeyType(
+                algorithm.key_type().to_string(),
+                CryptoSuite::EcdsaSd2023.to_string(),
+            ));
+        }
+
+        let document = unsigned_document(&self.credential)?;
+        let mut mandatory_pointers = required_pointers(&self.credential);
+        for pointer in mandatory {
+            if !mandatory_pointers.iter().any(|p| p == pointer) {
+                mandatory_pointers.push(pointer.to_string());
+            }
+        }
+
+        let mut hmac_key = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut hmac_key);
+        let groups = canonicalize_and_group(
+            &document,
+            |canonical| hmac_label(&hmac_key, canonical),
+            &[&mandatory_pointers],
+        )?;
+        let (mandatory, non_mandatory) = split(&groups.nquads, &groups.matching[0]);
+
+        let mut proof = DataIntegrityProof {
+            type_: "DataIntegrityProof".to_string(),
+            cryptosuite: CryptoSuite::EcdsaSd2023,
+            created: Some(
+                create_time
+                    .unwrap_or_else(Utc::now)
+                    .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            ),
+            verification_method: signer::Signer::verification_method(&issuer),
+            proof_purpose: "assertionMethod".to_string(),
+            proof_value: None,
+            context: None,
+            challenge: None,
+            domain: None,
+        };
+
+        // Each statement that isn't mandatory is signed by a key unique to this credential
+        let ephemeral = SigningKey::random(&mut rand::rngs::OsRng);
+        let public_key = multikey(ephemeral.verifying_key());
+        let signatures = non_mandatory
+            .iter()
+            .map(|nquad| {
+                let signature: Signature = ephemeral.sign(nquad.as_bytes());
+                signature.to_vec()
+            })
+            .collect();
+        let base_signature = signer::Signer::sign(
+            &issuer,
+            &base_sign_data(&document, &proof, &public_key, &mandatory)?,
+        )?;
+
+        proof.proof_value = Some(
+            ProofValue::Base {
+                base_signature,
+                public_key,
+                hmac_key: hmac_key.to_vec(),
+                signatures,
+                mandatory_pointers,
+            }
+            .encode()?,
+        );
+
+        self.credential.proof = Some(proof.clone());
+        Ok(proof)
+    }
+
+    /// Derive a credential that only discloses the claims at these JSON Pointers
+    /// The credential must hold an ecdsa-sd-2023 base proof, see [DTGCredential::sign_ecdsa_sd]
+    /// disclose: JSON Pointers of claims to reveal, mandatory claims are always revealed
+    pub fn derive_ecdsa_sd(&self, disclose: &[&str]) -> Result<DTGCredential, DTGCredentialError> {
+        let Some(proof) = &self.credential.proof else {
+            return Err(DTGCredentialError::NotSigned);
+        };
+        if proof.cryptosuite != CryptoSuite::EcdsaSd2023 {
+            return Err(DTGCredentialError::UnsupportedCryptoSuite(
+                proof.cryptosuite.to_string(),
+            ));
+        }
+        let ProofValue::Base {
+            base_signature,
+            public_key,
+            hmac_key,
+            signatures,
+            mandatory_pointers,
+        } = ProofValue::decode(proof)?
+        else {
+            return Err(sd_error("Can only derive from a base proof"));
+        };
+
+        let document = unsigned_document(&self.credential)?;
+        let selective_pointers: Vec<String> =
+            disclose.iter().map(|pointer| pointer.to_string()).collect();
+        let combined_pointers: Vec<String> = mandatory_pointers
+            .iter()
+            .chain(selective_pointers.iter())
+            .cloned()
+            .collect();
+        let groups = canonicalize_and_group(
+            &document,
+            |canonical| hmac_label(&hmac_key, canonical),
+            &[&mandatory_pointers, &selective_pointers, &combined_pointers],
+        )?;
+        let (mandatory, selective, combined) = (
+            &groups.matching[0],
+            &groups.matching[1],
+            &groups.matching[2],
+        );
+
+        let mandatory_indexes = combined
+            .iter()
+            .enumerate()
+            .filter(|(_, index)| mandatory.contains(index))
+            .map(|(position, _)| position)
+            .collect();
+
+        // Signatures are in the order of the statements that aren't mandatory
+        let non_mandatory: Vec<usize> = (0..groups.nquads.len())
+            .filter(|index| !mandatory.contains(index))
+            .collect();
+        if non_mandatory.len() != signatures.len() {
+            return Err(sd_error("Mismatched statement signatures"));
+        }
+        let signatures = non_mandatory
+            .iter()
+            .zip(signatures)
+            .filter(|(index, _)| selective.contains(index))
+            .map(|(_, signature)| signature)
+            .collect();
+
+        // The verifier canonicalizes the disclosed statements, map its labels to the HMAC labels
+        let label_map = rdfc::canonical_labels(&groups.selections[2])?
+            .into_iter()
+            .map(|(id, canonical)| {
+                let label = groups
+                    .labels
+                    .get(&id)
+                    .ok_or_else(|| sd_error(format!("No label for blank node _:{id}")))?;
+                let (_, hmac) = multibase::decode(label).map_err(sd_error)?;
+                Ok((label_number(&canonical)?, hmac))
+            })
+            .collect::<Result<BTreeMap<u64, Vec<u8>>, DTGCredentialError>>()?;
+
+        let reveal = select(&document, &combined_pointers)?
+            .ok_or_else(|| sd_error("Nothing to disclose"))?;
+        let mut derived: DTGCredential = serde_json::from_value(reveal)
+            .map_err(|e| sd_error(format!("Couldn't derive credential: {e}")))?;
+        derived.credential.proof = Some(DataIntegrityProof {
+            proof_value: Some(
+                ProofValue::Derived {
+                    base_signature,
+                    public_key,
+                    signatures,
+                    label_map,
+                    mandatory_indexes,
+                }
+                .encode()?,
+            ),
+            ..proof.clone()
+        });
+
+        Ok(derived)
+    }
+}
+
+/// Verifies an ecdsa-sd-2023 base or derived proof
+pub(crate) fn verify_proof(
+    credential: &DTGCredential,
+    proof: &DataIntegrityProof,
+    public_key_bytes: &[u8],
+) -> Result<(), DTGCredentialError> {
+    if proof.type_ != "DataIntegrityProof" {
+        return Err(sd_error(
+            "Invalid proof type, expected 'DataIntegrityProof'",
+        ));
+    }
+    if proof.cryptosuite != CryptoSuite::EcdsaSd2023 {
+        return Err(sd_error(format!(
+            "Invalid cryptosuite {}, expected 'ecdsa-sd-2023'",
+            proof.cryptosuite
+        )));
+    }
+    if proof.proof_purpose != "assertionMethod" {
+        return Err(sd_error(format!(
+            "Invalid proofPurpose {}, expected 'assertionMethod'",
+            proof.proof_purpose
+        )));
+    }
+    if let Some(created) = &proof.created {
+        let created = created
+            .parse::<DateTime<Utc>>()
+            .map_err(|e| sd_error(format!("Invalid created date: {e}")))?;
+        if created > Utc::now() {
+            return Err(sd_error("Created date is in the future"));
+        }
+    }
+
+    let document = unsigned_document(&credential.credential)?;
+    let (base_signature, public_key, signatures, groups, mandatory) =
+        match ProofValue::decode(proof)? {
+            ProofValue::Base {
+                base_signature,
+                public_key,
+                hmac_key,
+                signatures,
+                mandatory_pointers,
+            } => {
+                let mut groups = canonicalize_and_group(
+                    &document,
+                    |canonical| hmac_label(&hmac_key, canonical),
+                    &[&mandatory_pointers],
+                )?;
+                let mandatory = groups.matching.remove(0);
+                (base_signature, public_key, signatures, groups, mandatory)
+            }
+            ProofValue::Derived {
+                base_signature,
+                public_key,
+                signatures,
+                label_map,
+                mandatory_indexes,
+            } => {
+                let groups = canonicalize_and_group(
+                    &document,
+                    |canonical| {
+                        label_map
+                            .get(&label_number(canonical)?)
+                            .map(|hmac| multibase::encode(Base::Base64Url, hmac))
+                            .ok_or_else(|| {
+                                sd_error(format!("No label for blank node _:{canonical}"))
+                            })
+                    },
+                    &[],
+                )?;
+                let mandatory: BTreeSet<usize> = mandatory_indexes.iter().copied().collect();
+                if mandatory.len() != mandatory_indexes.len() {
+                    return Err(sd_error("Duplicate mandatory statement index"));
+                }
+                if mandatory
+                    .last()
+                    .is_some_and(|index| *index >= groups.nquads.len())
+                {
+                    return Err(sd_error("Mandatory statement index out of range"));
+                }
+                (base_signature, public_key, signatures, groups, mandatory)
+            }
+        };
+    let (mandatory, non_mandatory) = split(&groups.nquads, &mandatory);
+    if non_mandatory.len() != signatures.len() {
+        return Err(sd_error("Mismatched statement signatures"));
+    }
+
+    let verification_error = |e: String| sd_error(format!("Verification failed: {e}"));
+
+    // Check the issuer signature over the mandatory statements
+    Algorithm::ES256
+        .verify(
+            public_key_bytes,
+            &base_sign_data(&document, proof, &public_key, &mandatory)?,
+            &base_signature,
+        )
+        .map_err(verification_error)?;
+
+    // Check each other statement against the ephemeral key
+    let ephemeral = ephemeral_key(&public_key)?;
+    for (nquad, signature) in non_mandatory.iter().zip(signatures) {
+        let signature = Signature::from_slice(&signature)
+            .map_err(|_| verification_error("Invalid signature format".to_string()))?;
+        ephemeral
+            .verify(nquad.as_bytes(), &signature)
+            .map_err(|_| {
+                verification_error(format!("Statement signature is invalid: {}", nquad.trim()))
+            })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "affinidi-signing"))]
+mod tests {
+    use super::{DERIVED_HEADER, ProofValue};
+    use crate::{
+        CredentialSubject, DTGCredential, DTGCredentialError, WitnessContext, proof::CryptoSuite,
+        test_utils::did,
+    };
+    use affinidi_secrets_resolver::secrets::Secret;
+    use chrono::{Duration, Utc};
+    use serde_json::json;
+
+    fn vwc() -> DTGCredential {
+        DTGCredential::new_vwc(
+            did("witness"),
+            did("subject"),
+            Utc::now() - Duration::days(1),
+            Some(Utc::now() + Duration::days(30)),
+            Some("digest".to_string()),
+            Some(WitnessContext {
+                event: Some("Meetup".to_string()),
+                session_id: Some("session".to_string()),
+                method: None,
+            }),
+        )
+    }
+
+    fn subject(credential: &DTGCredential) -> serde_json::Value {
+        serde_json::to_value(&credential.credential().credential_subject).unwrap()
+    }
+
+    #[test]
+    fn test_derive_and_verify() {
+        let secret = Secret::generate_p256(None, None).unwrap();
+        let mut vwc = vwc();
+        let proof = vwc.sign_ecdsa_sd(&secret, &[], None).unwrap();
+        assert_eq!(proof.cryptosuite, CryptoSuite::EcdsaSd2023);
+        assert!(proof.proof_value.unwrap().starts_with('u'));
+
+        // The holder can check the base proof
+        assert!(
+            vwc.verify_proof_with_public_key(secret.get_public_bytes())
+                .is_ok()
+        );
+
+        let derived = vwc.derive_ecdsa_sd(&[]).unwrap();
+        assert_eq!(subject(&derived), json!({"id": did("subject")}));
+        assert_eq!(derived.issuer(), did("witness"));
+        assert!(derived.credential().valid_until.is_some());
+        assert!(matches!(
+            ProofValue::decode(derived.credential().proof.as_ref().unwrap()),
+            Ok(ProofValue::Derived { .. })
+        ));
+        assert!(
+            derived
+                .verify_proof_with_public_key(secret.get_public_bytes())
+                .is_ok()
+        );
+
+        let derived = vwc
+            .derive_ecdsa_sd(&["/credentialSubject/witnessContext/event"])
+            .unwrap();
+        assert_eq!(
+            subject(&derived),
+            json!({
+                "id": did("subject"),
+                "witnessContext": {"event": "Meetup", "sessionId": null, "method": null}
+            })
+        );
+        assert!(
+            derived
+                .verify_proof_with_public_key(secret.get_public_bytes())
+                .is_ok()
+        );
+
+        // Serialization round trip keeps the proof valid
+        let derived = vwc
+            .derive_ecdsa_sd(&[
+                "/credentialSubject/digest",
+                "/credentialSubject/witnessContext",
+            ])
+            .unwrap();
+        let derived: DTGCredential =
+            serde_json::from_str(&serde_json::to_string(&derived).unwrap()).unwrap();
+        assert_eq!(subject(&derived), subject(&vwc));
+        assert!(
+            derived
+                .verify_proof_with_public_key(secret.get_public_bytes())
+                .is_ok()
+        );
+
+        let other = Secret::generate_p256(None, None).unwrap();
+        assert!(
+            derived
+                .verify_proof_with_public_key(other.get_public_bytes())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_tampered() {
+        let secret = Secret::generate_p256(None, None).unwrap();
+        let mut vwc = vwc();
+        vwc.sign_ecdsa_sd(&secret, &["/credentialSubject/digest"], None)
+            .unwrap();
+
+        let derived = vwc
+            .derive_ecdsa_sd(&["/credentialSubject/witnessContext/sessionId"])
+            .unwrap();
+
+        // Mandatory claim
+        let mut tampered = derived.clone();
+        tampered.credential_mut().issuer = did("mallory");
+        assert!(
+            tampered
+                .verify_proof_with_public_key(secret.get_public_bytes())
+                .is_err()
+        );
+
+        // Disclosed claim
+        let mut tampered = derived.clone();
+        if let CredentialSubject::Witness(subject) =
+            &mut tampered.credential_mut().credential_subject
+        {
+            subject.witness_context.as_mut().unwrap().session_id = Some("other".to_string());
+        }
+        assert!(
+            tampered
+                .verify_proof_with_public_key(secret.get_public_bytes())
+                .is_err()
+        );
+
+        // Withheld claim added back
+        let mut tampered = derived.clone();
+        if let CredentialSubject::Witness(subject) =
+            &mut tampered.credential_mut().credential_subject
+        {
+            subject.witness_context.as_mut().unwrap().event = Some("Meetup".to_string());
+        }
+        assert!(
+            tampered
+                .verify_proof_with_public_key(secret.get_public_bytes())
+                .is_err()
+        );
+
+        // Mandatory statement indexes must be unique
+        let proof = derived.credential().proof.as_ref().unwrap();
+        let Ok(ProofValue::Derived {
+            base_signature,
+            public_key,
+            signatures,
+            label_map,
+            mut mandatory_indexes,
+        }) = ProofValue::decode(proof)
+        else {
+            panic!("Expected a derived proof");
+        };
+        mandatory_indexes.push(mandatory_indexes[0]);
+        let mut tampered = derived.clone();
+        tampered
+            .credential_mut()
+            .proof
+            .as_mut()
+            .unwrap()
+            .proof_value = Some(
+            ProofValue::Derived {
+                base_signature,
+                public_key,
+                signatures,
+                label_map,
+                mandatory_indexes,
+            }
+            .encode()
+            .unwrap(),
+        );
+        assert!(matches!(
+            tampered.verify_proof_with_public_key(secret.get_public_bytes()),
+            Err(DTGCredentialError::SelectiveDisclosure(_))
+        ));
+    }
+
+    #[test]
+    fn test_json_literals_disclosed_whole() {
+        let secret = Secret::generate_p256(None, None).unwrap();
+        let mut rcard = DTGCredential::new_rcard(
+            did("issuer"),
+            did("subject"),
+            Utc::now(),
+            None,
+            json!(["vcard", [["fn", {}, "text", "Alice Smith"]]]),
+        );
+        rcard.sign_ecdsa_sd(&secret, &[], None).unwrap();
+
+        let derived = rcard.derive_ecdsa_sd(&[]).unwrap();
+        assert_eq!(subject(&derived), subject(&rcard));
+        assert!(
+            derived
+                .verify_proof_with_public_key(secret.get_public_bytes())
+                .is_ok()
+        );
+
+        assert!(matches!(
+            rcard.derive_ecdsa_sd(&["/credentialSubject/card/1/0"]),
+            Err(DTGCredentialError::SelectiveDisclosure(_))
+        ));
+        assert!(matches!(
+            rcard.derive_ecdsa_sd(&["/credentialSubject/missing"]),
+            Err(DTGCredentialError::SelectiveDisclosure(_))
+        ));
+    }
+
+    #[test]
+    fn test_requires_p256_and_base_proof() {
+        let mut vwc = vwc();
+        assert!(matches!(
+            vwc.sign_ecdsa_sd(&Secret::generate_ed25519(None, None), &[], None),
+            Err(DTGCredentialError::IncompatibleKeyType(_, _))
+        ));
+
+        let secret = Secret::generate_p256(None, None).unwrap();
+        vwc.sign(&secret, None).unwrap();
+        assert!(matches!(
+            vwc.derive_ecdsa_sd(&[]),
+            Err(DTGCredentialError::UnsupportedCryptoSuite(_))
+        ));
+
+        vwc.sign_ecdsa_sd(&secret, &[], None).unwrap();
+        let derived = vwc.derive_ecdsa_sd(&[]).unwrap();
+        assert!(matches!(
+            derived.derive_ecdsa_sd(&[]),
+            Err(DTGCredentialError::SelectiveDisclosure(_))
+        ));
+        let proof_value = derived
+            .credential()
+            .proof
+            .as_ref()
+            .unwrap()
+            .proof_value
+            .as_ref();
+        let (_, bytes) = multibase::decode(proof_value.unwrap()).unwrap();
+        assert_eq!(bytes[..3], DERIVED_HEADER);
+    }
+}
@@ -0,0 +1,173 @@
+/*!
+*   The `eddsa-jcs-2022` cryptosuite for Ed25519 issuers.
+*
+*   <https://www.w3.org/TR/vc-di-eddsa/#eddsa-jcs-2022>
+*
+*   The credential and proof configuration are canonicalized with JCS and hashed with SHA-256.
+*   The proof configuration carries no `@context`, matching `affinidi-data-integrity` so that
+*   proofs created by either implementation verify with the other.
+*/
+
+#[cfg(feature = "signing")]
+use crate::crypto::Algorithm;
+use crate::{DTGCommon, DTGCredential, DTGCredentialError, proof::DataIntegrityProof};
+#[cfg(feature = "signing")]
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json_canonicalizer::to_string;
+use sha2::{Digest, Sha256};
+
+fn data_integrity_error(msg: impl Into<String>) -> DTGCredentialError {
+    DTGCredentialError::DataIntegrity(msg.into())
+}
+
+fn canonicalize(value: &impl Serialize) -> Result<String, DTGCredentialError> {
+    to_string(value).map_err(|e| data_integrity_error(format!("Couldn't canonicalize: {e}")))
+}
+
+/// hash(proofConfig) || hash(credential)
+pub(crate) fn jcs_hash_data(
+    credential: &DTGCredential,
+    proof: &DataIntegrityProof,
+) -> Result<Vec<u8>, DTGCredentialError> {
+    jcs_hash_document(
+        &DTGCommon {
+            proof: None,
+            ..credential.credential.clone()
+        },
+        proof,
+    )
+}
+
+/// hash(proofConfig) || hash(document)
+/// document: The unsecured document, without a proof
+pub(crate) fn jcs_hash_document(
+    document: &impl Serialize,
+    proof: &DataIntegrityProof,
+) -> Result<Vec<u8>, DTGCredentialError> {
+    let config = DataIntegrityProof {
+        proof_value: None,
+        ..proof.clone()
+    };
+
+    let mut data = Sha256::digest(canonicalize(&config)?).to_vec();
+    data.extend(Sha256::digest(canonicalize(document)?));
+    Ok(data)
+}
+
+#[cfg(feature = "signing")]
+/// Verifies an eddsa-jcs-2022 proof
+pub(crate) fn verify_proof(
+    credential: &DTGCredential,
+    proof: &DataIntegrityProof,
+    public_key_bytes: &[u8],
+) -> Result<(), DTGCredentialError> {
+    verify_document(
+        &DTGCommon {
+            proof: None,
+            ..credential.credential.clone()
+        },
+        proof,
+        public_key_bytes,
+    )
+}
+
+#[cfg(feature = "signing")]
+/// Verifies an eddsa-jcs-2022 proof of a document
+/// document: The unsecured document, without a proof
+pub(crate) fn verify_document(
+    document: &impl Serialize,
+    proof: &DataIntegrityProof,
+    public_key_bytes: &[u8],
+) -> Result<(), DTGCredentialError> {
+    if proof.type_ != "DataIntegrityProof" {
+        return Err(data_integrity_error(
+            "Invalid proof type, expected 'DataIntegrityProof'",
+        ));
+    }
+    if proof.context.is_some() {
+        return Err(data_integrity_error(
+            "Document context does not match proof context",
+        ));
+    }
+    if let Some(created) = &proof.created {
+        let created = created
+            .parse::<DateTime<Utc>>()
+            .map_err(|e| data_integrity_error(format!("Invalid created date: {e}")))?;
+        if created > Utc::now() {
+            return Err(data_integrity_error("Created date is in the future"));
+        }
+    }
+
+    let Some(proof_value) = &proof.proof_value else {
+        return Err(data_integrity_error("proofValue is missing in the proof"));
+    };
+    let (_, signature) = multibase::decode(proof_value)
+        .map_err(|e| data_integrity_error(format!("Invalid proof value: {e}")))?;
+
+    Algorithm::EdDSA
+        .verify(
+            public_key_bytes,
+            &jcs_hash_document(document, proof)?,
+            &signature,
+        )
+        .map_err(|e| data_integrity_error(format!("Signature verification failed: {e}")))
+}
+
+#[cfg(all(test, feature = "affinidi-signing"))]
+mod tests {
+    use crate::{DTGCommon, DTGCredential, proof::DataIntegrityProof};
+    use affinidi_secrets_resolver::secrets::Secret;
+    use chrono::Utc;
+
+    fn vrc() -> DTGCredential {
+        DTGCredential::new_vrc(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            Utc::now(),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_verifies_affinidi_proof() {
+        let secret = Secret::generate_ed25519(None, None);
+        let mut cred = vrc();
+
+        let proof: DataIntegrityProof = affinidi_data_integrity::DataIntegrityProof::sign_jcs_data(
+            &cred.credential,
+            None,
+            &secret,
+            None,
+        )
+        .unwrap()
+        .into();
+        cred.credential.proof = Some(proof);
+
+        assert!(
+            cred.verify_proof_with_public_key(secret.get_public_bytes())
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_affinidi_verifies_proof() {
+        let secret = Secret::generate_ed25519(None, None);
+        let mut cred = vrc();
+        let proof = cred.sign(&secret, None).unwrap();
+
+        let unsigned = DTGCommon {
+            proof: None,
+            ..cred.credential.clone()
+        };
+        assert!(
+            affinidi_data_integrity::verification_proof::verify_data_with_public_key(
+                &unsigned,
+                None,
+                &proof.try_into().unwrap(),
+                secret.get_public_bytes(),
+            )
+            .is_ok()
+        );
+    }
+}
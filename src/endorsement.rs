@@ -0,0 +1,444 @@
+/*!
+*   Typed endorsement vocabulary for Verified Endorsement Credentials (VEC).
+*
+*   The `endorsement` of a VEC is a JSON object identified by its `type`. Well known types have
+*   typed models, any other type is kept as a raw JSON value.
+*
+*   Applications can register their own endorsement types, with a validator, in an
+*   [EndorsementRegistry].
+*/
+
+use crate::{CredentialSubject, DTGCredential, DTGCredentialError};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
+use std::{collections::HashMap, fmt::Display};
+
+/// `type` of a [SkillEndorsement]
+pub const SKILL_ENDORSEMENT: &str = "SkillEndorsement";
+
+/// `type` of a [CharacterEndorsement]
+pub const CHARACTER_ENDORSEMENT: &str = "CharacterEndorsement";
+
+/// `type` of a [ProfessionalReference]
+pub const PROFESSIONAL_REFERENCE: &str = "ProfessionalReference";
+
+fn endorsement_error(msg: impl Display) -> DTGCredentialError {
+    DTGCredentialError::Endorsement(msg.to_string())
+}
+
+/// Level of competency in a skill
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompetencyLevel {
+    Beginner,
+    Intermediate,
+    Advanced,
+    Expert,
+}
+
+/// Endorses the subject's skill
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillEndorsement {
+    /// Name of the skill
+    pub name: String,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub competency_level: Option<CompetencyLevel>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+
+    /// Any other attributes
+    #[serde(flatten)]
+    pub additional: Map<String, Value>,
+}
+
+/// Endorses a character trait of the subject, e.g. honesty
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CharacterEndorsement {
+    /// The character trait
+    #[serde(rename = "trait")]
+    pub trait_: String,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+
+    /// Any other attributes
+    #[serde(flatten)]
+    pub additional: Map<String, Value>,
+}
+
+/// A professional reference for the subject
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfessionalReference {
+    /// How the issuer knows the subject, e.g. manager
+    pub relationship: String,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub organization: Option<String>,
+
+    /// Subject's role while the issuer worked with them
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub role: Option<String>,
+
+    /// Free text reference
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub statement: Option<String>,
+
+    /// Any other attributes
+    #[serde(flatten)]
+    pub additional: Map<String, Value>,
+}
+
+/// The endorsement of a VEC
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Endorsement {
+    Skill(SkillEndorsement),
+    Character(CharacterEndorsement),
+    ProfessionalReference(ProfessionalReference),
+
+    /// Any other endorsement type, as raw JSON
+    Other(Value),
+}
+
+/// Removes `type` and deserializes the remaining attributes
+fn parse_typed<T: DeserializeOwned>(value: &Value) -> Result<T, DTGCredentialError> {
+    let mut attributes = value.as_object().cloned().unwrap_or_default();
+    let type_ = attributes.remove("type");
+    serde_json::from_value(Value::Object(attributes))
+        .map_err(|e| endorsement_error(format!("Invalid {}: {e}", type_.unwrap_or_default())))
+}
+
+/// Serializes and adds `type`
+fn typed_value<T: Serialize>(type_: &str, endorsement: &T) -> Value {
+    let mut value = serde_json::to_value(endorsement).unwrap_or_default();
+    if let Some(attributes) = value.as_object_mut() {
+        attributes.insert("type".to_string(), Value::String(type_.to_string()));
+    }
+    value
+}
+
+impl Endorsement {
+    /// The endorsement `type`
+    pub fn type_(&self) -> Option<&str> {
+        match self {
+            Endorsement::Skill(_) => Some(SKILL_ENDORSEMENT),
+            Endorsement::Character(_) => Some(CHARACTER_ENDORSEMENT),
+            Endorsement::ProfessionalReference(_) => Some(PROFESSIONAL_REFERENCE),
+            Endorsement::Other(value) => value.get("type").and_then(|t| t.as_str()),
+        }
+    }
+
+    /// Converts back to raw JSON, including `type`
+    pub fn to_value(&self) -> Value {
+        match self {
+            Endorsement::Skill(e) => typed_value(SKILL_ENDORSEMENT, e),
+            Endorsement::Character(e) => typed_value(CHARACTER_ENDORSEMENT, e),
+            Endorsement::ProfessionalReference(e) => typed_value(PROFESSIONAL_REFERENCE, e),
+            Endorsement::Other(value) => value.clone(),
+        }
+    }
+}
+
+impl TryFrom<&Value> for Endorsement {
+    type Error = DTGCredentialError;
+
+    /// Parses well known endorsement types, other types are returned as [Endorsement::Other]
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        if !value.is_object() {
+            return Err(endorsement_error("Endorsement must be an object"));
+        }
+
+        match value.get("type").and_then(|t| t.as_str()) {
+            Some(SKILL_ENDORSEMENT) => Ok(Endorsement::Skill(parse_typed(value)?)),
+            Some(CHARACTER_ENDORSEMENT) => Ok(Endorsement::Character(parse_typed(value)?)),
+            Some(PROFESSIONAL_REFERENCE) => {
+                Ok(Endorsement::ProfessionalReference(parse_typed(value)?))
+            }
+            _ => Ok(Endorsement::Other(value.clone())),
+        }
+    }
+}
+
+impl From<Endorsement> for Value {
+    fn from(endorsement: Endorsement) -> Self {
+        endorsement.to_value()
+    }
+}
+
+impl From<SkillEndorsement> for Value {
+    fn from(endorsement: SkillEndorsement) -> Self {
+        Endorsement::Skill(endorsement).to_value()
+    }
+}
+
+impl From<CharacterEndorsement> for Value {
+    fn from(endorsement: CharacterEndorsement) -> Self {
+        Endorsement::Character(endorsement).to_value()
+    }
+}
+
+impl From<ProfessionalReference> for Value {
+    fn from(endorsement: ProfessionalReference) -> Self {
+        Endorsement::ProfessionalReference(endorsement).to_value()
+    }
+}
+
+/// Validates the attributes of an endorsement type
+pub type EndorsementValidator = Box<dyn Fn(&Value) -> Result<(), String> + Send + Sync>;
+
+/// Registry of accepted endorsement types and their validators
+/// [EndorsementRegistry::default] contains the well known endorsement types
+pub struct EndorsementRegistry {
+    validators: HashMap<String, EndorsementValidator>,
+}
+
+impl Default for EndorsementRegistry {
+    fn default() -> Self {
+        let mut registry = EndorsementRegistry::empty();
+        registry.register_type::<SkillEndorsement>(SKILL_ENDORSEMENT);
+        registry.register_type::<CharacterEndorsement>(CHARACTER_ENDORSEMENT);
+        registry.register_type::<ProfessionalReference>(PROFESSIONAL_REFERENCE);
+        registry
+    }
+}
+
+impl EndorsementRegistry {
+    /// Registry without any endorsement types
+    pub fn empty() -> Self {
+        EndorsementRegistry {
+            validators: HashMap::new(),
+        }
+    }
+
+    /// Registers an endorsement type, replacing any existing validator
+    /// type_: The endorsement `type`
+    /// validator: Checks the endorsement (including `type`), returning a reason when invalid
+    pub fn register<F>(&mut self, type_: &str, validator: F)
+    where
+        F: Fn(&Value) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.validators
+            .insert(type_.to_string(), Box::new(validator));
+    }
+
+    /// Registers an endorsement type validated by deserializing into a serde model
+    /// The model should not include the `type` attribute
+    pub fn register_type<T: DeserializeOwned>(&mut self, type_: &str) {
+        self.register(type_, |value| {
+            parse_typed::<T>(value)
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        });
+    }
+
+    pub fn is_registered(&self, type_: &str) -> bool {
+        self.validators.contains_key(type_)
+    }
+
+    /// Validates an endorsement against its registered type
+    pub fn validate(&self, endorsement: &Value) -> Result<Endorsement, DTGCredentialError> {
+        let Some(type_) = endorsement.get("type").and_then(|t| t.as_str()) else {
+            return Err(endorsement_error("Endorsement is missing type"));
+        };
+        let Some(validator) = self.validators.get(type_) else {
+            return Err(endorsement_error(format!(
+                "Unregistered endorsement type: {type_}"
+            )));
+        };
+
+        validator(endorsement).map_err(|e| endorsement_error(format!("Invalid {type_}: {e}")))?;
+        Endorsement::try_from(endorsement)
+    }
+
+    /// Validates the endorsement of a VEC
+    pub fn validate_credential(
+        &self,
+        credential: &DTGCredential,
+    ) -> Result<Endorsement, DTGCredentialError> {
+        match credential.endorsement_value() {
+            Some(endorsement) => self.validate(endorsement),
+            None => Err(endorsement_error("Credential is not an Endorsement")),
+        }
+    }
+}
+
+impl DTGCredential {
+    fn endorsement_value(&self) -> Option<&Value> {
+        match &self.credential.credential_subject {
+            CredentialSubject::Endorsement(subject) => Some(&subject.endorsement),
+            _ => None,
+        }
+    }
+
+    /// Typed endorsement of a VEC
+    /// Returns None if this is not an Endorsement
+    pub fn endorsement(&self) -> Result<Option<Endorsement>, DTGCredentialError> {
+        self.endorsement_value()
+            .map(Endorsement::try_from)
+            .transpose()
+    }
+
+    /// The endorsement `type` of a VEC
+    pub fn endorsement_type(&self) -> Option<&str> {
+        self.endorsement_value()
+            .and_then(|e| e.get("type"))
+            .and_then(|t| t.as_str())
+    }
+
+    /// Deserialize the endorsement of a VEC into an application defined model
+    /// The `type` attribute is not passed to the model
+    pub fn endorsement_as<T: DeserializeOwned>(&self) -> Result<Option<T>, DTGCredentialError> {
+        self.endorsement_value().map(parse_typed).transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CompetencyLevel, Endorsement, EndorsementRegistry, ProfessionalReference, SkillEndorsement,
+    };
+    use crate::{CredentialSubject, DTGCredential};
+    use chrono::Utc;
+    use serde::Deserialize;
+    use serde_json::{Map, Value, json};
+
+    fn vec(endorsement: impl Into<Value>) -> DTGCredential {
+        DTGCredential::new_vec(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            Utc::now(),
+            None,
+            endorsement,
+        )
+    }
+
+    #[test]
+    fn test_skill() {
+        let vec = vec(json!({
+          "type": "SkillEndorsement",
+          "name": "Software Development",
+          "competencyLevel": "expert",
+          "yearsExperience": 10
+        }));
+
+        assert_eq!(vec.endorsement_type(), Some("SkillEndorsement"));
+        let Some(Endorsement::Skill(skill)) = vec.endorsement().unwrap() else {
+            panic!("Expected a SkillEndorsement");
+        };
+        assert_eq!(skill.name, "Software Development");
+        assert_eq!(skill.competency_level, Some(CompetencyLevel::Expert));
+        assert_eq!(skill.additional["yearsExperience"], json!(10));
+
+        // Round trips, including additional attributes
+        let round_trip = Endorsement::Skill(skill).to_value();
+        let CredentialSubject::Endorsement(subject) = &vec.credential().credential_subject else {
+            panic!("Expected an Endorsement subject");
+        };
+        assert_eq!(round_trip, subject.endorsement);
+    }
+
+    #[test]
+    fn test_typed_constructor() {
+        let vec = vec(ProfessionalReference {
+            relationship: "manager".to_string(),
+            organization: Some("Example Corp".to_string()),
+            role: None,
+            statement: Some("Reliable and thorough".to_string()),
+            additional: Map::new(),
+        });
+
+        assert_eq!(vec.endorsement_type(), Some("ProfessionalReference"));
+        assert!(matches!(
+            vec.endorsement().unwrap(),
+            Some(Endorsement::ProfessionalReference(r)) if r.relationship == "manager"
+        ));
+
+        let vrc = DTGCredential::new_vrc(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            Utc::now(),
+            None,
+        );
+        assert!(vrc.endorsement().unwrap().is_none());
+        assert!(
+            EndorsementRegistry::default()
+                .validate_credential(&vrc)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_invalid_known_type() {
+        let vec = vec(json!({"type": "SkillEndorsement", "competencyLevel": "guru"}));
+        assert!(vec.endorsement().is_err());
+        assert!(
+            EndorsementRegistry::default()
+                .validate_credential(&vec)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_registry() {
+        #[derive(Deserialize)]
+        struct LanguageEndorsement {
+            language: String,
+        }
+
+        let mut registry = EndorsementRegistry::default();
+        let custom = vec(json!({"type": "LanguageEndorsement", "language": "Māori"}));
+        assert!(registry.validate_credential(&custom).is_err());
+
+        registry.register_type::<LanguageEndorsement>("LanguageEndorsement");
+        assert!(matches!(
+            registry.validate_credential(&custom).unwrap(),
+            Endorsement::Other(_)
+        ));
+        assert_eq!(
+            custom
+                .endorsement_as::<LanguageEndorsement>()
+                .unwrap()
+                .unwrap()
+                .language,
+            "Māori"
+        );
+        assert!(
+            registry
+                .validate(&json!({"type": "LanguageEndorsement"}))
+                .is_err()
+        );
+
+        registry.register("RatingEndorsement", |value| {
+            match value.get("rating").and_then(|r| r.as_u64()) {
+                Some(1..=5) => Ok(()),
+                _ => Err("rating must be 1 to 5".to_string()),
+            }
+        });
+        assert!(
+            registry
+                .validate(&json!({"type": "RatingEndorsement", "rating": 4}))
+                .is_ok()
+        );
+        assert!(
+            registry
+                .validate(&json!({"type": "RatingEndorsement", "rating": 9}))
+                .is_err()
+        );
+
+        assert!(registry.validate(&json!({"name": "no type"})).is_err());
+        assert!(!EndorsementRegistry::empty().is_registered("SkillEndorsement"));
+
+        let skill = SkillEndorsement {
+            name: "Rust".to_string(),
+            competency_level: None,
+            description: None,
+            additional: Map::new(),
+        };
+        assert!(registry.validate(&skill.into()).is_ok());
+    }
+}
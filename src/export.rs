@@ -0,0 +1,422 @@
+/*!
+*   Export the trust graph for visualisation and analysis.
+*
+*   - [TrustGraph::to_dot]: Graphviz DOT
+*   - [TrustGraph::to_graphml]: GraphML, e.g. for Gephi or NetworkX
+*   - [TrustGraph::to_json_ld]: JSON-LD document, an array of the credentials
+*   - [TrustGraph::to_nquads]: RDFC-1.0 canonical N-Quads of the JSON-LD document
+*
+*   DOT and GraphML nodes are DIDs labelled with what they issued and received, edges are
+*   credentials labelled with their type, issuer, validity and digest. An [ExportFilter] selects
+*   the credentials by type and validity window, only DIDs connected by a selected credential are
+*   exported. Output is ordered by DID and digest so exports of the same graph are identical.
+*/
+
+use crate::{
+    DTGCredentialError, DTGCredentialType,
+    graph::{TrustEdge, TrustGraph, graph_error},
+    rdfc,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::Value;
+use std::{collections::BTreeMap, fmt::Write};
+
+/// Selects the credentials to export
+#[derive(Clone, Debug)]
+pub struct ExportFilter {
+    /// Credential types to export
+    pub types: Vec<DTGCredentialType>,
+
+    /// Only credentials valid at some point from this time
+    pub from: Option<DateTime<Utc>>,
+
+    /// Only credentials valid at some point until this time
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl Default for ExportFilter {
+    fn default() -> Self {
+        ExportFilter {
+            types: vec![
+                DTGCredentialType::Relationship,
+                DTGCredentialType::Membership,
+                DTGCredentialType::Endorsement,
+                DTGCredentialType::Witness,
+            ],
+            from: None,
+            until: None,
+        }
+    }
+}
+
+impl ExportFilter {
+    /// Every credential in the graph
+    pub fn new() -> Self {
+        ExportFilter::default()
+    }
+
+    /// Sets the credential types to export
+    pub fn with_types(mut self, types: &[DTGCredentialType]) -> Self {
+        self.types = types.to_vec();
+        self
+    }
+
+    /// Only exports credentials valid at some point between from and until
+    pub fn with_window(mut self, from: DateTime<Utc>, until: DateTime<Utc>) -> Self {
+        self.from = Some(from);
+        self.until = Some(until);
+        self
+    }
+
+    /// Only exports credentials valid at this time
+    pub fn with_time(self, at: DateTime<Utc>) -> Self {
+        self.with_window(at, at)
+    }
+
+    /// Does the credential pass the filter?
+    pub fn matches(&self, edge: &TrustEdge) -> bool {
+        let credential = edge.credential().credential();
+        self.types.contains(&edge.type_())
+            && self
+                .until
+                .is_none_or(|until| credential.valid_from <= until)
+            && self.from.is_none_or(|from| {
+                credential
+                    .valid_until
+                    .is_none_or(|valid_until| valid_until >= from)
+            })
+    }
+}
+
+/// What a DID issued and received in the export
+#[derive(Default)]
+struct NodeLabel {
+    issued: usize,
+    received: usize,
+
+    /// Issued a VMC
+    community: bool,
+
+    /// Received a PHC
+    personhood: bool,
+}
+
+fn abbreviation(type_: DTGCredentialType) -> &'static str {
+    match type_ {
+        DTGCredentialType::Relationship => "VRC",
+        DTGCredentialType::Membership => "VMC",
+        DTGCredentialType::Endorsement => "VEC",
+        DTGCredentialType::Witness => "VWC",
+        DTGCredentialType::Invitation => "VIC",
+        DTGCredentialType::Persona => "VPC",
+        DTGCredentialType::RCard => "RCard",
+    }
+}
+
+fn timestamp(at: &DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Edge attributes shared by DOT and GraphML
+fn edge_attributes(edge: &TrustEdge) -> Vec<(&'static str, String)> {
+    let credential = edge.credential().credential();
+    let mut attributes = vec![
+        ("label", abbreviation(edge.type_()).to_string()),
+        ("type", edge.type_().to_string()),
+        ("issuer", edge.from().to_string()),
+        ("digest", edge.digest().to_string()),
+        ("valid_from", timestamp(&credential.valid_from)),
+    ];
+    if let Some(valid_until) = &credential.valid_until {
+        attributes.push(("valid_until", timestamp(valid_until)));
+    }
+    if edge.credential().is_personhood_credential() {
+        attributes.push(("personhood", "true".to_string()));
+    }
+    if let Some(witnessed) = edge.witnessed() {
+        attributes.push(("witnessed", witnessed.to_string()));
+    }
+    attributes
+}
+
+/// Node attributes shared by DOT and GraphML
+fn node_attributes(did: &str, label: &NodeLabel) -> Vec<(&'static str, String)> {
+    vec![
+        ("label", did.to_string()),
+        ("issued", label.issued.to_string()),
+        ("received", label.received.to_string()),
+        ("community", label.community.to_string()),
+        ("personhood", label.personhood.to_string()),
+    ]
+}
+
+fn dot_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// GraphML keys: (id, for, type)
+const GRAPHML_KEYS: [(&str, &str, &str); 13] = [
+    ("label", "node", "string"),
+    ("issued", "node", "int"),
+    ("received", "node", "int"),
+    ("community", "node", "boolean"),
+    ("personhood", "node", "boolean"),
+    ("label", "edge", "string"),
+    ("type", "edge", "string"),
+    ("issuer", "edge", "string"),
+    ("digest", "edge", "string"),
+    ("valid_from", "edge", "string"),
+    ("valid_until", "edge", "string"),
+    ("personhood", "edge", "boolean"),
+    ("witnessed", "edge", "string"),
+];
+
+/// DIDs connected by the exported credentials
+fn exported_nodes<'a>(edges: &[&'a TrustEdge]) -> BTreeMap<&'a str, NodeLabel> {
+    let mut nodes: BTreeMap<&str, NodeLabel> = BTreeMap::new();
+    for edge in edges {
+        let issuer = nodes.entry(edge.from()).or_default();
+        issuer.issued += 1;
+        issuer.community |= edge.type_() == DTGCredentialType::Membership;
+
+        let subject = nodes.entry(edge.to()).or_default();
+        subject.received += 1;
+        subject.personhood |= edge.credential().is_personhood_credential();
+    }
+    nodes
+}
+
+impl TrustGraph {
+    /// Credentials passing the filter, ordered by digest
+    fn exported(&self, filter: &ExportFilter) -> Vec<&TrustEdge> {
+        self.edges().filter(|edge| filter.matches(edge)).collect()
+    }
+
+    /// Graphviz DOT digraph, edges are coloured by credential type
+    pub fn to_dot(&self, filter: &ExportFilter) -> String {
+        let edges = self.exported(filter);
+        let mut dot = String::from("digraph dtg {\n");
+
+        for (did, label) in exported_nodes(&edges) {
+            let attributes = node_attributes(did, &label)
+                .into_iter()
+                .map(|(key, value)| format!("{key}=\"{}\"", dot_escape(&value)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let _ = writeln!(dot, "  \"{}\" [{attributes}];", dot_escape(did));
+        }
+
+        for edge in edges {
+            let style = match edge.type_() {
+                DTGCredentialType::Relationship => "color=\"black\"",
+                DTGCredentialType::Membership => "color=\"blue\"",
+                DTGCredentialType::Endorsement => "color=\"darkgreen\"",
+                _ => "color=\"gray\", style=\"dashed\"",
+            };
+            let attributes = edge_attributes(edge)
+                .into_iter()
+                .map(|(key, value)| format!("{key}=\"{}\"", dot_escape(&value)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let _ = writeln!(
+                dot,
+                "  \"{}\" -> \"{}\" [{attributes}, {style}];",
+                dot_escape(edge.from()),
+                dot_escape(edge.to())
+            );
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// GraphML directed graph, edges are identified by the credential digest
+    pub fn to_graphml(&self, filter: &ExportFilter) -> String {
+        let edges = self.exported(filter);
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+        );
+        for (id, for_, type_) in GRAPHML_KEYS {
+            let _ = writeln!(
+                xml,
+                "  <key id=\"{for_}_{id}\" for=\"{for_}\" attr.name=\"{id}\" attr.type=\"{type_}\"/>"
+            );
+        }
+        xml.push_str("  <graph id=\"dtg\" edgedefault=\"directed\">\n");
+
+        for (did, label) in exported_nodes(&edges) {
+            let _ = writeln!(xml, "    <node id=\"{}\">", xml_escape(did));
+            for (key, value) in node_attributes(did, &label) {
+                let _ = writeln!(
+                    xml,
+                    "      <data key=\"node_{key}\">{}</data>",
+                    xml_escape(&value)
+                );
+            }
+            xml.push_str("    </node>\n");
+        }
+
+        for edge in edges {
+            let _ = writeln!(
+                xml,
+                "    <edge id=\"{}\" source=\"{}\" target=\"{}\">",
+                xml_escape(edge.digest()),
+                xml_escape(edge.from()),
+                xml_escape(edge.to())
+            );
+            for (key, value) in edge_attributes(edge) {
+                let _ = writeln!(
+                    xml,
+                    "      <data key=\"edge_{key}\">{}</data>",
+                    xml_escape(&value)
+                );
+            }
+            xml.push_str("    </edge>\n");
+        }
+
+        xml.push_str("  </graph>\n</graphml>\n");
+        xml
+    }
+
+    /// JSON-LD document, an array of the exported credentials including their proofs
+    /// Each credential keeps its own `@context`
+    pub fn to_json_ld(&self, filter: &ExportFilter) -> Result<Value, DTGCredentialError> {
+        let credentials = self
+            .exported(filter)
+            .into_iter()
+            .map(|edge| {
+                serde_json::to_value(edge.credential())
+                    .map_err(|e| graph_error(format!("Couldn't serialize credential: {e}")))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Value::Array(credentials))
+    }
+
+    /// RDFC-1.0 canonical N-Quads of [TrustGraph::to_json_ld]
+    /// Proofs are in named graphs, contexts are resolved with the shared loader
+    pub fn to_nquads(&self, filter: &ExportFilter) -> Result<String, DTGCredentialError> {
+        rdfc::canonicalize(&self.to_json_ld(filter)?)
+    }
+}
+
+#[cfg(all(test, feature = "affinidi-signing"))]
+mod tests {
+    use super::ExportFilter;
+    use crate::{
+        DTGCredential, DTGCredentialType,
+        graph::TrustGraph,
+        test_utils::{did, signed, vmc, vrc},
+    };
+    use chrono::{Duration, Utc};
+
+    /// alice -> bob VRC, club -> alice PHC, carol -> bob VRC that expired a month ago
+    fn graph() -> (TrustGraph, String, String) {
+        let mut graph = TrustGraph::new();
+        let alice_bob = graph.add(vrc("alice", "bob")).unwrap();
+        graph.add(vmc("club", "alice", true)).unwrap();
+        let expired = graph
+            .add(signed(DTGCredential::new_vrc(
+                did("carol"),
+                did("bob"),
+                Utc::now() - Duration::days(365),
+                Some(Utc::now() - Duration::days(30)),
+            )))
+            .unwrap();
+        (graph, alice_bob, expired)
+    }
+
+    #[test]
+    fn test_dot() {
+        let (graph, vrc, expired) = graph();
+        let dot = graph.to_dot(&ExportFilter::new());
+
+        assert!(dot.starts_with("digraph dtg {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("\"did:example:alice\" -> \"did:example:bob\" [label=\"VRC\""));
+        assert!(dot.contains(&format!("digest=\"{vrc}\"")));
+        assert!(dot.contains(&format!("digest=\"{expired}\"")));
+        assert!(dot.contains(
+            "\"did:example:club\" [label=\"did:example:club\", issued=\"1\", received=\"0\", community=\"true\""
+        ));
+        assert!(dot.contains("personhood=\"true\", color=\"blue\""));
+
+        // The expired VRC and carol are filtered out
+        let dot = graph.to_dot(&ExportFilter::new().with_time(Utc::now()));
+        assert!(!dot.contains(&expired));
+        assert!(!dot.contains("did:example:carol"));
+
+        let dot = graph.to_dot(
+            &ExportFilter::new()
+                .with_types(&[DTGCredentialType::Membership])
+                .with_window(Utc::now() - Duration::days(400), Utc::now()),
+        );
+        assert_eq!(dot.matches(" -> ").count(), 1);
+        assert!(!dot.contains("did:example:bob"));
+    }
+
+    #[test]
+    fn test_graphml() {
+        let (graph, vrc, expired) = graph();
+        let xml = graph.to_graphml(&ExportFilter::new());
+
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<graphml"));
+        assert_eq!(xml.matches("<node ").count(), 4);
+        assert_eq!(xml.matches("<edge ").count(), 3);
+        assert!(xml.contains(&format!(
+            "<edge id=\"{vrc}\" source=\"did:example:alice\" target=\"did:example:bob\">"
+        )));
+        assert!(xml.contains("<data key=\"edge_type\">RelationshipCredential</data>"));
+        assert!(xml.contains("<data key=\"edge_valid_until\">"));
+
+        let xml = graph.to_graphml(
+            &ExportFilter::new().with_window(Utc::now() - Duration::days(7), Utc::now()),
+        );
+        assert_eq!(xml.matches("<edge ").count(), 2);
+        assert!(!xml.contains(&expired));
+    }
+
+    #[test]
+    fn test_escaping() {
+        let graph = TrustGraph::from_credentials([signed(DTGCredential::new_vrc(
+            "did:example:\"quoted\"".to_string(),
+            "did:example:<a&b>".to_string(),
+            Utc::now(),
+            None,
+        ))])
+        .unwrap();
+
+        assert!(
+            graph
+                .to_dot(&ExportFilter::new())
+                .contains("\"did:example:\\\"quoted\\\"\" -> \"did:example:<a&b>\"")
+        );
+        assert!(graph.to_graphml(&ExportFilter::new()).contains(
+            "source=\"did:example:&quot;quoted&quot;\" target=\"did:example:&lt;a&amp;b&gt;\""
+        ));
+    }
+
+    #[test]
+    fn test_json_ld() {
+        let (graph, _, _) = graph();
+        let filter = ExportFilter::new().with_time(Utc::now());
+
+        let document = graph.to_json_ld(&filter).unwrap();
+        assert_eq!(document.as_array().unwrap().len(), 2);
+
+        let nquads = graph.to_nquads(&filter).unwrap();
+        assert!(nquads.contains("<did:example:alice>"));
+        assert!(nquads.contains("<did:example:club>"));
+        assert!(!nquads.contains("<did:example:carol>"));
+        assert_eq!(nquads, graph.to_nquads(&filter).unwrap());
+    }
+}
@@ -0,0 +1,498 @@
+/*!
+*   Decentralized Trust Graph built from DTG Credentials.
+*
+*   A directed multigraph where DIDs are nodes and each credential is an edge from its issuer to
+*   its subject, labelled with the credential type and valid for the credential's validity period:
+*   - VRC: Relationship between issuer and subject
+*   - VMC: Membership of the subject in the issuing community
+*   - VEC: Endorsement of the subject by the issuer
+*   - VWC: Witness by the issuer, optionally bound to the credential it witnessed by `digest`
+*
+*   Edges are identified by the credential [DTGCredential::digest].
+*
+*   NOTE: Credentials must be verified before being added to the graph, unsigned credentials are
+*   rejected but proofs are not checked.
+*/
+
+use crate::{CredentialSubject, DTGCredential, DTGCredentialError, DTGCredentialType};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub(crate) fn graph_error(msg: impl Into<String>) -> DTGCredentialError {
+    DTGCredentialError::Graph(msg.into())
+}
+
+/// Direction of the edges to follow from a node
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    /// Credentials issued by the node
+    Outgoing,
+
+    /// Credentials issued about the node
+    Incoming,
+
+    /// Both issued by and about the node
+    Both,
+}
+
+/// A credential in the trust graph
+#[derive(Clone, Debug)]
+pub struct TrustEdge {
+    digest: String,
+    credential: DTGCredential,
+}
+
+impl TrustEdge {
+    /// Digest of the credential, identifies the edge
+    pub fn digest(&self) -> &str {
+        &self.digest
+    }
+
+    /// The credential backing this edge
+    pub fn credential(&self) -> &DTGCredential {
+        &self.credential
+    }
+
+    /// Credential type labelling this edge
+    pub fn type_(&self) -> DTGCredentialType {
+        self.credential.type_()
+    }
+
+    /// Issuer DID
+    pub fn from(&self) -> &str {
+        self.credential.issuer()
+    }
+
+    /// Subject DID
+    pub fn to(&self) -> &str {
+        self.credential.subject()
+    }
+
+    /// Is the edge valid at this time?
+    pub fn is_valid_at(&self, at: DateTime<Utc>) -> bool {
+        self.credential.is_valid_at(at)
+    }
+
+    /// Digest of the credential a VWC witnessed
+    /// Returns None for other credential types or if the VWC isn't bound to a credential
+    pub fn witnessed(&self) -> Option<&str> {
+        witnessed_digest(&self.credential)
+    }
+}
+
+/// Digest of the credential a VWC witnessed, None for other credential types
+pub(crate) fn witnessed_digest(credential: &DTGCredential) -> Option<&str> {
+    match &credential.credential().credential_subject {
+        CredentialSubject::Witness(subject) => subject.digest.as_deref(),
+        _ => None,
+    }
+}
+
+/// Directed multigraph of DIDs connected by DTG Credentials
+#[derive(Clone, Debug, Default)]
+pub struct TrustGraph {
+    edges: BTreeMap<String, TrustEdge>,
+
+    /// DID => digests of credentials issued by the DID
+    outgoing: BTreeMap<String, BTreeSet<String>>,
+
+    /// DID => digests of credentials issued about the DID
+    incoming: BTreeMap<String, BTreeSet<String>>,
+
+    /// Credential digest => digests of the VWCs witnessing it
+    witnessed: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl TrustGraph {
+    /// Empty trust graph
+    pub fn new() -> Self {
+        TrustGraph::default()
+    }
+
+    /// credentials: Verified VRC, VMC, VEC and VWC credentials
+    pub fn from_credentials(
+        credentials: impl IntoIterator<Item = DTGCredential>,
+    ) -> Result<Self, DTGCredentialError> {
+        let mut graph = TrustGraph::new();
+        for credential in credentials {
+            graph.add(credential)?;
+        }
+        Ok(graph)
+    }
+
+    /// Adds a verified credential as an edge, duplicates are ignored
+    /// Only VRC, VMC, VEC and VWC credentials are edges
+    /// Returns the digest identifying the edge
+    pub fn add(&mut self, credential: DTGCredential) -> Result<String, DTGCredentialError> {
+        if !credential.signed() {
+            return Err(DTGCredentialError::NotSigned);
+        }
+        match credential.type_() {
+            DTGCredentialType::Relationship
+            | DTGCredentialType::Membership
+            | DTGCredentialType::Endorsement
+            | DTGCredentialType::Witness => {}
+            other => {
+                return Err(graph_error(format!("{other} is not a trust graph edge")));
+            }
+        }
+        if credential.issuer().is_empty() || credential.subject().is_empty() {
+            return Err(graph_error("Credential must have an issuer and a subject"));
+        }
+
+        let digest = credential.digest()?;
+        if self.edges.contains_key(&digest) {
+            return Ok(digest);
+        }
+
+        self.outgoing
+            .entry(credential.issuer().to_string())
+            .or_default()
+            .insert(digest.clone());
+        self.incoming
+            .entry(credential.subject().to_string())
+            .or_default()
+            .insert(digest.clone());
+        let edge = TrustEdge {
+            digest: digest.clone(),
+            credential,
+        };
+        if let Some(witnessed) = edge.witnessed() {
+            self.witnessed
+                .entry(witnessed.to_string())
+                .or_default()
+                .insert(digest.clone());
+        }
+        self.edges.insert(digest.clone(), edge);
+        Ok(digest)
+    }
+
+    /// Removes a credential, e.g. when it has been revoked
+    /// Returns the removed credential
+    pub fn remove(&mut self, digest: &str) -> Option<DTGCredential> {
+        let edge = self.edges.remove(digest)?;
+        for (index, key) in [
+            (&mut self.outgoing, Some(edge.from())),
+            (&mut self.incoming, Some(edge.to())),
+            (&mut self.witnessed, edge.witnessed()),
+        ] {
+            let Some(key) = key else {
+                continue;
+            };
+            if let Some(digests) = index.get_mut(key) {
+                digests.remove(digest);
+                if digests.is_empty() {
+                    index.remove(key);
+                }
+            }
+        }
+        Some(edge.credential)
+    }
+
+    /// Finds an edge by the digest of its credential
+    pub fn get(&self, digest: &str) -> Option<&TrustEdge> {
+        self.edges.get(digest)
+    }
+
+    /// Is the credential in the graph?
+    pub fn contains(&self, digest: &str) -> bool {
+        self.edges.contains_key(digest)
+    }
+
+    /// All DIDs in the graph, sorted
+    pub fn nodes(&self) -> Vec<&str> {
+        self.outgoing
+            .keys()
+            .chain(self.incoming.keys())
+            .map(String::as_str)
+            .collect::<BTreeSet<&str>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// All edges, ordered by digest
+    pub fn edges(&self) -> impl Iterator<Item = &TrustEdge> {
+        self.edges.values()
+    }
+
+    /// Number of DIDs in the graph
+    pub fn node_count(&self) -> usize {
+        self.nodes().len()
+    }
+
+    /// Number of credentials in the graph
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Is the graph empty?
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty()
+    }
+
+    /// Edges connected to a DID, ordered by digest
+    /// type_: Only edges of this credential type, all types if None
+    pub fn edges_of(
+        &self,
+        did: &str,
+        type_: Option<&DTGCredentialType>,
+        direction: Direction,
+    ) -> Vec<&TrustEdge> {
+        let outgoing = matches!(direction, Direction::Outgoing | Direction::Both)
+            .then(|| self.outgoing.get(did))
+            .flatten();
+        let incoming = matches!(direction, Direction::Incoming | Direction::Both)
+            .then(|| self.incoming.get(did))
+            .flatten();
+
+        // A self-issued credential is both outgoing and incoming
+        outgoing
+            .into_iter()
+            .chain(incoming)
+            .flatten()
+            .collect::<BTreeSet<&String>>()
+            .into_iter()
+            .filter_map(|digest| self.edges.get(digest))
+            .filter(|edge| type_.is_none_or(|t| edge.type_() == *t))
+            .collect()
+    }
+
+    /// DIDs connected to a DID, sorted
+    /// type_: Only follow edges of this credential type, all types if None
+    /// at: Only follow edges valid at this time, all edges if None
+    pub fn neighbours(
+        &self,
+        did: &str,
+        type_: Option<&DTGCredentialType>,
+        direction: Direction,
+        at: Option<DateTime<Utc>>,
+    ) -> Vec<&str> {
+        self.edges_of(did, type_, direction)
+            .into_iter()
+            .filter(|edge| at.is_none_or(|at| edge.is_valid_at(at)))
+            .map(|edge| {
+                if edge.from() == did {
+                    edge.to()
+                } else {
+                    edge.from()
+                }
+            })
+            .collect::<BTreeSet<&str>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Edges issued by `from` about `to`
+    pub fn edges_between(&self, from: &str, to: &str) -> Vec<&TrustEdge> {
+        self.edges_of(from, None, Direction::Outgoing)
+            .into_iter()
+            .filter(|edge| edge.to() == to)
+            .collect()
+    }
+
+    /// VRCs between two DIDs, in either direction, that are valid at this time
+    pub fn relationships(&self, a: &str, b: &str, at: DateTime<Utc>) -> Vec<&TrustEdge> {
+        self.edges_between(a, b)
+            .into_iter()
+            .chain(self.edges_between(b, a))
+            .filter(|edge| edge.type_() == DTGCredentialType::Relationship && edge.is_valid_at(at))
+            .collect()
+    }
+
+    /// Does a VRC between two DIDs, in either direction, exist at this time?
+    pub fn has_relationship(&self, a: &str, b: &str, at: DateTime<Utc>) -> bool {
+        !self.relationships(a, b, at).is_empty()
+    }
+
+    /// VWCs bound to a credential by its digest that are valid at this time
+    /// The witnessed credential doesn't need to be in the graph
+    pub fn witnesses(&self, digest: &str, at: DateTime<Utc>) -> Vec<&TrustEdge> {
+        self.witnessed
+            .get(digest)
+            .into_iter()
+            .flatten()
+            .filter_map(|witness| self.edges.get(witness))
+            .filter(|witness| witness.is_valid_at(at))
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "affinidi-signing"))]
+mod tests {
+    use super::{Direction, TrustGraph};
+    use crate::{
+        DTGCredential, DTGCredentialError, DTGCredentialType,
+        test_utils::{did, signed, vmc, vrc, vwc},
+    };
+    use chrono::{Duration, Utc};
+    use serde_json::json;
+
+    /// alice <-> bob, club -> alice (VMC), carol -> bob (VEC), dave witnesses alice -> bob
+    fn graph() -> (TrustGraph, String) {
+        let mut graph = TrustGraph::new();
+        let alice_bob = graph.add(vrc("alice", "bob")).unwrap();
+        graph.add(vrc("bob", "alice")).unwrap();
+        graph.add(vmc("club", "alice", false)).unwrap();
+        graph
+            .add(signed(DTGCredential::new_vec(
+                did("carol"),
+                did("bob"),
+                Utc::now() - Duration::days(1),
+                None,
+                json!({"type": "SkillEndorsement", "skill": "chess"}),
+            )))
+            .unwrap();
+        graph.add(vwc("dave", "bob", &alice_bob, None)).unwrap();
+        (graph, alice_bob)
+    }
+
+    #[test]
+    fn test_build() {
+        let (graph, _) = graph();
+        assert_eq!(graph.edge_count(), 5);
+        assert_eq!(
+            graph.nodes(),
+            vec![
+                "did:example:alice",
+                "did:example:bob",
+                "did:example:carol",
+                "did:example:club",
+                "did:example:dave"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_neighbours_by_type() {
+        let (graph, _) = graph();
+
+        assert_eq!(
+            graph.neighbours("did:example:alice", None, Direction::Both, None),
+            vec!["did:example:bob", "did:example:club"]
+        );
+        assert_eq!(
+            graph.neighbours(
+                "did:example:alice",
+                Some(&DTGCredentialType::Membership),
+                Direction::Incoming,
+                None
+            ),
+            vec!["did:example:club"]
+        );
+        assert_eq!(
+            graph.neighbours(
+                "did:example:bob",
+                Some(&DTGCredentialType::Endorsement),
+                Direction::Incoming,
+                None
+            ),
+            vec!["did:example:carol"]
+        );
+        assert!(
+            graph
+                .neighbours(
+                    "did:example:bob",
+                    Some(&DTGCredentialType::Endorsement),
+                    Direction::Outgoing,
+                    None
+                )
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_relationship_at_time() {
+        let mut graph = TrustGraph::new();
+        let now = Utc::now();
+        graph
+            .add(signed(DTGCredential::new_vrc(
+                "did:example:alice".to_string(),
+                "did:example:bob".to_string(),
+                now - Duration::days(10),
+                Some(now - Duration::days(5)),
+            )))
+            .unwrap();
+
+        assert!(graph.has_relationship(
+            "did:example:alice",
+            "did:example:bob",
+            now - Duration::days(7)
+        ));
+        assert!(graph.has_relationship(
+            "did:example:bob",
+            "did:example:alice",
+            now - Duration::days(7)
+        ));
+        assert!(!graph.has_relationship("did:example:alice", "did:example:bob", now));
+        assert!(!graph.has_relationship(
+            "did:example:alice",
+            "did:example:bob",
+            now - Duration::days(11)
+        ));
+        assert!(!graph.has_relationship(
+            "did:example:alice",
+            "did:example:carol",
+            now - Duration::days(7)
+        ));
+    }
+
+    #[test]
+    fn test_witnesses() {
+        let (graph, alice_bob) = graph();
+        let witnesses = graph.witnesses(&alice_bob, Utc::now());
+        assert_eq!(witnesses.len(), 1);
+        assert_eq!(witnesses[0].from(), "did:example:dave");
+    }
+
+    #[test]
+    fn test_remove() {
+        let (mut graph, alice_bob) = graph();
+        let witness = graph.witnesses(&alice_bob, Utc::now())[0]
+            .digest()
+            .to_string();
+
+        assert!(graph.remove(&alice_bob).is_some());
+        assert!(!graph.contains(&alice_bob));
+        assert!(graph.remove(&alice_bob).is_none());
+        // The reverse VRC remains
+        assert!(graph.has_relationship("did:example:alice", "did:example:bob", Utc::now()));
+
+        assert!(graph.remove(&witness).is_some());
+        assert!(graph.witnesses(&alice_bob, Utc::now()).is_empty());
+        assert!(!graph.nodes().contains(&"did:example:dave"));
+    }
+
+    #[test]
+    fn test_rejects() {
+        let mut graph = TrustGraph::new();
+
+        let unsigned = DTGCredential::new_vrc(
+            "did:example:alice".to_string(),
+            "did:example:bob".to_string(),
+            Utc::now(),
+            None,
+        );
+        assert!(matches!(
+            graph.add(unsigned),
+            Err(DTGCredentialError::NotSigned)
+        ));
+
+        let vpc = signed(DTGCredential::new_vpc(
+            "did:example:alice".to_string(),
+            "did:example:alice".to_string(),
+            Utc::now(),
+            None,
+        ));
+        assert!(matches!(graph.add(vpc), Err(DTGCredentialError::Graph(_))));
+        assert!(graph.is_empty());
+    }
+
+    #[test]
+    fn test_duplicates_ignored() {
+        let mut graph = TrustGraph::new();
+        let credential = vrc("alice", "bob");
+        let digest = graph.add(credential.clone()).unwrap();
+        assert_eq!(graph.add(credential).unwrap(), digest);
+        assert_eq!(graph.edge_count(), 1);
+    }
+}
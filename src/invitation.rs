@@ -0,0 +1,348 @@
+/*!
+*   Compact, QR friendly encoding of Verified Invitation Credentials (VIC).
+*
+*   The VIC is secured as COSE_Sign1 over deterministic CBOR (see [crate::cose]) and multibase
+*   (base64url) encoded. This can be carried as a `dtg:invite?` URI for deep links, or split over
+*   multiple URI frames when a single QR code would be too dense.
+*
+*   Single frame: `dtg:invite?v=1&c=<multibase>`
+*
+*   Multiple frames: `dtg:invite?v=1&id=<id>&i=<index>&n=<total>&c=<multibase chunk>`
+*
+*   Decoding and verifying requires no network access, only the issuer public key.
+*/
+
+use crate::{DTGCredential, DTGCredentialError, DTGCredentialType, signer::SigningKey};
+use multibase::Base;
+use sha2::{Digest, Sha256};
+
+/// URI scheme and path of an invitation
+pub const INVITE_URI_PREFIX: &str = "dtg:invite?";
+
+/// Invitation encoding version
+pub const INVITE_VERSION: &str = "1";
+
+/// Byte capacity of the largest QR code (version 40, ECC level L)
+pub const QR_MAX_BYTES: usize = 2953;
+
+/// Byte capacity of a version 20 QR code at ECC level M, reliably scanned by phone cameras
+pub const QR_RECOMMENDED_BYTES: usize = 666;
+
+fn invitation_error(msg: impl Into<String>) -> DTGCredentialError {
+    DTGCredentialError::Invitation(msg.into())
+}
+
+/// A signed, compact invitation
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompactInvitation {
+    /// COSE_Sign1 secured VIC
+    bytes: Vec<u8>,
+}
+
+impl CompactInvitation {
+    /// COSE_Sign1 bytes of the invitation
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Multibase (base64url) encoding of the invitation
+    pub fn encoded(&self) -> String {
+        multibase::encode(Base::Base64Url, &self.bytes)
+    }
+
+    /// Single `dtg:invite?` URI
+    pub fn to_uri(&self) -> String {
+        format!("{INVITE_URI_PREFIX}v={INVITE_VERSION}&c={}", self.encoded())
+    }
+
+    /// Checks that the single URI form fits within the byte budget
+    /// Returns the URI length
+    /// budget: Maximum number of bytes, e.g. [QR_RECOMMENDED_BYTES]
+    pub fn check_size(&self, budget: usize) -> Result<usize, DTGCredentialError> {
+        let size = self.to_uri().len();
+        if size > budget {
+            Err(invitation_error(format!(
+                "Invitation is {size} bytes, exceeds budget of {budget} bytes"
+            )))
+        } else {
+            Ok(size)
+        }
+    }
+
+    /// Splits the invitation into multiple URI frames, each no larger than frame_size bytes
+    /// Returns a single frame when the invitation fits
+    /// frame_size: Maximum number of bytes per frame
+    pub fn to_frames(&self, frame_size: usize) -> Result<Vec<String>, DTGCredentialError> {
+        if self.check_size(frame_size).is_ok() {
+            return Ok(vec![self.to_uri()]);
+        }
+
+        let encoded = self.encoded();
+        let id = self.frame_id();
+
+        // Worst case header uses the largest possible index and total
+        let mut total = 2;
+        loop {
+            let header = frame_header(&id, total, total).len();
+            if header >= frame_size {
+                return Err(invitation_error(format!(
+                    "Frame size of {frame_size} bytes is too small"
+                )));
+            }
+            let needed = encoded.len().div_ceil(frame_size - header);
+            if needed <= total {
+                return Ok(encoded
+                    .as_bytes()
+                    .chunks(frame_size - header)
+                    .enumerate()
+                    .map(|(index, chunk)| {
+                        format!(
+                            "{}{}",
+                            frame_header(&id, index + 1, needed),
+                            // base64url and multibase prefix are ASCII
+                            String::from_utf8_lossy(chunk)
+                        )
+                    })
+                    .collect());
+            }
+            total = needed;
+        }
+    }
+
+    /// Parses a single `dtg:invite?` URI
+    pub fn from_uri(uri: &str) -> Result<Self, DTGCredentialError> {
+        let frame = Frame::parse(uri)?;
+        if frame.total != 1 {
+            return Err(invitation_error(
+                "URI is one frame of a multi-frame invitation",
+            ));
+        }
+        Self::from_encoded(&frame.content)
+    }
+
+    /// Reassembles an invitation from its URI frames, frames may be in any order
+    pub fn from_frames<S: AsRef<str>>(frames: &[S]) -> Result<Self, DTGCredentialError> {
+        let mut frames = frames
+            .iter()
+            .map(|frame| Frame::parse(frame.as_ref()))
+            .collect::<Result<Vec<Frame>, _>>()?;
+        let Some(first) = frames.first() else {
+            return Err(invitation_error("No frames"));
+        };
+
+        let (id, total) = (first.id.clone(), first.total);
+        if frames.iter().any(|f| f.id != id || f.total != total) {
+            return Err(invitation_error("Frames belong to different invitations"));
+        }
+
+        frames.sort_by_key(|f| f.index);
+        frames.dedup_by_key(|f| f.index);
+        if frames.len() != total || frames.iter().enumerate().any(|(i, f)| f.index != i + 1) {
+            return Err(invitation_error(format!(
+                "Expected {total} frames, received {}",
+                frames.len()
+            )));
+        }
+
+        let invitation = Self::from_encoded(
+            &frames
+                .into_iter()
+                .map(|f| f.content)
+                .collect::<Vec<String>>()
+                .concat(),
+        )?;
+        if id.is_some_and(|id| id != invitation.frame_id()) {
+            return Err(invitation_error("Frame id doesn't match invitation"));
+        }
+        Ok(invitation)
+    }
+
+    /// Returns the issuer `kid` without verifying the invitation
+    /// Use this to find the issuer key before calling [CompactInvitation::verify]
+    pub fn key_id(&self) -> Result<Option<String>, DTGCredentialError> {
+        DTGCredential::vc_cose_key_id(&self.bytes)
+    }
+
+    /// Verifies the invitation and restores the VIC
+    /// public_key_bytes: The public key bytes of the issuer
+    pub fn verify(&self, public_key_bytes: &[u8]) -> Result<DTGCredential, DTGCredentialError> {
+        let vic = DTGCredential::from_vc_cose(&self.bytes, public_key_bytes)?;
+        if !matches!(vic.type_(), DTGCredentialType::Invitation) {
+            return Err(invitation_error("Credential is not a VIC"));
+        }
+        Ok(vic)
+    }
+
+    fn from_encoded(encoded: &str) -> Result<Self, DTGCredentialError> {
+        let (_, bytes) = multibase::decode(encoded)
+            .map_err(|e| invitation_error(format!("Invalid multibase encoding: {e}")))?;
+        Ok(CompactInvitation { bytes })
+    }
+
+    /// Short identifier linking the frames of an invitation
+    fn frame_id(&self) -> String {
+        Sha256::digest(&self.bytes)[..4]
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+}
+
+fn frame_header(id: &str, index: usize, total: usize) -> String {
+    format!("{INVITE_URI_PREFIX}v={INVITE_VERSION}&id={id}&i={index}&n={total}&c=")
+}
+
+/// A parsed `dtg:invite?` URI
+struct Frame {
+    id: Option<String>,
+    index: usize,
+    total: usize,
+    content: String,
+}
+
+impl Frame {
+    fn parse(uri: &str) -> Result<Self, DTGCredentialError> {
+        let query = uri
+            .trim()
+            .strip_prefix(INVITE_URI_PREFIX)
+            .ok_or_else(|| invitation_error("Not a dtg:invite URI"))?;
+
+        let mut version = None;
+        let mut frame = Frame {
+            id: None,
+            index: 1,
+            total: 1,
+            content: String::new(),
+        };
+        for param in query.split('&') {
+            let (key, value) = param
+                .split_once('=')
+                .ok_or_else(|| invitation_error(format!("Invalid URI parameter: {param}")))?;
+            match key {
+                "v" => version = Some(value),
+                "id" => frame.id = Some(value.to_string()),
+                "i" => frame.index = parse_number(value)?,
+                "n" => frame.total = parse_number(value)?,
+                "c" => frame.content = value.to_string(),
+                // Ignore unknown parameters for forward compatibility
+                _ => {}
+            }
+        }
+
+        if version != Some(INVITE_VERSION) {
+            return Err(invitation_error("Unsupported invitation version"));
+        }
+        if frame.content.is_empty() {
+            return Err(invitation_error("Missing invitation content"));
+        }
+        if frame.index == 0 || frame.index > frame.total {
+            return Err(invitation_error("Invalid frame index"));
+        }
+        Ok(frame)
+    }
+}
+
+fn parse_number(value: &str) -> Result<usize, DTGCredentialError> {
+    value
+        .parse()
+        .map_err(|_| invitation_error(format!("Invalid number: {value}")))
+}
+
+impl DTGCredential {
+    /// Creates a compact, signed invitation from a VIC
+    /// signing_secret: The key to sign with, see [SigningKey]
+    pub fn to_invitation(
+        &self,
+        signing_secret: &impl SigningKey,
+    ) -> Result<CompactInvitation, DTGCredentialError> {
+        if !matches!(self.type_, DTGCredentialType::Invitation) {
+            return Err(invitation_error(
+                "Only a VIC can be encoded as an invitation",
+            ));
+        }
+
+        Ok(CompactInvitation {
+            bytes: self.to_vc_cose(signing_secret)?,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "affinidi-signing"))]
+mod tests {
+    use super::{CompactInvitation, QR_RECOMMENDED_BYTES};
+    use crate::{DTGCredential, DTGCredentialType};
+    use affinidi_secrets_resolver::secrets::Secret;
+    use chrono::Utc;
+
+    fn vic() -> DTGCredential {
+        DTGCredential::new_vic(
+            "did:peer:2.Vz6MkgEF5ybLMXtCQiFGPUWqNJbtNfE1mhc1u4bXfhDEdbkZa".to_string(),
+            "did:peer:2.Vz6MkqTdLcxGC6vwaAk6dM5UcxDcLbTyeaxAXDGm7jjwmPD5D".to_string(),
+            Utc::now(),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_uri_round_trip() {
+        let secret = Secret::generate_ed25519(Some("did:example:issuer#key-1"), None);
+        let invitation = vic().to_invitation(&secret).unwrap();
+
+        let uri = invitation.to_uri();
+        assert!(uri.starts_with("dtg:invite?v=1&c=u"));
+        assert!(invitation.check_size(QR_RECOMMENDED_BYTES).is_ok());
+        let mut signed = vic();
+        signed.sign(&secret, None).unwrap();
+        assert!(uri.len() < serde_json::to_string(&signed).unwrap().len());
+
+        let decoded = CompactInvitation::from_uri(&uri).unwrap();
+        assert_eq!(
+            decoded.key_id().unwrap().as_deref(),
+            Some("did:example:issuer#key-1")
+        );
+        let vic = decoded.verify(secret.get_public_bytes()).unwrap();
+        assert!(matches!(vic.type_(), DTGCredentialType::Invitation));
+
+        let secret2 = Secret::generate_ed25519(None, None);
+        assert!(decoded.verify(secret2.get_public_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_frames() {
+        let secret = Secret::generate_ed25519(None, None);
+        let invitation = vic().to_invitation(&secret).unwrap();
+        assert!(invitation.check_size(100).is_err());
+
+        let mut frames = invitation.to_frames(100).unwrap();
+        assert!(frames.len() > 1);
+        assert!(frames.iter().all(|f| f.len() <= 100));
+
+        frames.reverse();
+        assert_eq!(CompactInvitation::from_frames(&frames).unwrap(), invitation);
+        assert!(CompactInvitation::from_uri(&frames[0]).is_err());
+
+        frames.pop();
+        assert!(CompactInvitation::from_frames(&frames).is_err());
+
+        assert!(invitation.to_frames(20).is_err());
+        assert_eq!(
+            invitation.to_frames(1000).unwrap(),
+            vec![invitation.to_uri()]
+        );
+    }
+
+    #[test]
+    fn test_not_vic() {
+        let secret = Secret::generate_ed25519(None, None);
+        let vrc = DTGCredential::new_vrc(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            Utc::now(),
+            None,
+        );
+        assert!(vrc.to_invitation(&secret).is_err());
+
+        assert!(CompactInvitation::from_uri("https://example.com").is_err());
+        assert!(CompactInvitation::from_uri("dtg:invite?v=2&c=uAA").is_err());
+    }
+}
@@ -0,0 +1,587 @@
+/*!
+*   Typed jCard (RFC 7095) model for RCard credential subjects.
+*
+*   <https://www.rfc-editor.org/rfc/rfc7095>
+*
+*   The RCard `card` remains a raw JSON value, [JCard] provides a validated view with accessors for
+*   common properties. Unknown properties, parameters and value types are preserved as is.
+*/
+
+use crate::{CredentialSubject, DTGCredential, DTGCredentialError};
+use serde_json::{Map, Value, json};
+use std::fmt::Display;
+
+fn jcard_error(msg: impl Into<String>) -> DTGCredentialError {
+    DTGCredentialError::JCard(msg.into())
+}
+
+/// jCard property value types (RFC 7095 section 3.5)
+#[derive(Clone, Debug, PartialEq)]
+pub enum JCardValueType {
+    Text,
+    Uri,
+    Date,
+    Time,
+    DateTime,
+    DateAndOrTime,
+    Timestamp,
+    Boolean,
+    Integer,
+    Float,
+    UtcOffset,
+    LanguageTag,
+    Unknown,
+    /// Extension value type
+    Other(String),
+}
+
+impl JCardValueType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            JCardValueType::Text => "text",
+            JCardValueType::Uri => "uri",
+            JCardValueType::Date => "date",
+            JCardValueType::Time => "time",
+            JCardValueType::DateTime => "date-time",
+            JCardValueType::DateAndOrTime => "date-and-or-time",
+            JCardValueType::Timestamp => "timestamp",
+            JCardValueType::Boolean => "boolean",
+            JCardValueType::Integer => "integer",
+            JCardValueType::Float => "float",
+            JCardValueType::UtcOffset => "utc-offset",
+            JCardValueType::LanguageTag => "language-tag",
+            JCardValueType::Unknown => "unknown",
+            JCardValueType::Other(other) => other,
+        }
+    }
+
+    /// Checks a single property value is valid for this value type
+    fn validate(&self, value: &Value) -> bool {
+        match self {
+            JCardValueType::Boolean => value.is_boolean(),
+            JCardValueType::Integer => value.is_i64(),
+            JCardValueType::Float => value.is_number(),
+            JCardValueType::Other(_) => true,
+            // Structured values are arrays of components (RFC 7095 section 3.3.1.3)
+            _ => match value {
+                Value::String(_) => true,
+                Value::Array(components) => components.iter().all(|component| match component {
+                    Value::String(_) => true,
+                    Value::Array(items) => items.iter().all(Value::is_string),
+                    _ => false,
+                }),
+                _ => false,
+            },
+        }
+    }
+}
+
+impl From<&str> for JCardValueType {
+    fn from(value: &str) -> Self {
+        match value {
+            "text" => JCardValueType::Text,
+            "uri" => JCardValueType::Uri,
+            "date" => JCardValueType::Date,
+            "time" => JCardValueType::Time,
+            "date-time" => JCardValueType::DateTime,
+            "date-and-or-time" => JCardValueType::DateAndOrTime,
+            "timestamp" => JCardValueType::Timestamp,
+            "boolean" => JCardValueType::Boolean,
+            "integer" => JCardValueType::Integer,
+            "float" => JCardValueType::Float,
+            "utc-offset" => JCardValueType::UtcOffset,
+            "language-tag" => JCardValueType::LanguageTag,
+            "unknown" => JCardValueType::Unknown,
+            other => JCardValueType::Other(other.to_string()),
+        }
+    }
+}
+
+impl Display for JCardValueType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A single jCard property: `[name, parameters, type, value, ...]`
+#[derive(Clone, Debug, PartialEq)]
+pub struct JCardProperty {
+    /// Lowercase property name
+    pub name: String,
+
+    /// Property parameters, values are a string or an array of strings
+    pub parameters: Map<String, Value>,
+
+    pub value_type: JCardValueType,
+
+    /// One or more values
+    pub values: Vec<Value>,
+}
+
+impl JCardProperty {
+    /// Creates a property with a single value and no parameters
+    pub fn new(name: &str, value_type: JCardValueType, value: Value) -> Self {
+        JCardProperty {
+            name: name.to_lowercase(),
+            parameters: Map::new(),
+            value_type,
+            values: vec![value],
+        }
+    }
+
+    /// Adds a parameter to the property
+    pub fn with_parameter(mut self, name: &str, value: &str) -> Self {
+        self.parameters
+            .insert(name.to_lowercase(), Value::String(value.to_string()));
+        self
+    }
+
+    /// First value as text, if it is a string
+    pub fn text(&self) -> Option<&str> {
+        self.values.first().and_then(|v| v.as_str())
+    }
+
+    /// The `pref` parameter (1 is most preferred), if any
+    pub fn pref(&self) -> Option<u8> {
+        match self.parameters.get("pref")? {
+            Value::String(pref) => pref.parse().ok(),
+            Value::Number(pref) => pref.as_u64().and_then(|p| u8::try_from(p).ok()),
+            _ => None,
+        }
+    }
+
+    /// The `type` parameter values, e.g. `work` or `home`
+    pub fn types(&self) -> Vec<&str> {
+        match self.parameters.get("type") {
+            Some(Value::String(type_)) => vec![type_.as_str()],
+            Some(Value::Array(types)) => types.iter().filter_map(|t| t.as_str()).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        let mut property = vec![
+            Value::String(self.name.clone()),
+            Value::Object(self.parameters.clone()),
+            Value::String(self.value_type.to_string()),
+        ];
+        property.extend(self.values.iter().cloned());
+        Value::Array(property)
+    }
+}
+
+impl TryFrom<&Value> for JCardProperty {
+    type Error = DTGCredentialError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        let Some(items) = value.as_array() else {
+            return Err(jcard_error("Property must be an array"));
+        };
+        if items.len() < 4 {
+            return Err(jcard_error(format!(
+                "Property must have a name, parameters, type and value: {value}"
+            )));
+        }
+
+        let Some(name) = items[0].as_str() else {
+            return Err(jcard_error("Property name must be a string"));
+        };
+        if name.is_empty() || name != name.to_lowercase() {
+            return Err(jcard_error(format!(
+                "Property name must be lowercase: {name}"
+            )));
+        }
+
+        let Some(parameters) = items[1].as_object() else {
+            return Err(jcard_error(format!(
+                "Parameters of {name} must be an object"
+            )));
+        };
+        for (parameter, value) in parameters {
+            let valid = match value {
+                Value::String(_) | Value::Number(_) => true,
+                Value::Array(values) => values.iter().all(Value::is_string),
+                _ => false,
+            };
+            if !valid || parameter != &parameter.to_lowercase() {
+                return Err(jcard_error(format!(
+                    "Invalid parameter {parameter} of {name}"
+                )));
+            }
+        }
+
+        let Some(value_type) = items[2].as_str() else {
+            return Err(jcard_error(format!(
+                "Value type of {name} must be a string"
+            )));
+        };
+        let value_type = JCardValueType::from(value_type);
+
+        let values = items[3..].to_vec();
+        if let Some(value) = values.iter().find(|v| !value_type.validate(v)) {
+            return Err(jcard_error(format!(
+                "Invalid {value_type} value for {name}: {value}"
+            )));
+        }
+
+        Ok(JCardProperty {
+            name: name.to_string(),
+            parameters: parameters.clone(),
+            value_type,
+            values,
+        })
+    }
+}
+
+/// A validated jCard
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct JCard {
+    properties: Vec<JCardProperty>,
+}
+
+impl JCard {
+    /// Start building a jCard, fn (formatted name) is required by vCard 4.0
+    pub fn builder(full_name: &str) -> JCardBuilder {
+        JCardBuilder::new(full_name)
+    }
+
+    /// All properties, in order
+    pub fn properties(&self) -> &[JCardProperty] {
+        &self.properties
+    }
+
+    /// All properties with the given name
+    pub fn properties_named(&self, name: &str) -> impl Iterator<Item = &JCardProperty> {
+        let name = name.to_lowercase();
+        self.properties.iter().filter(move |p| p.name == name)
+    }
+
+    /// Preferred property with the given name (lowest `pref`, otherwise the first)
+    pub fn property(&self, name: &str) -> Option<&JCardProperty> {
+        self.properties_named(name)
+            .enumerate()
+            .min_by_key(|(index, p)| (p.pref().unwrap_or(u8::MAX), *index))
+            .map(|(_, p)| p)
+    }
+
+    /// Adds a property, use this for extensions (`x-` properties)
+    pub fn add_property(&mut self, property: JCardProperty) {
+        self.properties.push(property);
+    }
+
+    fn text(&self, name: &str) -> Option<&str> {
+        self.property(name).and_then(|p| p.text())
+    }
+
+    fn texts(&self, name: &str) -> Vec<&str> {
+        self.properties_named(name)
+            .filter_map(|p| p.text())
+            .collect()
+    }
+
+    /// vCard version, `4.0` for jCard
+    pub fn version(&self) -> Option<&str> {
+        self.text("version")
+    }
+
+    /// Formatted name (`fn`)
+    pub fn full_name(&self) -> Option<&str> {
+        self.text("fn")
+    }
+
+    /// Preferred email address
+    pub fn email(&self) -> Option<&str> {
+        self.text("email")
+    }
+
+    /// All email addresses
+    pub fn emails(&self) -> Vec<&str> {
+        self.texts("email")
+    }
+
+    /// Preferred telephone number, may be a `tel:` URI
+    pub fn tel(&self) -> Option<&str> {
+        self.text("tel")
+    }
+
+    /// All telephone numbers
+    pub fn tels(&self) -> Vec<&str> {
+        self.texts("tel")
+    }
+
+    /// Photo URI (may be a `data:` URI)
+    pub fn photo(&self) -> Option<&str> {
+        self.text("photo")
+    }
+
+    /// Preferred URL
+    pub fn url(&self) -> Option<&str> {
+        self.text("url")
+    }
+
+    /// All URLs
+    pub fn urls(&self) -> Vec<&str> {
+        self.texts("url")
+    }
+
+    /// Organization name followed by any organizational units
+    pub fn org(&self) -> Option<Vec<&str>> {
+        match self.property("org")?.values.first()? {
+            Value::String(org) => Some(vec![org.as_str()]),
+            Value::Array(units) => Some(units.iter().filter_map(|u| u.as_str()).collect()),
+            _ => None,
+        }
+    }
+
+    /// Converts back to the raw jCard JSON
+    pub fn to_value(&self) -> Value {
+        json!([
+            "vcard",
+            self.properties
+                .iter()
+                .map(JCardProperty::to_value)
+                .collect::<Vec<Value>>()
+        ])
+    }
+}
+
+impl TryFrom<&Value> for JCard {
+    type Error = DTGCredentialError;
+
+    /// Parses and validates a jCard against the RFC 7095 structure
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        let Some([Value::String(vcard), Value::Array(properties)]) =
+            value.as_array().map(|v| v.as_slice())
+        else {
+            return Err(jcard_error(
+                "jCard must be an array of \"vcard\" and properties",
+            ));
+        };
+        if vcard != "vcard" {
+            return Err(jcard_error("jCard must start with \"vcard\""));
+        }
+
+        let card = JCard {
+            properties: properties
+                .iter()
+                .map(JCardProperty::try_from)
+                .collect::<Result<Vec<JCardProperty>, _>>()?,
+        };
+
+        match card.version() {
+            None | Some("4.0") => Ok(card),
+            Some(version) => Err(jcard_error(format!("Unsupported vCard version: {version}"))),
+        }
+    }
+}
+
+impl From<JCard> for Value {
+    fn from(card: JCard) -> Self {
+        card.to_value()
+    }
+}
+
+/// Builds a jCard, the version property is added automatically
+pub struct JCardBuilder {
+    card: JCard,
+}
+
+impl JCardBuilder {
+    pub fn new(full_name: &str) -> Self {
+        let mut card = JCard::default();
+        card.add_property(JCardProperty::new(
+            "version",
+            JCardValueType::Text,
+            json!("4.0"),
+        ));
+        card.add_property(JCardProperty::new(
+            "fn",
+            JCardValueType::Text,
+            json!(full_name),
+        ));
+        JCardBuilder { card }
+    }
+
+    pub fn email(mut self, email: &str) -> Self {
+        self.card.add_property(JCardProperty::new(
+            "email",
+            JCardValueType::Text,
+            json!(email),
+        ));
+        self
+    }
+
+    /// tel: Telephone number, `tel:` URIs are typed as uri
+    pub fn tel(mut self, tel: &str) -> Self {
+        let value_type = if tel.starts_with("tel:") {
+            JCardValueType::Uri
+        } else {
+            JCardValueType::Text
+        };
+        self.card
+            .add_property(JCardProperty::new("tel", value_type, json!(tel)));
+        self
+    }
+
+    pub fn photo(mut self, uri: &str) -> Self {
+        self.card
+            .add_property(JCardProperty::new("photo", JCardValueType::Uri, json!(uri)));
+        self
+    }
+
+    pub fn url(mut self, uri: &str) -> Self {
+        self.card
+            .add_property(JCardProperty::new("url", JCardValueType::Uri, json!(uri)));
+        self
+    }
+
+    /// units: Organization name followed by any organizational units
+    pub fn org(mut self, units: &[&str]) -> Self {
+        let value = match units {
+            [org] => json!(org),
+            units => json!(units),
+        };
+        self.card
+            .add_property(JCardProperty::new("org", JCardValueType::Text, value));
+        self
+    }
+
+    /// Adds any other property
+    pub fn property(mut self, property: JCardProperty) -> Self {
+        self.card.add_property(property);
+        self
+    }
+
+    pub fn build(self) -> JCard {
+        self.card
+    }
+}
+
+impl DTGCredential {
+    /// Typed view of an RCard's jCard
+    /// Returns None if this is not an RCard
+    pub fn jcard(&self) -> Result<Option<JCard>, DTGCredentialError> {
+        match &self.credential.credential_subject {
+            CredentialSubject::RCard(subject) => Ok(Some(JCard::try_from(&subject.card)?)),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JCard, JCardProperty, JCardValueType};
+    use crate::DTGCredential;
+    use chrono::Utc;
+    use serde_json::json;
+
+    #[test]
+    fn test_rfc7095_example() {
+        let value = json!(["vcard",
+          [
+            ["version", {}, "text", "4.0"],
+            ["fn", {}, "text", "Simon Perreault"],
+            ["n", {}, "text", ["Perreault", "Simon", "", "", ["ing. jr", "M.Sc."]]],
+            ["bday", {}, "date-and-or-time", "--02-03"],
+            ["gender", {}, "text", ["M", ""]],
+            ["lang", {"pref": "1"}, "language-tag", "fr"],
+            ["org", {"type": "work"}, "text", "Viagenie"],
+            ["tel", {"type": ["work", "voice"], "pref": "1"}, "uri", "tel:+1-418-656-9254;ext=102"],
+            ["tel", {"type": ["work", "cell", "voice", "video", "text"]}, "uri", "tel:+1-418-262-6501"],
+            ["email", {"type": "work"}, "text", "simon.perreault@viagenie.ca"],
+            ["x-custom", {}, "unknown", "kept"],
+            ["url", {"type": "home"}, "uri", "http://nomis80.org"]
+          ]
+        ]);
+
+        let card = JCard::try_from(&value).unwrap();
+        assert_eq!(card.full_name(), Some("Simon Perreault"));
+        assert_eq!(card.email(), Some("simon.perreault@viagenie.ca"));
+        assert_eq!(card.tel(), Some("tel:+1-418-656-9254;ext=102"));
+        assert_eq!(card.tels().len(), 2);
+        assert_eq!(card.org(), Some(vec!["Viagenie"]));
+        assert_eq!(card.url(), Some("http://nomis80.org"));
+        assert_eq!(card.photo(), None);
+        assert_eq!(card.property("email").unwrap().types(), vec!["work"]);
+        assert_eq!(card.property("x-custom").unwrap().text(), Some("kept"));
+
+        assert_eq!(card.to_value(), value);
+    }
+
+    #[test]
+    fn test_preferred() {
+        let card = JCard::try_from(&json!(["vcard", [
+            ["email", {}, "text", "other@example.com"],
+            ["email", {"pref": "1"}, "text", "preferred@example.com"]
+        ]]))
+        .unwrap();
+
+        assert_eq!(card.email(), Some("preferred@example.com"));
+        assert_eq!(
+            card.emails(),
+            vec!["other@example.com", "preferred@example.com"]
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        for value in [
+            json!([]),
+            json!({"fn": "Alice"}),
+            json!(["vcard"]),
+            json!(["jcard", []]),
+            json!(["vcard", [["fn", {}, "text"]]]),
+            json!(["vcard", [["FN", {}, "text", "Alice"]]]),
+            json!(["vcard", [["fn", [], "text", "Alice"]]]),
+            json!(["vcard", [["fn", {"pref": {}}, "text", "Alice"]]]),
+            json!(["vcard", [["fn", {}, "text", 42]]]),
+            json!(["vcard", [["x-flag", {}, "boolean", "true"]]]),
+            json!(["vcard", [["x-count", {}, "integer", 1.5]]]),
+            json!(["vcard", [["version", {}, "text", "3.0"]]]),
+        ] {
+            assert!(JCard::try_from(&value).is_err(), "{value}");
+        }
+    }
+
+    #[test]
+    fn test_builder_rcard() {
+        let card = JCard::builder("Alice Smith")
+            .email("alice@example.com")
+            .tel("tel:+1-555-555-0100")
+            .photo("https://example.com/alice.png")
+            .url("https://alice.example.com")
+            .org(&["Example Corp", "Engineering"])
+            .property(
+                JCardProperty::new("x-pronouns", JCardValueType::Text, json!("she/her"))
+                    .with_parameter("pref", "1"),
+            )
+            .build();
+
+        let rcard = DTGCredential::new_rcard(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            Utc::now(),
+            None,
+            card.clone(),
+        );
+
+        let parsed = rcard.jcard().unwrap().unwrap();
+        assert_eq!(parsed, card);
+        assert_eq!(parsed.version(), Some("4.0"));
+        assert_eq!(parsed.full_name(), Some("Alice Smith"));
+        assert_eq!(parsed.tel(), Some("tel:+1-555-555-0100"));
+        assert_eq!(parsed.org(), Some(vec!["Example Corp", "Engineering"]));
+        assert_eq!(
+            parsed.property("x-pronouns").unwrap().value_type,
+            JCardValueType::Text
+        );
+
+        let vrc = DTGCredential::new_vrc(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            Utc::now(),
+            None,
+        );
+        assert!(vrc.jcard().unwrap().is_none());
+    }
+}
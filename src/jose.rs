@@ -0,0 +1,139 @@
+/*!
+*   VC-JOSE enveloped DTG Credentials (`application/vc+jwt`).
+*
+*   <https://www.w3.org/TR/vc-jose-cose/#securing-with-jose>
+*
+*   The JWS payload is the unsecured credential itself, this is an alternative to attaching a
+*   Data Integrity proof.
+*/
+
+use crate::{
+    DTGCommon, DTGCredential, DTGCredentialError,
+    jws::{decode_compact, sign_compact},
+    signer::{Signer, SigningKey},
+};
+use serde_json::json;
+
+/// JOSE `typ` header of an enveloped credential
+pub const VC_JWT_TYPE: &str = "vc+jwt";
+
+/// Media type of an enveloped credential
+pub const VC_JWT_MEDIA_TYPE: &str = "application/vc+jwt";
+
+impl DTGCredential {
+    /// Secure the credential as a compact JWS (VC-JOSE)
+    /// Any Data Integrity proof is removed from the payload
+    /// The credential itself is not modified
+    /// signing_secret: The key to sign with, see [SigningKey]
+    pub fn to_vc_jwt(
+        &self,
+        signing_secret: &impl SigningKey,
+    ) -> Result<String, DTGCredentialError> {
+        let signer = signing_secret.signer()?;
+        let payload = serde_json::to_value(DTGCommon {
+            proof: None,
+            ..self.credential.clone()
+        })
+        .map_err(|e| DTGCredentialError::Jws(format!("Couldn't serialize credential: {e}")))?;
+
+        sign_compact(
+            json!({"typ": VC_JWT_TYPE, "cty": "vc", "kid": signer.verification_method()}),
+            &payload,
+            &signer,
+        )
+    }
+
+    /// Verify a VC-JOSE enveloped credential and return the DTG Credential it contains
+    /// token: Compact JWS
+    /// public_key_bytes: The public key bytes of the issuer
+    pub fn from_vc_jwt(
+        token: &str,
+        public_key_bytes: &[u8],
+    ) -> Result<DTGCredential, DTGCredentialError> {
+        let jws = decode_compact(token)?;
+        if jws.header.get("typ").and_then(|t| t.as_str()) != Some(VC_JWT_TYPE) {
+            return Err(DTGCredentialError::Jws(format!(
+                "JWS typ header must be {VC_JWT_TYPE}"
+            )));
+        }
+        jws.verify(public_key_bytes)?;
+
+        serde_json::from_value(jws.payload)
+            .map_err(|e| DTGCredentialError::Jws(format!("Not a valid DTG Credential: {e}")))
+    }
+
+    /// Returns the `kid` header of a VC-JOSE enveloped credential without verifying it
+    /// Use this to find the issuer key before calling [DTGCredential::from_vc_jwt]
+    pub fn vc_jwt_key_id(token: &str) -> Result<Option<String>, DTGCredentialError> {
+        Ok(decode_compact(token)?
+            .header
+            .get("kid")
+            .and_then(|kid| kid.as_str())
+            .map(String::from))
+    }
+}
+
+#[cfg(all(test, feature = "affinidi-signing"))]
+mod tests {
+    use crate::{DTGCredential, DTGCredentialType, signer::SecretSigner};
+    use affinidi_secrets_resolver::secrets::Secret;
+    use chrono::Utc;
+
+    #[test]
+    fn test_vc_jwt_round_trip() {
+        let secret = Secret::generate_ed25519(Some("did:example:issuer#key-1"), None);
+        let mut vmc = DTGCredential::new_vmc(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            Utc::now(),
+            None,
+            true,
+        );
+        vmc.sign(&secret, None).unwrap();
+
+        let token = vmc.to_vc_jwt(&secret).unwrap();
+        assert_eq!(
+            DTGCredential::vc_jwt_key_id(&token).unwrap().as_deref(),
+            Some("did:example:issuer#key-1")
+        );
+
+        let cred = DTGCredential::from_vc_jwt(&token, secret.get_public_bytes()).unwrap();
+        assert!(matches!(cred.type_(), DTGCredentialType::Membership));
+        assert!(cred.is_personhood_credential());
+        assert!(!cred.signed());
+
+        let secret2 = Secret::generate_ed25519(None, None);
+        assert!(DTGCredential::from_vc_jwt(&token, secret2.get_public_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_vc_jwt_ecdsa() {
+        let vrc = DTGCredential::new_vrc(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            Utc::now(),
+            None,
+        );
+
+        for secret in [
+            Secret::generate_p256(None, None).unwrap(),
+            Secret::generate_p384(None, None).unwrap(),
+        ] {
+            let token = vrc.to_vc_jwt(&secret).unwrap();
+            assert!(DTGCredential::from_vc_jwt(&token, secret.get_public_bytes()).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_vc_jwt_wrong_type() {
+        let secret = Secret::generate_ed25519(None, None);
+        let token = crate::jws::sign_compact(
+            serde_json::json!({"typ": "JWT"}),
+            &serde_json::json!({}),
+            &SecretSigner::new(&secret).unwrap(),
+        )
+        .unwrap();
+
+        assert!(DTGCredential::from_vc_jwt(&token, secret.get_public_bytes()).is_err());
+    }
+}
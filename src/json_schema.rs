@@ -0,0 +1,237 @@
+/*!
+*   JSON Schema documents for DTG Credentials, generated from the Rust types.
+*
+*   [dtg_common_schema] describes any DTG Credential, the subject is one of the
+*   [crate::CredentialSubject] variants. [DTGCredentialType::json_schema] narrows that down to a
+*   single credential type: the `type` array must contain `VerifiableCredential`, `DTGCredential`
+*   and the type identifier (and no other DTG type), and the `credentialSubject` must match the
+*   subject shape used by that type.
+*
+*   Schemas are derived with `schemars` from the same structs serde uses, so renames and
+*   `deny_unknown_fields` are reflected automatically.
+*/
+
+use crate::{DTGCommon, DTGCredentialType};
+use serde_json::{Value, json};
+
+/// All credential types, in the order [json_schemas] returns them
+const CREDENTIAL_TYPES: [DTGCredentialType; 7] = [
+    DTGCredentialType::Membership,
+    DTGCredentialType::Relationship,
+    DTGCredentialType::Invitation,
+    DTGCredentialType::Persona,
+    DTGCredentialType::Endorsement,
+    DTGCredentialType::Witness,
+    DTGCredentialType::RCard,
+];
+
+/// JSON Schema of [DTGCommon], matches every DTG Credential type
+pub fn dtg_common_schema() -> Value {
+    schemars::schema_for!(DTGCommon).to_value()
+}
+
+/// JSON Schemas for every [DTGCredentialType]
+pub fn json_schemas() -> Vec<(DTGCredentialType, Value)> {
+    CREDENTIAL_TYPES
+        .into_iter()
+        .map(|type_| {
+            let schema = type_.json_schema();
+            (type_, schema)
+        })
+        .collect()
+}
+
+impl DTGCredentialType {
+    /// Name of the `$defs` entry describing the credential subject of this type
+    fn subject_definition(&self) -> &'static str {
+        match self {
+            DTGCredentialType::Membership
+            | DTGCredentialType::Relationship
+            | DTGCredentialType::Invitation
+            | DTGCredentialType::Persona => "CredentialSubjectBasic",
+            DTGCredentialType::Endorsement => "CredentialSubjectEndorsement",
+            DTGCredentialType::Witness => "CredentialSubjectWitness",
+            DTGCredentialType::RCard => "CredentialSubjectRCard",
+        }
+    }
+
+    /// JSON Schema a credential of this type must conform to
+    pub fn json_schema(&self) -> Value {
+        let mut schema = dtg_common_schema();
+        let type_name = self.to_string();
+
+        let others: Vec<String> = CREDENTIAL_TYPES
+            .iter()
+            .map(|t| t.to_string())
+            .filter(|t| t != &type_name)
+            .collect();
+
+        schema["title"] = json!(type_name);
+        schema["properties"]["type"] = json!({
+            "description": format!(
+                "Credential type identifiers, must contain VerifiableCredential, DTGCredential and {type_name}"
+            ),
+            "type": "array",
+            "items": { "type": "string" },
+            "allOf": [
+                { "contains": { "const": "VerifiableCredential" } },
+                { "contains": { "const": "DTGCredential" } },
+                { "contains": { "const": type_name } },
+                { "not": { "contains": { "enum": others } } }
+            ]
+        });
+        schema["properties"]["credentialSubject"] = json!({
+            "description": "The assertion between the entities involved",
+            "$ref": format!("#/$defs/{}", self.subject_definition())
+        });
+
+        schema
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dtg_common_schema, json_schemas};
+    use crate::{DTGCredential, DTGCredentialType, WitnessContext, schema::CredentialSchema};
+    use chrono::Utc;
+    use serde_json::{Value, json};
+
+    const ISSUER: &str = "did:example:issuer";
+    const SUBJECT: &str = "did:example:subject";
+
+    fn credentials() -> Vec<DTGCredential> {
+        let now = Utc::now();
+        let until = Some(now + chrono::Duration::days(1));
+        vec![
+            DTGCredential::new_vmc(ISSUER.into(), SUBJECT.into(), now, until, true),
+            DTGCredential::new_vrc(ISSUER.into(), SUBJECT.into(), now, until),
+            DTGCredential::new_vic(ISSUER.into(), SUBJECT.into(), now, until),
+            DTGCredential::new_vpc(ISSUER.into(), SUBJECT.into(), now, until),
+            DTGCredential::new_vec(
+                ISSUER.into(),
+                SUBJECT.into(),
+                now,
+                until,
+                json!({"type": "SkillEndorsement", "name": "Rust"}),
+            ),
+            DTGCredential::new_vwc(
+                ISSUER.into(),
+                SUBJECT.into(),
+                now,
+                until,
+                Some("sha256:abcd".into()),
+                Some(WitnessContext {
+                    event: Some("Meetup".into()),
+                    session_id: Some("1234".into()),
+                    method: Some("in-person".into()),
+                }),
+            ),
+            DTGCredential::new_rcard(
+                ISSUER.into(),
+                SUBJECT.into(),
+                now,
+                until,
+                json!(["vcard", [["fn", {}, "text", "Alice"]]]),
+            ),
+        ]
+    }
+
+    fn is_valid(schema: &Value, instance: &Value) -> bool {
+        jsonschema::validator_for(schema)
+            .expect("schema should compile")
+            .is_valid(instance)
+    }
+
+    #[test]
+    fn test_common_schema_accepts_all_credentials() {
+        let schema = dtg_common_schema();
+        for credential in credentials() {
+            assert!(is_valid(
+                &schema,
+                &serde_json::to_value(&credential).unwrap()
+            ));
+        }
+    }
+
+    #[test]
+    fn test_type_schema_accepts_own_type_only() {
+        let schemas = json_schemas();
+        for credential in credentials() {
+            let value = serde_json::to_value(&credential).unwrap();
+            for (type_, schema) in &schemas {
+                assert_eq!(
+                    is_valid(schema, &value),
+                    type_.to_string() == credential.type_().to_string(),
+                    "{} against {type_} schema",
+                    credential.type_()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_optional_fields_validate() {
+        let mut rcard = DTGCredential::new_rcard(
+            ISSUER.into(),
+            SUBJECT.into(),
+            Utc::now(),
+            None,
+            json!(["vcard", []]),
+        )
+        .supersede_rcard(Utc::now(), None, json!(["vcard", []]))
+        .unwrap();
+        rcard.add_credential_schema(CredentialSchema::json_schema(
+            "https://example.com/schemas/rcard.json",
+        ));
+        let value = serde_json::to_value(&rcard).unwrap();
+
+        assert!(is_valid(&DTGCredentialType::RCard.json_schema(), &value));
+    }
+
+    #[test]
+    fn test_unknown_subject_fields_rejected() {
+        let mut value = serde_json::to_value(DTGCredential::new_vrc(
+            ISSUER.into(),
+            SUBJECT.into(),
+            Utc::now(),
+            None,
+        ))
+        .unwrap();
+        value["credentialSubject"]["extra"] = json!(true);
+
+        assert!(!is_valid(
+            &DTGCredentialType::Relationship.json_schema(),
+            &value
+        ));
+    }
+
+    #[test]
+    fn test_schema_properties_match_serde() {
+        // Every serialized field must be described, so a serde rename can't drift from the schema
+        let schema = dtg_common_schema();
+        for credential in credentials() {
+            let value = serde_json::to_value(&credential).unwrap();
+            let subject_def = credential.type_().subject_definition();
+            let subject_properties = schema["$defs"][subject_def]["properties"]
+                .as_object()
+                .unwrap();
+            for key in value["credentialSubject"].as_object().unwrap().keys() {
+                assert!(
+                    subject_properties.contains_key(key),
+                    "{subject_def} missing {key}"
+                );
+            }
+            for key in value.as_object().unwrap().keys() {
+                assert!(
+                    schema["properties"].get(key).is_some(),
+                    "DTGCommon missing {key}"
+                );
+            }
+        }
+
+        let context = &schema["$defs"]["WitnessContext"]["properties"];
+        for key in ["event", "sessionId", "method"] {
+            assert!(context.get(key).is_some());
+        }
+    }
+}
@@ -0,0 +1,212 @@
+/*!
+*   Minimal JSON Web Signature (RFC 7515) compact serialization helpers.
+*
+*   Used by the JOSE based encodings of DTG Credentials.
+*/
+
+use crate::{DTGCredentialError, crypto::Algorithm, signer::Signer};
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use serde_json::{Value, json};
+
+/// A decoded compact JWS
+pub(crate) struct CompactJws {
+    pub header: Value,
+    pub payload: Value,
+
+    /// ASCII(BASE64URL(header) || '.' || BASE64URL(payload))
+    pub signing_input: String,
+    pub signature: Vec<u8>,
+}
+
+pub(crate) fn b64_encode<T: AsRef<[u8]>>(input: T) -> String {
+    BASE64_URL_SAFE_NO_PAD.encode(input)
+}
+
+pub(crate) fn b64_decode(input: &str) -> Result<Vec<u8>, DTGCredentialError> {
+    BASE64_URL_SAFE_NO_PAD
+        .decode(input)
+        .map_err(|e| DTGCredentialError::Jws(format!("Invalid base64url: {e}")))
+}
+
+/// Public JWK representation of a public key
+/// public_key: Ed25519 key bytes or a SEC1 uncompressed P-256/P-384 point
+pub fn public_jwk(algorithm: Algorithm, public_key: &[u8]) -> Result<Value, DTGCredentialError> {
+    match algorithm {
+        Algorithm::EdDSA => Ok(json!({
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "x": b64_encode(public_key),
+        })),
+        Algorithm::ES256 | Algorithm::ES384 => {
+            let crv = if algorithm == Algorithm::ES256 {
+                "P-256"
+            } else {
+                "P-384"
+            };
+            // SEC1 uncompressed point: 0x04 || x || y
+            let point = public_key;
+            if point.first() != Some(&4) {
+                return Err(DTGCredentialError::Jws(
+                    "Expected an uncompressed EC public key".to_string(),
+                ));
+            }
+            let (x, y) = point[1..].split_at((point.len() - 1) / 2);
+            Ok(json!({
+                "kty": "EC",
+                "crv": crv,
+                "x": b64_encode(x),
+                "y": b64_encode(y),
+            }))
+        }
+    }
+}
+
+/// Returns the JWS algorithm and raw public key bytes from a public JWK
+pub(crate) fn jwk_public_key(jwk: &Value) -> Result<(&'static str, Vec<u8>), DTGCredentialError> {
+    match (
+        jwk.get("kty").and_then(|v| v.as_str()),
+        jwk.get("crv").and_then(|v| v.as_str()),
+        jwk.get("x").and_then(|v| v.as_str()),
+    ) {
+        (Some("OKP"), Some("Ed25519"), Some(x)) => Ok(("EdDSA", b64_decode(x)?)),
+        (Some("EC"), Some(crv @ ("P-256" | "P-384")), Some(x)) => {
+            let y = jwk
+                .get("y")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| DTGCredentialError::Jws("EC JWK is missing y".to_string()))?;
+            let mut point = vec![4];
+            point.extend(b64_decode(x)?);
+            point.extend(b64_decode(y)?);
+            Ok((if crv == "P-256" { "ES256" } else { "ES384" }, point))
+        }
+        _ => Err(DTGCredentialError::Jws("Unsupported JWK".to_string())),
+    }
+}
+
+/// Creates a compact JWS, the `alg` header is set from the signer algorithm
+pub(crate) fn sign_compact(
+    header: Value,
+    payload: &Value,
+    signer: &impl Signer,
+) -> Result<String, DTGCredentialError> {
+    let algorithm = signer.algorithm();
+    let mut header = header;
+    header["alg"] = json!(algorithm.jose_name());
+
+    let signing_input = format!(
+        "{}.{}",
+        b64_encode(header.to_string()),
+        b64_encode(payload.to_string())
+    );
+
+    let signature = signer.sign(signing_input.as_bytes())?;
+
+    Ok(format!("{signing_input}.{}", b64_encode(signature)))
+}
+
+/// Splits and decodes a compact JWS without verifying it
+pub(crate) fn decode_compact(token: &str) -> Result<CompactJws, DTGCredentialError> {
+    let parts: Vec<&str> = token.split('.').collect();
+    if parts.len() != 3 {
+        return Err(DTGCredentialError::Jws(
+            "Compact JWS must have three parts".to_string(),
+        ));
+    }
+
+    let header: Value = serde_json::from_slice(&b64_decode(parts[0])?)
+        .map_err(|e| DTGCredentialError::Jws(format!("Invalid header: {e}")))?;
+    let payload: Value = serde_json::from_slice(&b64_decode(parts[1])?)
+        .map_err(|e| DTGCredentialError::Jws(format!("Invalid payload: {e}")))?;
+
+    Ok(CompactJws {
+        header,
+        payload,
+        signing_input: format!("{}.{}", parts[0], parts[1]),
+        signature: b64_decode(parts[2])?,
+    })
+}
+
+impl CompactJws {
+    /// Verifies the JWS signature
+    /// public_key_bytes: Raw public key bytes of the signer
+    pub fn verify(&self, public_key_bytes: &[u8]) -> Result<(), DTGCredentialError> {
+        let algorithm = self
+            .header
+            .get("alg")
+            .and_then(|alg| alg.as_str())
+            .ok_or_else(|| DTGCredentialError::Jws("Missing alg header".to_string()))?;
+
+        Algorithm::from_jose_name(algorithm)
+            .and_then(|algorithm| {
+                algorithm.verify(
+                    public_key_bytes,
+                    self.signing_input.as_bytes(),
+                    &self.signature,
+                )
+            })
+            .map_err(DTGCredentialError::Jws)
+    }
+}
+
+#[cfg(all(test, feature = "affinidi-signing"))]
+mod tests {
+    use super::{decode_compact, jwk_public_key, public_jwk, sign_compact};
+    use crate::{crypto::Algorithm, signer::SecretSigner};
+    use affinidi_secrets_resolver::secrets::Secret;
+    use serde_json::json;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let secret = Secret::generate_ed25519(None, None);
+        let token = sign_compact(
+            json!({"typ": "JWT"}),
+            &json!({"test": true}),
+            &SecretSigner::new(&secret).unwrap(),
+        )
+        .unwrap();
+
+        let jws = decode_compact(&token).unwrap();
+        assert_eq!(jws.header["alg"], "EdDSA");
+        assert_eq!(jws.payload, json!({"test": true}));
+        assert!(jws.verify(secret.get_public_bytes()).is_ok());
+
+        let secret2 = Secret::generate_ed25519(None, None);
+        assert!(jws.verify(secret2.get_public_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_jwk_round_trip() {
+        let secret = Secret::generate_ed25519(None, None);
+        let (alg, key) =
+            jwk_public_key(&public_jwk(Algorithm::EdDSA, secret.get_public_bytes()).unwrap())
+                .unwrap();
+        assert_eq!(alg, "EdDSA");
+        assert_eq!(key, secret.get_public_bytes());
+    }
+
+    #[test]
+    fn test_ec_jwk_round_trip() {
+        for (secret, algorithm, expected) in [
+            (
+                Secret::generate_p256(None, None).unwrap(),
+                Algorithm::ES256,
+                "ES256",
+            ),
+            (
+                Secret::generate_p384(None, None).unwrap(),
+                Algorithm::ES384,
+                "ES384",
+            ),
+        ] {
+            let (alg, key) =
+                jwk_public_key(&public_jwk(algorithm, secret.get_public_bytes()).unwrap()).unwrap();
+            assert_eq!(alg, expected);
+            assert_eq!(key, secret.get_public_bytes());
+        }
+    }
+
+    #[test]
+    fn test_bad_compact() {
+        assert!(decode_compact("abc.def").is_err());
+    }
+}
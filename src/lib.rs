@@ -1,18 +1,59 @@
 /*! Decentralized Trust Graph (DTG) Credentials
 */
 
-use affinidi_data_integrity::DataIntegrityProof;
-#[cfg(feature = "affinidi-signing")]
-use affinidi_data_integrity::{DataIntegrityError, verification_proof::VerificationProof};
-#[cfg(feature = "affinidi-signing")]
-use affinidi_secrets_resolver::secrets::Secret;
 use chrono::{DateTime, Utc};
+use proof::DataIntegrityProof;
+#[cfg(feature = "signing")]
+use proof::VerificationProof;
+use schema::CredentialSchemas;
 use serde::{Deserialize, Serialize, Serializer};
-use serde_json::Value;
+use serde_json::{Map, Value};
 use std::fmt::Display;
 use thiserror::Error;
 
+pub mod contexts;
+#[cfg(feature = "signing")]
+pub mod cose;
 pub mod create;
+pub(crate) mod crypto;
+pub mod dcql;
+pub mod ecdsa;
+#[cfg(feature = "signing")]
+pub mod ecdsa_sd;
+pub mod eddsa;
+pub mod endorsement;
+pub mod export;
+pub mod graph;
+#[cfg(feature = "signing")]
+pub mod invitation;
+pub mod jcard;
+#[cfg(feature = "signing")]
+pub mod jose;
+#[cfg(feature = "json-schema")]
+pub mod json_schema;
+#[cfg(feature = "signing")]
+pub(crate) mod jws;
+pub mod path;
+pub mod presentation;
+pub mod presentation_exchange;
+pub mod proof;
+pub mod rcard;
+pub mod rdfc;
+pub mod schema;
+pub mod score;
+#[cfg(feature = "signing")]
+pub mod sd_jwt;
+#[cfg(feature = "signing")]
+pub mod securing;
+#[cfg(feature = "signing")]
+pub mod selective_disclosure;
+pub mod signer;
+pub mod store;
+pub mod sybil;
+#[cfg(all(test, feature = "affinidi-signing"))]
+mod test_utils;
+pub mod vcard;
+pub mod wallet;
 
 /// What W3C VC Format is the credential using?
 #[derive(Clone, Copy, Debug)]
@@ -45,15 +86,83 @@ pub enum DTGCredentialError {
     #[error("Unknown credential type")]
     UnknownCredential,
 
-    #[cfg(feature = "affinidi-signing")]
     #[error("Data Integrity Error: {0}")]
-    DataIntegrity(#[from] DataIntegrityError),
+    DataIntegrity(String),
 
     #[error("Credential is not signed")]
     NotSigned,
 
     #[error("Unknown W3C VC Version")]
     UnknownVCVersion,
+
+    #[error("Unsupported cryptosuite: {0}")]
+    UnsupportedCryptoSuite(String),
+
+    #[error("Selective Disclosure Error: {0}")]
+    SelectiveDisclosure(String),
+
+    #[error("JWS Error: {0}")]
+    Jws(String),
+
+    #[error("SD-JWT Error: {0}")]
+    SdJwt(String),
+
+    #[error("CBOR Error: {0}")]
+    Cbor(String),
+
+    #[error("COSE Error: {0}")]
+    Cose(String),
+
+    #[error("Invitation Error: {0}")]
+    Invitation(String),
+
+    #[error("jCard Error: {0}")]
+    JCard(String),
+
+    #[error("vCard Error: {0}")]
+    VCard(String),
+
+    #[error("RCard Error: {0}")]
+    RCard(String),
+
+    #[error("Endorsement Error: {0}")]
+    Endorsement(String),
+
+    #[error("Schema Error: {0}")]
+    Schema(String),
+
+    #[error("RDF Canonicalization Error: {0}")]
+    Rdfc(String),
+
+    #[error("JSON-LD Context Error: {0}")]
+    Context(String),
+
+    #[error("ECDSA Error: {0}")]
+    Ecdsa(String),
+
+    #[error("Incompatible key type ({0}) for cryptosuite: {1}")]
+    IncompatibleKeyType(String, String),
+
+    #[error("Signer Error: {0}")]
+    Signer(String),
+
+    #[error("Unknown securing mechanism")]
+    UnknownSecuringMechanism,
+
+    #[error("Trust Graph Error: {0}")]
+    Graph(String),
+
+    #[error("Credential Store Error: {0}")]
+    Store(String),
+
+    #[error("Presentation Error: {0}")]
+    Presentation(String),
+
+    #[error("Presentation Exchange Error: {0}")]
+    PresentationExchange(String),
+
+    #[error("DCQL Error: {0}")]
+    Dcql(String),
 }
 
 /// Defined DTG Credentials
@@ -114,6 +223,12 @@ impl DTGCredential {
         self.credential.valid_until()
     }
 
+    /// Is the credential within its validity period at this time?
+    /// NOTE: This does NOT check the proof or revocation
+    pub fn is_valid_at(&self, at: DateTime<Utc>) -> bool {
+        self.valid_from() <= at && self.valid_until().is_none_or(|until| until > at)
+    }
+
     /// Returns the proof value if signed else None
     pub fn proof_value(&self) -> Option<&str> {
         if let Some(proof) = &self.credential.proof {
@@ -123,29 +238,40 @@ impl DTGCredential {
         }
     }
 
-    #[cfg(feature = "affinidi-signing")]
-    /// Sign the credential using W3C Data Integrity Proof with JCS EdDSA 2022
-    /// signing_secret: The secret key to use to sign the credential
+    /// Sign the credential using W3C Data Integrity Proof
+    /// The cryptosuite is selected from the key type:
+    /// Ed25519 uses eddsa-jcs-2022, P-256 and P-384 use ecdsa-jcs-2019
+    /// signing_secret: The key to sign with, see [signer::SigningKey]
     /// create_time: Optional creation time for the proof, defaults to now if None
     pub fn sign(
         &mut self,
-        signing_secret: &Secret,
+        signing_secret: &impl signer::SigningKey,
         create_time: Option<DateTime<Utc>>,
     ) -> Result<DataIntegrityProof, DTGCredentialError> {
-        let proof = DataIntegrityProof::sign_jcs_data(
-            self,
-            None,
-            signing_secret,
-            create_time.map(|ts| ts.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
-        )?;
+        self.sign_with_signer(&signing_secret.signer()?, None, create_time)
+    }
 
-        self.credential.proof = Some(proof.clone());
-        Ok(proof)
+    /// Sign the credential using W3C Data Integrity Proof with the chosen cryptosuite
+    /// signing_secret: The key to sign with, see [signer::SigningKey]
+    /// cryptosuite: eddsa-jcs-2022, eddsa-rdfc-2022 or ecdsa-jcs-2019
+    ///              Use [DTGCredential::sign_selective] for x-dtg-eddsa-sd-jcs
+    /// create_time: Optional creation time for the proof, defaults to now if None
+    ///
+    /// Returns [DTGCredentialError::IncompatibleKeyType] if the key can't be used with the
+    /// cryptosuite
+    pub fn sign_with_cryptosuite(
+        &mut self,
+        signing_secret: &impl signer::SigningKey,
+        cryptosuite: proof::CryptoSuite,
+        create_time: Option<DateTime<Utc>>,
+    ) -> Result<DataIntegrityProof, DTGCredentialError> {
+        self.sign_with_signer(&signing_secret.signer()?, Some(cryptosuite), create_time)
     }
 
-    #[cfg(feature = "affinidi-signing")]
+    #[cfg(feature = "signing")]
     /// Verify the credential if you already know the public key bytes
     /// otherwise use the affinidi_tdk:verify_data() method
+    /// The cryptosuite is detected from the attached proof
     /// public_key_bytes: The public key bytes to use to verify the credential
     pub fn verify_proof_with_public_key(
         &self,
@@ -160,19 +286,28 @@ impl DTGCredential {
             return Err(DTGCredentialError::NotSigned);
         };
 
-        let unsigned = DTGCommon {
-            proof: None,
-            ..self.credential.clone()
-        };
+        match proof.cryptosuite {
+            proof::CryptoSuite::EddsaJcs2022 => {
+                eddsa::verify_proof(self, &proof, public_key_bytes)?
+            }
+            proof::CryptoSuite::EddsaRdfc2022 => {
+                rdfc::verify_proof(self, &proof, public_key_bytes)?
+            }
+            proof::CryptoSuite::EcdsaJcs2019 => {
+                ecdsa::verify_proof(self, &proof, public_key_bytes)?
+            }
+            proof::CryptoSuite::EcdsaSd2023 => {
+                ecdsa_sd::verify_proof(self, &proof, public_key_bytes)?
+            }
+            proof::CryptoSuite::XDtgEddsaSdJcs => {
+                selective_disclosure::verify_proof(self, &proof, public_key_bytes)?
+            }
+        }
 
-        Ok(
-            affinidi_data_integrity::verification_proof::verify_data_with_public_key(
-                &unsigned,
-                None,
-                &proof,
-                public_key_bytes,
-            )?,
-        )
+        Ok(VerificationProof {
+            verified: true,
+            verified_document: None,
+        })
     }
 
     /// Is this credential a W3C VC Version 1.1 or 2.0 credential?
@@ -193,7 +328,7 @@ impl DTGCredential {
 }
 
 /// TDG VC Type Identifiers
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[non_exhaustive]
 pub enum DTGCredentialType {
     Membership,
@@ -253,6 +388,7 @@ impl TryFrom<&[String]> for DTGCredentialType {
 
 /// All DTG Credentials follow a common structure.
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct DTGCommon {
     /// JSON-LD links to contexts
@@ -288,9 +424,18 @@ pub struct DTGCommon {
     /// The assertion between the entities involved
     pub credential_subject: CredentialSubject,
 
+    /// Schemas this credential conforms to, see [crate::schema]
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub credential_schema: Option<CredentialSchemas>,
+
     /// Cryptographic proof of credential authenticity
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub proof: Option<DataIntegrityProof>,
+
+    /// Members this crate doesn't model, such as `id`
+    /// Kept so that proofs covering them still verify
+    #[serde(flatten, default)]
+    pub extra: Map<String, Value>,
 }
 
 impl DTGCommon {
@@ -345,7 +490,9 @@ impl Default for DTGCommon {
             credential_subject: CredentialSubject::Basic(CredentialSubjectBasic {
                 id: String::new(),
             }),
+            credential_schema: None,
             proof: None,
+            extra: Map::new(),
         }
     }
 }
@@ -461,6 +608,7 @@ where
 /// This represents all possible credential subjects
 /// The order of the enum is important as it will match on first match
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum CredentialSubject {
     /// Verifiable Endorsement Credential subject
@@ -479,6 +627,7 @@ pub enum CredentialSubject {
 
 /// id of the credential subject only
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 #[serde(deny_unknown_fields)]
 pub struct CredentialSubjectBasic {
     pub id: String,
@@ -486,15 +635,17 @@ pub struct CredentialSubjectBasic {
 
 /// Endorsement Credential subject
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 #[serde(deny_unknown_fields)]
 pub struct CredentialSubjectEndorsement {
     pub id: String,
-    /// There is no spec for the endorsement content, so we use a generic JSON value
+    /// Endorsement content identified by its `type`, see [crate::endorsement] for typed access
     pub endorsement: Value,
 }
 
 /// Witness Credential subject
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct CredentialSubjectWitness {
     pub id: String,
@@ -509,6 +660,7 @@ pub struct CredentialSubjectWitness {
 
 /// Witness Credential Context
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct WitnessContext {
     /// Human-readable event name
@@ -523,12 +675,21 @@ pub struct WitnessContext {
 
 /// R-Card Credential subject
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
 #[serde(deny_unknown_fields)]
 pub struct CredentialSubjectRCard {
     pub id: String,
 
     /// JCard spec, generic JSON value
     pub card: Value,
+
+    /// Version of this RCard, increases with each superseding RCard
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sequence: Option<u64>,
+
+    /// Digest of the RCard credential this one supersedes
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub replaces: Option<String>,
 }
 
 #[cfg(test)]
@@ -1022,6 +1183,8 @@ mod tests {
         cred.credential_subject = CredentialSubject::RCard(CredentialSubjectRCard {
             id: "did:example:bad".to_string(),
             card: Value::Null,
+            sequence: None,
+            replaces: None,
         });
 
         assert!(std::convert::TryInto::<DTGCredential>::try_into(cred).is_err());
@@ -0,0 +1,471 @@
+/*!
+*   Trust path discovery: how is one DID connected to another?
+*
+*   Paths are found breadth first over the [TrustGraph], so every path returned is a shortest
+*   path. Each hop carries the credentials that justify it, and the VWCs that witnessed them, so
+*   a path can be presented as evidence of the connection.
+*
+*   By default hops follow valid VRCs and VMCs in either direction: two members of the same
+*   community are two hops apart through the community DID.
+*/
+
+use crate::{
+    DTGCredential, DTGCredentialType,
+    graph::{Direction, TrustEdge, TrustGraph},
+};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Constraints on the paths to find
+#[derive(Clone, Debug)]
+pub struct PathQuery {
+    /// Longest path to consider, in hops
+    pub max_hops: usize,
+
+    /// Credential types that can be followed
+    pub types: Vec<DTGCredentialType>,
+
+    /// Direction to follow credentials in, [Direction::Outgoing] goes from issuer to subject
+    pub direction: Direction,
+
+    /// Every VRC hop must be witnessed by a valid VWC
+    pub require_witness: bool,
+
+    /// Credentials must be valid at this time
+    pub at: DateTime<Utc>,
+
+    /// Maximum number of paths returned
+    pub max_paths: usize,
+}
+
+impl Default for PathQuery {
+    fn default() -> Self {
+        PathQuery {
+            max_hops: 6,
+            types: vec![
+                DTGCredentialType::Relationship,
+                DTGCredentialType::Membership,
+            ],
+            direction: Direction::Both,
+            require_witness: false,
+            at: Utc::now(),
+            max_paths: 100,
+        }
+    }
+}
+
+impl PathQuery {
+    /// VRCs and VMCs in either direction, up to 6 hops, valid now
+    pub fn new() -> Self {
+        PathQuery::default()
+    }
+
+    /// Sets the longest path to consider
+    pub fn with_max_hops(mut self, max_hops: usize) -> Self {
+        self.max_hops = max_hops;
+        self
+    }
+
+    /// Sets the credential types that can be followed
+    pub fn with_types(mut self, types: &[DTGCredentialType]) -> Self {
+        self.types = types.to_vec();
+        self
+    }
+
+    /// Sets the direction to follow credentials in
+    pub fn with_direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Requires every VRC hop to be witnessed by a VWC
+    pub fn with_witness_required(mut self) -> Self {
+        self.require_witness = true;
+        self
+    }
+
+    /// Sets the time credentials must be valid at
+    pub fn with_time(mut self, at: DateTime<Utc>) -> Self {
+        self.at = at;
+        self
+    }
+
+    /// Sets the maximum number of paths returned
+    pub fn with_max_paths(mut self, max_paths: usize) -> Self {
+        self.max_paths = max_paths;
+        self
+    }
+}
+
+/// A single hop between two DIDs
+#[derive(Clone, Debug)]
+pub struct PathHop<'a> {
+    /// DID the hop starts from
+    pub from: &'a str,
+
+    /// DID the hop ends at
+    pub to: &'a str,
+
+    /// Credentials connecting the DIDs that satisfy the query
+    pub edges: Vec<&'a TrustEdge>,
+
+    /// VWCs witnessing the VRCs in `edges`
+    pub witnesses: Vec<&'a TrustEdge>,
+}
+
+/// A chain of hops connecting two DIDs
+#[derive(Clone, Debug)]
+pub struct TrustPath<'a> {
+    pub hops: Vec<PathHop<'a>>,
+}
+
+impl<'a> TrustPath<'a> {
+    /// Number of hops, the degrees of separation
+    pub fn len(&self) -> usize {
+        self.hops.len()
+    }
+
+    /// Is this the path from a DID to itself?
+    pub fn is_empty(&self) -> bool {
+        self.hops.is_empty()
+    }
+
+    /// DIDs along the path, including both ends
+    pub fn dids(&self) -> Vec<&'a str> {
+        let mut dids: Vec<&str> = self.hops.iter().map(|hop| hop.from).collect();
+        if let Some(last) = self.hops.last() {
+            dids.push(last.to);
+        }
+        dids
+    }
+
+    /// Every credential justifying the path, hop by hop, witnesses after the credentials they
+    /// witness
+    pub fn credentials(&self) -> Vec<&'a DTGCredential> {
+        self.hops
+            .iter()
+            .flat_map(|hop| hop.edges.iter().chain(hop.witnesses.iter()))
+            .map(|edge| edge.credential())
+            .collect()
+    }
+}
+
+impl TrustGraph {
+    /// Credentials a query can follow from a DID, grouped by the DID they lead to
+    fn hops_from<'a>(&'a self, did: &str, query: &PathQuery) -> BTreeMap<&'a str, PathHop<'a>> {
+        let mut hops: BTreeMap<&str, PathHop> = BTreeMap::new();
+        for edge in self.edges_of(did, None, query.direction) {
+            if !query.types.contains(&edge.type_()) || !edge.is_valid_at(query.at) {
+                continue;
+            }
+            let witnesses = if edge.type_() == DTGCredentialType::Relationship {
+                self.witnesses(edge.digest(), query.at)
+            } else {
+                Vec::new()
+            };
+            if query.require_witness
+                && edge.type_() == DTGCredentialType::Relationship
+                && witnesses.is_empty()
+            {
+                continue;
+            }
+
+            let (from, to) = if edge.from() == did {
+                (edge.from(), edge.to())
+            } else {
+                (edge.to(), edge.from())
+            };
+            if from == to {
+                continue;
+            }
+            let hop = hops.entry(to).or_insert_with(|| PathHop {
+                from,
+                to,
+                edges: Vec::new(),
+                witnesses: Vec::new(),
+            });
+            hop.edges.push(edge);
+            hop.witnesses.extend(witnesses);
+        }
+        hops
+    }
+
+    /// Shortest paths between two DIDs, ordered by the DIDs they pass through
+    /// Returns no paths if the DIDs aren't connected within `query.max_hops`
+    pub fn shortest_paths(&self, from: &str, to: &str, query: &PathQuery) -> Vec<TrustPath<'_>> {
+        if query.max_paths == 0 {
+            return Vec::new();
+        }
+        if from == to {
+            return vec![TrustPath { hops: Vec::new() }];
+        }
+
+        // Breadth first, keeping every hop that reaches a DID at its shortest distance
+        let mut distance: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut reached_by: BTreeMap<&str, Vec<PathHop>> = BTreeMap::new();
+        let mut queue: VecDeque<&str> = VecDeque::new();
+
+        let Some(start) = self.nodes().into_iter().find(|did| *did == from) else {
+            return Vec::new();
+        };
+        distance.insert(start, 0);
+        queue.push_back(start);
+
+        while let Some(did) = queue.pop_front() {
+            let hops = distance[did];
+            if hops >= query.max_hops || distance.get(to).is_some_and(|d| *d <= hops) {
+                continue;
+            }
+            for (next, hop) in self.hops_from(did, query) {
+                match distance.get(next) {
+                    Some(d) if *d < hops + 1 => continue,
+                    Some(_) => {}
+                    None => {
+                        distance.insert(next, hops + 1);
+                        queue.push_back(next);
+                    }
+                }
+                reached_by.entry(next).or_default().push(hop);
+            }
+        }
+
+        let Some((target, _)) = distance.get_key_value(to) else {
+            return Vec::new();
+        };
+
+        let mut paths = Vec::new();
+        collect_paths(
+            &reached_by,
+            start,
+            target,
+            &mut Vec::new(),
+            &mut paths,
+            query.max_paths,
+        );
+        paths.sort_by(|a, b| a.dids().cmp(&b.dids()));
+        paths
+    }
+
+    /// Degrees of separation between two DIDs, the number of hops on the shortest path
+    /// Returns None if the DIDs aren't connected within `query.max_hops`
+    pub fn degrees_of_separation(&self, from: &str, to: &str, query: &PathQuery) -> Option<usize> {
+        self.shortest_paths(from, to, &query.clone().with_max_paths(1))
+            .first()
+            .map(TrustPath::len)
+    }
+
+    /// DIDs reachable from a DID, with their degrees of separation
+    pub fn reachable(&self, from: &str, query: &PathQuery) -> BTreeMap<&str, usize> {
+        let mut distance: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut queue: VecDeque<&str> = VecDeque::new();
+
+        let Some(start) = self.nodes().into_iter().find(|did| *did == from) else {
+            return distance;
+        };
+        distance.insert(start, 0);
+        queue.push_back(start);
+
+        while let Some(did) = queue.pop_front() {
+            let hops = distance[did];
+            if hops >= query.max_hops {
+                continue;
+            }
+            for next in self.hops_from(did, query).into_keys() {
+                if !distance.contains_key(next) {
+                    distance.insert(next, hops + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        distance.remove(start);
+        distance
+    }
+}
+
+/// Walks back from `did` to `start` through every shortest hop, stops after `max` paths
+fn collect_paths<'a>(
+    reached_by: &BTreeMap<&str, Vec<PathHop<'a>>>,
+    start: &str,
+    did: &str,
+    suffix: &mut Vec<PathHop<'a>>,
+    paths: &mut Vec<TrustPath<'a>>,
+    max: usize,
+) {
+    if paths.len() >= max {
+        return;
+    }
+    if did == start {
+        paths.push(TrustPath {
+            hops: suffix.iter().rev().cloned().collect(),
+        });
+        return;
+    }
+    for hop in reached_by.get(did).into_iter().flatten() {
+        suffix.push(hop.clone());
+        collect_paths(reached_by, start, hop.from, suffix, paths, max);
+        suffix.pop();
+    }
+}
+
+#[cfg(all(test, feature = "affinidi-signing"))]
+mod tests {
+    use super::PathQuery;
+    use crate::{
+        DTGCredential, DTGCredentialType,
+        graph::{Direction, TrustGraph},
+        test_utils::{did, signed, vmc, vrc, vwc},
+    };
+    use chrono::{Duration, Utc};
+
+    /// alice - bob - dave and alice - erin - dave, dave - frank
+    fn graph() -> TrustGraph {
+        TrustGraph::from_credentials([
+            vrc("alice", "bob"),
+            vrc("bob", "dave"),
+            vrc("alice", "erin"),
+            vrc("dave", "erin"),
+            vrc("dave", "frank"),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_shortest_paths() {
+        let graph = graph();
+        let paths = graph.shortest_paths(&did("alice"), &did("frank"), &PathQuery::new());
+
+        assert_eq!(paths.len(), 2);
+        assert_eq!(
+            paths[0].dids(),
+            vec![did("alice"), did("bob"), did("dave"), did("frank")]
+        );
+        assert_eq!(
+            paths[1].dids(),
+            vec![did("alice"), did("erin"), did("dave"), did("frank")]
+        );
+        assert_eq!(paths[0].credentials().len(), 3);
+        assert_eq!(paths[0].hops[0].edges[0].from(), did("alice"));
+
+        assert_eq!(
+            graph.degrees_of_separation(&did("alice"), &did("frank"), &PathQuery::new()),
+            Some(3)
+        );
+        assert_eq!(
+            graph.degrees_of_separation(&did("alice"), &did("alice"), &PathQuery::new()),
+            Some(0)
+        );
+        assert_eq!(
+            graph.degrees_of_separation(&did("alice"), &did("nobody"), &PathQuery::new()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        let graph = graph();
+
+        assert!(
+            graph
+                .shortest_paths(
+                    &did("alice"),
+                    &did("frank"),
+                    &PathQuery::new().with_max_hops(2)
+                )
+                .is_empty()
+        );
+        assert_eq!(
+            graph
+                .shortest_paths(
+                    &did("alice"),
+                    &did("frank"),
+                    &PathQuery::new().with_max_paths(1)
+                )
+                .len(),
+            1
+        );
+
+        // Only issuer to subject: dave -> erin can't be followed from erin
+        let outgoing = PathQuery::new().with_direction(Direction::Outgoing);
+        let paths = graph.shortest_paths(&did("alice"), &did("frank"), &outgoing);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].dids()[1], did("bob"));
+        assert!(
+            graph
+                .shortest_paths(&did("frank"), &did("alice"), &outgoing)
+                .is_empty()
+        );
+
+        let reachable = graph.reachable(&did("alice"), &PathQuery::new().with_max_hops(2));
+        assert_eq!(reachable.get(did("dave").as_str()), Some(&2));
+        assert!(!reachable.contains_key(did("frank").as_str()));
+    }
+
+    #[test]
+    fn test_types_and_validity() {
+        let mut graph = TrustGraph::from_credentials([
+            vmc("club", "alice", false),
+            vmc("club", "bob", false),
+            signed(DTGCredential::new_vrc(
+                did("alice"),
+                did("bob"),
+                Utc::now() - Duration::days(10),
+                Some(Utc::now() - Duration::days(5)),
+            )),
+        ])
+        .unwrap();
+
+        let paths = graph.shortest_paths(&did("alice"), &did("bob"), &PathQuery::new());
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].dids(), vec![did("alice"), did("club"), did("bob")]);
+
+        // The expired VRC was valid a week ago
+        let week_ago = PathQuery::new().with_time(Utc::now() - Duration::days(7));
+        assert_eq!(
+            graph.degrees_of_separation(&did("alice"), &did("bob"), &week_ago),
+            Some(1)
+        );
+
+        let relationships = PathQuery::new().with_types(&[DTGCredentialType::Relationship]);
+        assert_eq!(
+            graph.degrees_of_separation(&did("alice"), &did("bob"), &relationships),
+            None
+        );
+
+        graph.add(vrc("bob", "alice")).unwrap();
+        assert_eq!(
+            graph.degrees_of_separation(&did("alice"), &did("bob"), &relationships),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_witness_required() {
+        let mut graph = graph();
+
+        let query = PathQuery::new().with_witness_required();
+        assert!(
+            graph
+                .shortest_paths(&did("alice"), &did("dave"), &query)
+                .is_empty()
+        );
+
+        let digests: Vec<String> = graph
+            .edges()
+            .filter(|e| e.to() == did("erin"))
+            .map(|e| e.digest().to_string())
+            .collect();
+        for digest in &digests {
+            graph.add(vwc("witness", "erin", digest, None)).unwrap();
+        }
+
+        let paths = graph.shortest_paths(&did("alice"), &did("dave"), &query);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(
+            paths[0].dids(),
+            vec![did("alice"), did("erin"), did("dave")]
+        );
+        assert_eq!(paths[0].hops[0].witnesses.len(), 1);
+        // Two VRCs and their two witnesses
+        assert_eq!(paths[0].credentials().len(), 4);
+    }
+}
@@ -0,0 +1,293 @@
+/*!
+*   Verifiable Presentations of DTG Credentials.
+*
+*   A holder wraps the credentials it is sharing in a [DTGPresentation] and signs it with a Data
+*   Integrity proof for `authentication`. The proof carries the verifier's `challenge` and
+*   `domain` so the presentation can't be replayed to another verifier.
+*
+*   Presentations are signed with eddsa-jcs-2022 (Ed25519) or ecdsa-jcs-2019 (P-256, P-384).
+*
+*   NOTE: Verifying the presentation proof only proves the holder signed it, each credential must
+*   still be verified with the key of its issuer.
+*
+*   See [crate::wallet] to select the credentials that satisfy a verifier's request.
+*/
+
+#[cfg(feature = "signing")]
+use crate::proof::VerificationProof;
+use crate::{
+    DTGCredential, DTGCredentialError, ecdsa, eddsa,
+    presentation_exchange::PresentationSubmission,
+    proof::{CryptoSuite, DataIntegrityProof},
+    signer::{self, Signer},
+};
+use chrono::{DateTime, Utc};
+use multibase::Base;
+use serde::{Deserialize, Serialize};
+
+pub(crate) fn presentation_error(msg: impl Into<String>) -> DTGCredentialError {
+    DTGCredentialError::Presentation(msg.into())
+}
+
+/// W3C Verifiable Presentation of DTG Credentials
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DTGPresentation {
+    /// JSON-LD links to contexts
+    #[serde(rename = "@context")]
+    pub context: Vec<String>,
+
+    /// Optional identifier of the presentation
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub id: Option<String>,
+
+    /// Must contain VerifiablePresentation
+    #[serde(rename = "type")]
+    pub type_: Vec<String>,
+
+    /// DID of the holder presenting the credentials
+    pub holder: String,
+
+    /// The credentials being presented
+    #[serde(default)]
+    pub verifiable_credential: Vec<DTGCredential>,
+
+    /// Maps the input descriptors of a presentation definition to the credentials
+    /// See [crate::presentation_exchange]
+    #[serde(
+        rename = "presentation_submission",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub presentation_submission: Option<PresentationSubmission>,
+
+    /// Holder's proof over the presentation
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub proof: Option<DataIntegrityProof>,
+}
+
+impl DTGPresentation {
+    /// Creates an unsigned presentation
+    /// holder: DID of the holder
+    /// credentials: Credentials to present
+    pub fn new(holder: &str, credentials: Vec<DTGCredential>) -> Self {
+        DTGPresentation {
+            context: vec!["https://www.w3.org/ns/credentials/v2".to_string()],
+            id: None,
+            type_: vec!["VerifiablePresentation".to_string()],
+            holder: holder.to_string(),
+            verifiable_credential: credentials,
+            presentation_submission: None,
+            proof: None,
+        }
+    }
+
+    /// Returns the holder DID
+    pub fn holder(&self) -> &str {
+        &self.holder
+    }
+
+    /// The presented credentials
+    pub fn credentials(&self) -> &[DTGCredential] {
+        &self.verifiable_credential
+    }
+
+    /// Has this presentation been signed?
+    /// NOTE: This does NOT validate the proof itself
+    pub fn signed(&self) -> bool {
+        self.proof.is_some()
+    }
+
+    /// The presentation without its proof
+    fn unsecured(&self) -> DTGPresentation {
+        DTGPresentation {
+            proof: None,
+            ..self.clone()
+        }
+    }
+
+    /// Sign the presentation using W3C Data Integrity Proof
+    /// The cryptosuite is selected from the key type:
+    /// Ed25519 uses eddsa-jcs-2022, P-256 and P-384 use ecdsa-jcs-2019
+    /// signing_secret: The holder's key, see [signer::SigningKey]
+    /// challenge: Optional challenge from the verifier
+    /// domain: Optional domain of the verifier
+    /// create_time: Optional creation time for the proof, defaults to now if None
+    pub fn sign(
+        &mut self,
+        signing_secret: &impl signer::SigningKey,
+        challenge: Option<&str>,
+        domain: Option<&str>,
+        create_time: Option<DateTime<Utc>>,
+    ) -> Result<DataIntegrityProof, DTGCredentialError> {
+        self.sign_with_signer(&signing_secret.signer()?, challenge, domain, create_time)
+    }
+
+    /// Sign the presentation using W3C Data Integrity Proof with an external key
+    /// See [DTGPresentation::sign]
+    pub fn sign_with_signer(
+        &mut self,
+        signer: &impl Signer,
+        challenge: Option<&str>,
+        domain: Option<&str>,
+        create_time: Option<DateTime<Utc>>,
+    ) -> Result<DataIntegrityProof, DTGCredentialError> {
+        let algorithm = signer.algorithm();
+        let mut proof = DataIntegrityProof {
+            type_: "DataIntegrityProof".to_string(),
+            cryptosuite: signer::default_cryptosuite(algorithm),
+            created: Some(
+                create_time
+                    .unwrap_or_else(Utc::now)
+                    .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            ),
+            verification_method: signer.verification_method(),
+            proof_purpose: "authentication".to_string(),
+            proof_value: None,
+            context: None,
+            challenge: challenge.map(str::to_string),
+            domain: domain.map(str::to_string),
+        };
+
+        let unsecured = self.unsecured();
+        let data = match proof.cryptosuite {
+            CryptoSuite::EddsaJcs2022 => eddsa::jcs_hash_document(&unsecured, &proof)?,
+            _ => ecdsa::jcs_hash_document(algorithm, &unsecured, &self.context, &proof)?,
+        };
+
+        proof.proof_value = Some(multibase::encode(Base::Base58Btc, signer.sign(&data)?));
+        self.proof = Some(proof.clone());
+        Ok(proof)
+    }
+
+    #[cfg(feature = "signing")]
+    /// Verify the holder's proof if you already know the public key bytes
+    /// The proof must be for `authentication` and carry the expected challenge and domain
+    /// public_key_bytes: The holder's public key bytes
+    /// challenge: Challenge the verifier sent, None if it didn't send one
+    /// domain: Domain of the verifier, None if it didn't send one
+    pub fn verify_proof_with_public_key(
+        &self,
+        public_key_bytes: &[u8],
+        challenge: Option<&str>,
+        domain: Option<&str>,
+    ) -> Result<VerificationProof, DTGCredentialError> {
+        let Some(proof) = &self.proof else {
+            return Err(DTGCredentialError::NotSigned);
+        };
+        if proof.proof_purpose != "authentication" {
+            return Err(presentation_error(format!(
+                "Proof purpose must be authentication, not {}",
+                proof.proof_purpose
+            )));
+        }
+        if proof.challenge.as_deref() != challenge {
+            return Err(presentation_error("Challenge does not match"));
+        }
+        if proof.domain.as_deref() != domain {
+            return Err(presentation_error("Domain does not match"));
+        }
+
+        let unsecured = self.unsecured();
+        match &proof.cryptosuite {
+            CryptoSuite::EddsaJcs2022 => {
+                eddsa::verify_document(&unsecured, proof, public_key_bytes)?
+            }
+            CryptoSuite::EcdsaJcs2019 => ecdsa::verify_document(
+                &unsecured,
+                &self.context,
+                proof,
+                "authentication",
+                public_key_bytes,
+            )?,
+            other => {
+                return Err(DTGCredentialError::UnsupportedCryptoSuite(
+                    other.to_string(),
+                ));
+            }
+        }
+
+        Ok(VerificationProof {
+            verified: true,
+            verified_document: None,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "affinidi-signing"))]
+mod tests {
+    use super::DTGPresentation;
+    use crate::{DTGCredential, DTGCredentialError};
+    use affinidi_secrets_resolver::secrets::Secret;
+    use chrono::Utc;
+
+    fn presentation() -> DTGPresentation {
+        let mut vrc = DTGCredential::new_vrc(
+            "did:example:bob".to_string(),
+            "did:example:alice".to_string(),
+            Utc::now(),
+            None,
+        );
+        vrc.sign(&Secret::generate_ed25519(None, None), None)
+            .unwrap();
+        DTGPresentation::new("did:example:alice", vec![vrc])
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        for holder in [
+            Secret::generate_ed25519(None, None),
+            Secret::generate_p256(None, None).unwrap(),
+        ] {
+            let mut vp = presentation();
+            let proof = vp
+                .sign(&holder, Some("1234"), Some("verifier.example"), None)
+                .unwrap();
+            assert_eq!(proof.proof_purpose, "authentication");
+            assert!(vp.signed());
+
+            // Survives a round trip through JSON
+            let vp: DTGPresentation =
+                serde_json::from_str(&serde_json::to_string(&vp).unwrap()).unwrap();
+            assert_eq!(vp.credentials().len(), 1);
+            assert!(
+                vp.verify_proof_with_public_key(
+                    holder.get_public_bytes(),
+                    Some("1234"),
+                    Some("verifier.example")
+                )
+                .is_ok()
+            );
+            assert!(matches!(
+                vp.verify_proof_with_public_key(
+                    holder.get_public_bytes(),
+                    Some("5678"),
+                    Some("verifier.example")
+                ),
+                Err(DTGCredentialError::Presentation(_))
+            ));
+            assert!(
+                vp.verify_proof_with_public_key(holder.get_public_bytes(), Some("1234"), None)
+                    .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_tampered() {
+        let holder = Secret::generate_ed25519(None, None);
+        let mut vp = presentation();
+        vp.sign(&holder, Some("1234"), None, None).unwrap();
+
+        vp.holder = "did:example:mallory".to_string();
+        assert!(
+            vp.verify_proof_with_public_key(holder.get_public_bytes(), Some("1234"), None)
+                .is_err()
+        );
+
+        assert!(matches!(
+            presentation().verify_proof_with_public_key(holder.get_public_bytes(), None, None),
+            Err(DTGCredentialError::NotSigned)
+        ));
+    }
+}
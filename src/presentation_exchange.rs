@@ -0,0 +1,667 @@
+/*!
+*   DIF Presentation Exchange v2 for DTG Credentials.
+*
+*   <https://identity.foundation/presentation-exchange/spec/v2.0.0/>
+*
+*   A verifier's [PresentationDefinition] is evaluated against the serialized credentials:
+*   - `constraints.fields`: each path is a RFC 9535 JSONPath, the first value found that
+*     satisfies the optional JSON Schema `filter` satisfies the field, e.g. a filter on `$.type`
+*     with `"contains": {"const": "RelationshipCredential"}`
+*   - `submission_requirements`: `all` and `pick` rules over groups of input descriptors and
+*     nested requirements, without them every input descriptor must be satisfied
+*   - `format`: DTG Credentials are `ldp_vc`, descriptors asking for other formats don't match
+*
+*   Credentials are always presented whole, so descriptors with `limit_disclosure: required` are
+*   never satisfied. `predicate` is not supported.
+*
+*   [PresentationDefinition::submit] picks the credentials to present and builds the
+*   `presentation_submission` mapping descriptors to `$.verifiableCredential[n]` of a
+*   [DTGPresentation].
+*/
+
+use crate::{DTGCredential, DTGCredentialError, presentation::DTGPresentation};
+use jsonschema::Validator;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_json_path::JsonPath;
+use std::collections::BTreeSet;
+
+fn presentation_exchange_error(msg: impl Into<String>) -> DTGCredentialError {
+    DTGCredentialError::PresentationExchange(msg.into())
+}
+
+/// Formats DTG Credentials can be presented as
+const FORMATS: [&str; 3] = ["ldp_vc", "ldp", "di_vc"];
+
+/// What a verifier asks to be presented
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PresentationDefinition {
+    pub id: String,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub purpose: Option<String>,
+
+    /// Claim formats the verifier accepts, any if None
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub format: Option<Value>,
+
+    pub input_descriptors: Vec<InputDescriptor>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub submission_requirements: Vec<SubmissionRequirement>,
+}
+
+/// A credential the verifier asks for
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InputDescriptor {
+    pub id: String,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub purpose: Option<String>,
+
+    /// Claim formats accepted for this descriptor, overrides the definition
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub format: Option<Value>,
+
+    /// Groups referenced by [SubmissionRequirement::from]
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub group: Vec<String>,
+
+    #[serde(default)]
+    pub constraints: Constraints,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Constraints {
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub fields: Vec<Field>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub limit_disclosure: Option<LimitDisclosure>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LimitDisclosure {
+    Required,
+    Preferred,
+}
+
+/// A constraint on a value of the credential
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Field {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub id: Option<String>,
+
+    /// JSONPaths tried in order
+    pub path: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub purpose: Option<String>,
+
+    /// JSON Schema the value must satisfy
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub filter: Option<Value>,
+
+    /// The credential matches even if the field isn't satisfied
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub optional: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Rule {
+    All,
+    Pick,
+}
+
+/// Which input descriptors must be submitted
+/// Exactly one of `from` or `from_nested` must be set
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SubmissionRequirement {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub name: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub purpose: Option<String>,
+
+    pub rule: Rule,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub count: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub min: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max: Option<usize>,
+
+    /// Input descriptors in this group
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub from: Option<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub from_nested: Vec<SubmissionRequirement>,
+}
+
+/// Maps input descriptors to the presented credentials
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PresentationSubmission {
+    pub id: String,
+    pub definition_id: String,
+    pub descriptor_map: Vec<DescriptorMapEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DescriptorMapEntry {
+    /// Input descriptor id
+    pub id: String,
+
+    /// Claim format of the credential
+    pub format: String,
+
+    /// JSONPath to the credential
+    pub path: String,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub path_nested: Option<Box<DescriptorMapEntry>>,
+}
+
+/// Result of evaluating a [PresentationDefinition]
+#[derive(Clone, Debug)]
+pub struct Evaluation {
+    /// Indexes of the credentials matching each input descriptor, in definition order
+    pub matches: Vec<(String, Vec<usize>)>,
+
+    /// Input descriptors to submit, None if the submission requirements can't be satisfied
+    pub selected: Option<Vec<String>>,
+}
+
+impl Evaluation {
+    /// Indexes of the credentials matching an input descriptor
+    pub fn matches_for(&self, descriptor_id: &str) -> &[usize] {
+        self.matches
+            .iter()
+            .find(|(id, _)| id == descriptor_id)
+            .map(|(_, matches)| matches.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Can the definition be satisfied?
+    pub fn is_satisfied(&self) -> bool {
+        self.selected.is_some()
+    }
+}
+
+/// A field ready to evaluate
+struct CompiledField {
+    paths: Vec<JsonPath>,
+    filter: Option<Validator>,
+    optional: bool,
+}
+
+impl CompiledField {
+    fn new(field: &Field) -> Result<Self, DTGCredentialError> {
+        Ok(CompiledField {
+            paths: field
+                .path
+                .iter()
+                .map(|path| {
+                    JsonPath::parse(path).map_err(|e| {
+                        presentation_exchange_error(format!("Invalid path {path}: {e}"))
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+            filter: field
+                .filter
+                .as_ref()
+                .map(|filter| {
+                    jsonschema::validator_for(filter)
+                        .map_err(|e| presentation_exchange_error(format!("Invalid filter: {e}")))
+                })
+                .transpose()?,
+            optional: field.optional,
+        })
+    }
+
+    fn matches(&self, credential: &Value) -> bool {
+        self.optional
+            || self.paths.iter().any(|path| {
+                path.query(credential).iter().any(|value| {
+                    self.filter
+                        .as_ref()
+                        .is_none_or(|filter| filter.is_valid(value))
+                })
+            })
+    }
+}
+
+fn supports_format(format: Option<&Value>) -> bool {
+    format.is_none_or(|format| FORMATS.iter().any(|f| format.get(f).is_some()))
+}
+
+/// Random UUID v4 URN for a submission
+fn submission_id() -> String {
+    let mut bytes = [0u8; 16];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    format!(
+        "urn:uuid:{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+impl PresentationDefinition {
+    /// Input descriptors matched by each credential and the descriptors to submit
+    pub fn evaluate(
+        &self,
+        credentials: &[DTGCredential],
+    ) -> Result<Evaluation, DTGCredentialError> {
+        let mut ids = BTreeSet::new();
+        if let Some(descriptor) = self
+            .input_descriptors
+            .iter()
+            .find(|descriptor| !ids.insert(descriptor.id.as_str()))
+        {
+            return Err(presentation_exchange_error(format!(
+                "Duplicate input descriptor id: {}",
+                descriptor.id
+            )));
+        }
+
+        let values = credentials
+            .iter()
+            .map(|credential| {
+                serde_json::to_value(credential).map_err(|e| {
+                    presentation_exchange_error(format!("Couldn't serialize credential: {e}"))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut matches = Vec::new();
+        for descriptor in &self.input_descriptors {
+            let format = descriptor.format.as_ref().or(self.format.as_ref());
+            let fields = descriptor
+                .constraints
+                .fields
+                .iter()
+                .map(CompiledField::new)
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let matching = if supports_format(format)
+                && descriptor.constraints.limit_disclosure != Some(LimitDisclosure::Required)
+            {
+                values
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, value)| fields.iter().all(|field| field.matches(value)))
+                    .map(|(index, _)| index)
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            matches.push((descriptor.id.clone(), matching));
+        }
+
+        let satisfied: BTreeSet<&str> = matches
+            .iter()
+            .filter(|(_, matching)| !matching.is_empty())
+            .map(|(id, _)| id.as_str())
+            .collect();
+        let selected = if self.submission_requirements.is_empty() {
+            (satisfied.len() == self.input_descriptors.len()).then(|| {
+                self.input_descriptors
+                    .iter()
+                    .map(|descriptor| descriptor.id.clone())
+                    .collect()
+            })
+        } else {
+            let mut selected = BTreeSet::new();
+            let mut all = true;
+            for requirement in &self.submission_requirements {
+                match self.select(requirement, &satisfied)? {
+                    Some(ids) => selected.extend(ids),
+                    None => all = false,
+                }
+            }
+            // Submit in definition order
+            all.then(|| {
+                self.input_descriptors
+                    .iter()
+                    .filter(|descriptor| selected.contains(&descriptor.id))
+                    .map(|descriptor| descriptor.id.clone())
+                    .collect()
+            })
+        };
+
+        Ok(Evaluation { matches, selected })
+    }
+
+    /// Input descriptors submitted for a requirement, None if it can't be satisfied
+    /// `pick` submits as many members as it can, up to `count` or `max`
+    fn select(
+        &self,
+        requirement: &SubmissionRequirement,
+        satisfied: &BTreeSet<&str>,
+    ) -> Result<Option<BTreeSet<String>>, DTGCredentialError> {
+        let members: Vec<Option<BTreeSet<String>>> =
+            match (&requirement.from, requirement.from_nested.is_empty()) {
+                (Some(group), true) => self
+                    .input_descriptors
+                    .iter()
+                    .filter(|descriptor| descriptor.group.contains(group))
+                    .map(|descriptor| {
+                        satisfied
+                            .contains(descriptor.id.as_str())
+                            .then(|| BTreeSet::from([descriptor.id.clone()]))
+                    })
+                    .collect(),
+                (None, false) => requirement
+                    .from_nested
+                    .iter()
+                    .map(|nested| self.select(nested, satisfied))
+                    .collect::<Result<_, _>>()?,
+                _ => {
+                    return Err(presentation_exchange_error(
+                        "Submission requirement needs exactly one of from or from_nested",
+                    ));
+                }
+            };
+
+        let total = members.len();
+        let available: Vec<BTreeSet<String>> = members.into_iter().flatten().collect();
+        let (min, max) = match requirement.rule {
+            Rule::All => (total, total),
+            Rule::Pick => match requirement.count {
+                Some(count) => (count, count),
+                None => (
+                    requirement.min.unwrap_or(0),
+                    requirement.max.unwrap_or(total),
+                ),
+            },
+        };
+        if available.len() < min || min > max {
+            return Ok(None);
+        }
+
+        Ok(Some(available.into_iter().take(max).flatten().collect()))
+    }
+
+    /// Picks the credentials to present and maps the input descriptors to them
+    /// The first matching credential is presented for each selected input descriptor
+    /// Returns the credentials for `verifiableCredential` and the `presentation_submission`
+    pub fn submit(
+        &self,
+        credentials: &[DTGCredential],
+    ) -> Result<(Vec<DTGCredential>, PresentationSubmission), DTGCredentialError> {
+        let evaluation = self.evaluate(credentials)?;
+        let Some(selected) = &evaluation.selected else {
+            return Err(presentation_exchange_error(format!(
+                "Presentation definition {} can't be satisfied",
+                self.id
+            )));
+        };
+
+        let mut presented: Vec<usize> = Vec::new();
+        let mut descriptor_map = Vec::new();
+        for id in selected {
+            let Some(&index) = evaluation.matches_for(id).first() else {
+                return Err(presentation_exchange_error(format!(
+                    "No credential matches input descriptor {id}"
+                )));
+            };
+            let position = match presented.iter().position(|p| *p == index) {
+                Some(position) => position,
+                None => {
+                    presented.push(index);
+                    presented.len() - 1
+                }
+            };
+            descriptor_map.push(DescriptorMapEntry {
+                id: id.clone(),
+                format: "ldp_vc".to_string(),
+                path: format!("$.verifiableCredential[{position}]"),
+                path_nested: None,
+            });
+        }
+
+        Ok((
+            presented
+                .into_iter()
+                .map(|index| credentials[index].clone())
+                .collect(),
+            PresentationSubmission {
+                id: submission_id(),
+                definition_id: self.id.clone(),
+                descriptor_map,
+            },
+        ))
+    }
+}
+
+impl DTGPresentation {
+    /// Unsigned presentation of the credentials satisfying a presentation definition, with its
+    /// `presentation_submission`
+    /// holder: DID of the holder
+    /// definition: The verifier's presentation definition
+    /// credentials: Credentials to choose from
+    pub fn from_definition(
+        holder: &str,
+        definition: &PresentationDefinition,
+        credentials: &[DTGCredential],
+    ) -> Result<Self, DTGCredentialError> {
+        let (credentials, submission) = definition.submit(credentials)?;
+        let mut presentation = DTGPresentation::new(holder, credentials);
+        presentation.presentation_submission = Some(submission);
+        Ok(presentation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PresentationDefinition, Rule};
+    use crate::{DTGCredential, DTGCredentialError, presentation::DTGPresentation};
+    use chrono::Utc;
+    use serde_json::json;
+
+    fn credentials() -> Vec<DTGCredential> {
+        vec![
+            DTGCredential::new_vrc(
+                "did:example:bob".to_string(),
+                "did:example:alice".to_string(),
+                Utc::now(),
+                None,
+            ),
+            DTGCredential::new_vmc(
+                "did:example:club".to_string(),
+                "did:example:alice".to_string(),
+                Utc::now(),
+                None,
+                true,
+            ),
+        ]
+    }
+
+    fn type_field(type_: &str) -> serde_json::Value {
+        json!({
+            "path": ["$.type"],
+            "filter": {"type": "array", "contains": {"const": type_}}
+        })
+    }
+
+    #[test]
+    fn test_fields_and_filters() {
+        let definition: PresentationDefinition = serde_json::from_value(json!({
+            "id": "definition",
+            "input_descriptors": [
+                {"id": "relationship", "constraints": {"fields": [
+                    type_field("RelationshipCredential"),
+                    {"path": ["$.issuer"], "filter": {"const": "did:example:bob"}}
+                ]}},
+                {"id": "personhood", "constraints": {"fields": [
+                    type_field("PersonhoodCredential"),
+                    {"path": ["$.credentialSubject.id"], "filter": {"pattern": "^did:example:"}},
+                    {"path": ["$.credentialSubject.nickname"], "optional": true}
+                ]}},
+                {"id": "any", "format": {"ldp_vc": {"proof_type": ["DataIntegrityProof"]}}}
+            ]
+        }))
+        .unwrap();
+
+        let evaluation = definition.evaluate(&credentials()).unwrap();
+        assert_eq!(evaluation.matches_for("relationship"), &[0]);
+        assert_eq!(evaluation.matches_for("personhood"), &[1]);
+        assert_eq!(evaluation.matches_for("any"), &[0, 1]);
+        assert!(evaluation.is_satisfied());
+
+        let (presented, submission) = definition.submit(&credentials()).unwrap();
+        assert_eq!(presented.len(), 2);
+        assert_eq!(submission.definition_id, "definition");
+        let paths: Vec<&str> = submission
+            .descriptor_map
+            .iter()
+            .map(|entry| entry.path.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "$.verifiableCredential[0]",
+                "$.verifiableCredential[1]",
+                "$.verifiableCredential[0]"
+            ]
+        );
+
+        // Only the VRC can't satisfy the personhood descriptor
+        let evaluation = definition.evaluate(&credentials()[..1]).unwrap();
+        assert!(!evaluation.is_satisfied());
+        assert!(matches!(
+            DTGPresentation::from_definition("did:example:alice", &definition, &credentials()[..1]),
+            Err(DTGCredentialError::PresentationExchange(_))
+        ));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let definition: PresentationDefinition = serde_json::from_value(json!({
+            "id": "definition",
+            "input_descriptors": [
+                {"id": "jwt", "format": {"jwt_vc": {"alg": ["ES256"]}}},
+                {"id": "limited", "constraints": {"limit_disclosure": "required"}}
+            ]
+        }))
+        .unwrap();
+        let evaluation = definition.evaluate(&credentials()).unwrap();
+        assert!(evaluation.matches_for("jwt").is_empty());
+        assert!(evaluation.matches_for("limited").is_empty());
+
+        let definition: PresentationDefinition = serde_json::from_value(json!({
+            "id": "definition",
+            "input_descriptors": [
+                {"id": "bad", "constraints": {"fields": [{"path": ["$[?bad"]}]}}
+            ]
+        }))
+        .unwrap();
+        assert!(matches!(
+            definition.evaluate(&credentials()),
+            Err(DTGCredentialError::PresentationExchange(_))
+        ));
+    }
+
+    #[test]
+    fn test_submission_requirements() {
+        let mut definition: PresentationDefinition = serde_json::from_value(json!({
+            "id": "definition",
+            "submission_requirements": [
+                {"rule": "pick", "count": 1, "from": "A"},
+                {"rule": "all", "from_nested": [
+                    {"rule": "pick", "min": 1, "from": "B"}
+                ]}
+            ],
+            "input_descriptors": [
+                {"id": "relationship", "group": ["A"], "constraints": {"fields": [type_field("RelationshipCredential")]}},
+                {"id": "membership", "group": ["A"], "constraints": {"fields": [type_field("MembershipCredential")]}},
+                {"id": "persona", "group": ["B"], "constraints": {"fields": [type_field("PersonaCredential")]}},
+                {"id": "personhood", "group": ["B"], "constraints": {"fields": [type_field("PersonhoodCredential")]}}
+            ]
+        }))
+        .unwrap();
+
+        let evaluation = definition.evaluate(&credentials()).unwrap();
+        assert_eq!(
+            evaluation.selected.unwrap(),
+            ["relationship".to_string(), "personhood".to_string()]
+        );
+
+        let presentation =
+            DTGPresentation::from_definition("did:example:alice", &definition, &credentials())
+                .unwrap();
+        assert_eq!(presentation.credentials().len(), 2);
+        let submission = presentation.presentation_submission.as_ref().unwrap();
+        assert!(submission.id.starts_with("urn:uuid:"));
+        let json = serde_json::to_value(&presentation).unwrap();
+        assert_eq!(
+            json["presentation_submission"]["descriptor_map"][1]["id"],
+            "personhood"
+        );
+
+        // Both members of A are required now
+        definition.submission_requirements[0].rule = Rule::All;
+        assert_eq!(
+            definition
+                .evaluate(&credentials())
+                .unwrap()
+                .selected
+                .unwrap(),
+            [
+                "relationship".to_string(),
+                "membership".to_string(),
+                "personhood".to_string()
+            ]
+        );
+
+        // B needs two
+        definition.submission_requirements[1].from_nested[0].min = Some(2);
+        assert!(!definition.evaluate(&credentials()).unwrap().is_satisfied());
+
+        definition.submission_requirements[1].from = Some("B".to_string());
+        assert!(definition.evaluate(&credentials()).is_err());
+    }
+
+    #[test]
+    fn test_duplicate_descriptor_ids() {
+        let definition: PresentationDefinition = serde_json::from_value(json!({
+            "id": "definition",
+            "submission_requirements": [{"rule": "pick", "count": 1, "from": "A"}],
+            "input_descriptors": [
+                {"id": "x", "group": ["A"], "constraints": {"fields": [type_field("PersonaCredential")]}},
+                {"id": "x", "group": ["A"], "constraints": {"fields": [type_field("RelationshipCredential")]}}
+            ]
+        }))
+        .unwrap();
+
+        assert!(matches!(
+            definition.evaluate(&credentials()),
+            Err(DTGCredentialError::PresentationExchange(_))
+        ));
+        assert!(matches!(
+            definition.submit(&credentials()),
+            Err(DTGCredentialError::PresentationExchange(_))
+        ));
+    }
+}
@@ -0,0 +1,186 @@
+/*!
+*   W3C Data Integrity Proof attached to DTG Credentials.
+*
+*   Mirrors `affinidi_data_integrity::DataIntegrityProof`, but recognizes every cryptosuite this
+*   crate can produce so that received credentials deserialize regardless of how they were
+*   secured.
+*/
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::Display;
+
+#[cfg(feature = "affinidi-signing")]
+use crate::DTGCredentialError;
+
+/// Cryptosuites recognized on a DTG Credential proof
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[non_exhaustive]
+pub enum CryptoSuite {
+    /// EDDSA JCS 2022 spec
+    /// https://www.w3.org/TR/vc-di-eddsa/
+    #[serde(rename = "eddsa-jcs-2022")]
+    EddsaJcs2022,
+
+    /// EDDSA RDFC 2022 spec, see [crate::rdfc]
+    /// https://www.w3.org/TR/vc-di-eddsa/
+    #[serde(rename = "eddsa-rdfc-2022")]
+    EddsaRdfc2022,
+
+    /// ECDSA JCS 2019 spec, P-256 and P-384 keys, see [crate::ecdsa]
+    /// https://www.w3.org/TR/vc-di-ecdsa/
+    #[serde(rename = "ecdsa-jcs-2019")]
+    EcdsaJcs2019,
+
+    /// ECDSA SD 2023 spec, selective disclosure with P-256 keys, see [crate::ecdsa_sd]
+    /// https://www.w3.org/TR/vc-di-ecdsa/
+    #[serde(rename = "ecdsa-sd-2023")]
+    EcdsaSd2023,
+
+    /// Selective disclosure using EdDSA over JCS canonicalized statements
+    /// Private to this crate and NOT interoperable with ecdsa-sd-2023 or any other W3C
+    /// cryptosuite, only verifiers using this crate accept it. See [crate::selective_disclosure]
+    #[serde(rename = "x-dtg-eddsa-sd-jcs")]
+    XDtgEddsaSdJcs,
+}
+
+impl Display for CryptoSuite {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CryptoSuite::EddsaJcs2022 => write!(f, "eddsa-jcs-2022"),
+            CryptoSuite::EddsaRdfc2022 => write!(f, "eddsa-rdfc-2022"),
+            CryptoSuite::EcdsaJcs2019 => write!(f, "ecdsa-jcs-2019"),
+            CryptoSuite::EcdsaSd2023 => write!(f, "ecdsa-sd-2023"),
+            CryptoSuite::XDtgEddsaSdJcs => write!(f, "x-dtg-eddsa-sd-jcs"),
+        }
+    }
+}
+
+/// Data Integrity Proof
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct DataIntegrityProof {
+    /// Must be 'DataIntegrityProof'
+    #[serde(rename = "type")]
+    pub type_: String,
+
+    pub cryptosuite: CryptoSuite,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+
+    pub verification_method: String,
+
+    pub proof_purpose: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_value: Option<String>,
+
+    #[serde(rename = "@context", skip_serializing_if = "Option::is_none")]
+    pub context: Option<Vec<String>>,
+
+    /// Verifier challenge, used by presentation proofs
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub challenge: Option<String>,
+
+    /// Verifier domain, used by presentation proofs
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub domain: Option<String>,
+}
+
+/// Outcome of verifying a Data Integrity Proof
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct VerificationProof {
+    /// true or false
+    pub verified: bool,
+
+    /// the verified document or None
+    pub verified_document: Option<Value>,
+}
+
+#[cfg(feature = "affinidi-signing")]
+impl From<affinidi_data_integrity::DataIntegrityProof> for DataIntegrityProof {
+    fn from(proof: affinidi_data_integrity::DataIntegrityProof) -> Self {
+        DataIntegrityProof {
+            type_: proof.type_,
+            // affinidi_data_integrity only knows about eddsa-jcs-2022
+            cryptosuite: CryptoSuite::EddsaJcs2022,
+            created: proof.created,
+            verification_method: proof.verification_method,
+            proof_purpose: proof.proof_purpose,
+            proof_value: proof.proof_value,
+            context: proof.context,
+            challenge: None,
+            domain: None,
+        }
+    }
+}
+
+/// Converts back to the affinidi_data_integrity proof so it can be used with the Affinidi TDK
+/// Only eddsa-jcs-2022 proofs without a challenge or domain can be converted
+#[cfg(feature = "affinidi-signing")]
+impl TryFrom<DataIntegrityProof> for affinidi_data_integrity::DataIntegrityProof {
+    type Error = DTGCredentialError;
+
+    fn try_from(proof: DataIntegrityProof) -> Result<Self, Self::Error> {
+        if proof.challenge.is_some() || proof.domain.is_some() {
+            return Err(DTGCredentialError::DataIntegrity(
+                "affinidi_data_integrity proofs have no challenge or domain".to_string(),
+            ));
+        }
+        match proof.cryptosuite {
+            CryptoSuite::EddsaJcs2022 => Ok(affinidi_data_integrity::DataIntegrityProof {
+                type_: proof.type_,
+                cryptosuite: affinidi_data_integrity::crypto_suites::CryptoSuite::EddsaJcs2022,
+                created: proof.created,
+                verification_method: proof.verification_method,
+                proof_purpose: proof.proof_purpose,
+                proof_value: proof.proof_value,
+                context: proof.context,
+            }),
+            other => Err(DTGCredentialError::UnsupportedCryptoSuite(
+                other.to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CryptoSuite, DataIntegrityProof};
+
+    #[test]
+    fn test_deserialize_sd_proof() {
+        let proof: DataIntegrityProof = serde_json::from_str(
+            r#"{
+                "type": "DataIntegrityProof",
+                "cryptosuite": "x-dtg-eddsa-sd-jcs",
+                "verificationMethod": "did:example:test#key-1",
+                "proofPurpose": "assertionMethod",
+                "proofValue": "uabcd"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(proof.cryptosuite, CryptoSuite::XDtgEddsaSdJcs);
+        #[cfg(feature = "affinidi-signing")]
+        assert!(affinidi_data_integrity::DataIntegrityProof::try_from(proof).is_err());
+    }
+
+    #[test]
+    fn test_unknown_cryptosuite() {
+        assert!(
+            serde_json::from_str::<DataIntegrityProof>(
+                r#"{
+                "type": "DataIntegrityProof",
+                "cryptosuite": "unknown-suite",
+                "verificationMethod": "did:example:test#key-1",
+                "proofPurpose": "assertionMethod"
+            }"#,
+            )
+            .is_err()
+        );
+    }
+}
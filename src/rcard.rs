@@ -0,0 +1,432 @@
+/*!
+*   RCard versioning and supersession.
+*
+*   Each superseding RCard carries an increasing `sequence` and the digest of the RCard it
+*   `replaces`. Holders use [RCardHistory] to select the current RCard from those received,
+*   detect forks (conflicting RCards for the same version), and diff versions field by field.
+*
+*   NOTE: Credentials must be verified before being added to the history.
+*/
+
+use crate::{
+    CredentialSubject, CredentialSubjectRCard, DTGCredential, DTGCredentialError,
+    jcard::{JCard, JCardProperty},
+};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use serde_json_canonicalizer::to_string;
+use sha2::{Digest, Sha256};
+
+fn rcard_error(msg: impl Into<String>) -> DTGCredentialError {
+    DTGCredentialError::RCard(msg.into())
+}
+
+impl DTGCredential {
+    /// Digest identifying this credential, including any proof
+    /// Format: `sha256:<hex of the JCS canonical credential>`
+    pub fn digest(&self) -> Result<String, DTGCredentialError> {
+        let canonical = to_string(self).map_err(|e| {
+            DTGCredentialError::RCard(format!("Couldn't canonicalize credential: {e}"))
+        })?;
+        Ok(format!(
+            "sha256:{}",
+            Sha256::digest(canonical.as_bytes())
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect::<String>()
+        ))
+    }
+
+    /// RCard sequence, RCards without a sequence are version 0
+    /// Returns None if this is not an RCard
+    pub fn rcard_sequence(&self) -> Option<u64> {
+        self.rcard_subject().map(|s| s.sequence.unwrap_or(0))
+    }
+
+    /// Digest of the RCard this RCard supersedes
+    pub fn rcard_replaces(&self) -> Option<&str> {
+        self.rcard_subject().and_then(|s| s.replaces.as_deref())
+    }
+
+    fn rcard_subject(&self) -> Option<&CredentialSubjectRCard> {
+        match &self.credential.credential_subject {
+            CredentialSubject::RCard(subject) => Some(subject),
+            _ => None,
+        }
+    }
+
+    /// Creates a new RCard superseding this RCard
+    /// Sign this RCard first, the digest of the signed credential is referenced
+    /// valid_from: The datetime from which the new RCard is valid
+    /// valid_until: Optional: The datetime the new RCard is valid until
+    /// card: The updated Jcard
+    pub fn supersede_rcard(
+        &self,
+        valid_from: DateTime<Utc>,
+        valid_until: Option<DateTime<Utc>>,
+        card: impl Into<Value>,
+    ) -> Result<DTGCredential, DTGCredentialError> {
+        let Some(sequence) = self.rcard_sequence() else {
+            return Err(rcard_error("Only an RCard can be superseded"));
+        };
+
+        let mut rcard = DTGCredential::new_rcard(
+            self.issuer().to_string(),
+            self.subject().to_string(),
+            valid_from,
+            valid_until,
+            card,
+        );
+        if let CredentialSubject::RCard(subject) = &mut rcard.credential.credential_subject {
+            subject.sequence = Some(sequence + 1);
+            subject.replaces = Some(self.digest()?);
+        }
+        Ok(rcard)
+    }
+}
+
+/// Conflicting RCards for the same version
+#[derive(Debug)]
+pub struct RCardFork<'a> {
+    /// Sequence the RCards conflict at
+    pub sequence: u64,
+
+    /// The conflicting RCards
+    pub rcards: Vec<&'a DTGCredential>,
+}
+
+/// RCards received for the same issuer and subject
+#[derive(Debug)]
+pub struct RCardHistory {
+    rcards: Vec<(String, DTGCredential)>,
+}
+
+impl RCardHistory {
+    /// rcards: Verified RCards, all from the same issuer for the same subject
+    pub fn new(rcards: Vec<DTGCredential>) -> Result<Self, DTGCredentialError> {
+        let mut history = RCardHistory { rcards: Vec::new() };
+        for rcard in rcards {
+            history.add(rcard)?;
+        }
+        Ok(history)
+    }
+
+    /// Adds a received RCard, duplicates are ignored
+    pub fn add(&mut self, rcard: DTGCredential) -> Result<(), DTGCredentialError> {
+        if rcard.rcard_subject().is_none() {
+            return Err(rcard_error("Credential is not an RCard"));
+        }
+        if let Some((_, first)) = self.rcards.first()
+            && (first.issuer() != rcard.issuer() || first.subject() != rcard.subject())
+        {
+            return Err(rcard_error("RCards must have the same issuer and subject"));
+        }
+
+        let digest = rcard.digest()?;
+        if !self.rcards.iter().any(|(d, _)| *d == digest) {
+            self.rcards.push((digest, rcard));
+        }
+        Ok(())
+    }
+
+    /// All RCards, ordered by sequence
+    pub fn rcards(&self) -> Vec<&DTGCredential> {
+        let mut rcards: Vec<&DTGCredential> = self.rcards.iter().map(|(_, r)| r).collect();
+        rcards.sort_by_key(|r| r.rcard_sequence());
+        rcards
+    }
+
+    /// Finds an RCard by digest
+    pub fn get(&self, digest: &str) -> Option<&DTGCredential> {
+        self.rcards
+            .iter()
+            .find(|(d, _)| d == digest)
+            .map(|(_, r)| r)
+    }
+
+    /// Conflicting RCards: different RCards with the same sequence, or RCards superseding the
+    /// same RCard
+    pub fn forks(&self) -> Vec<RCardFork<'_>> {
+        let mut forks: Vec<RCardFork> = Vec::new();
+        for (_, rcard) in &self.rcards {
+            let sequence = rcard.rcard_sequence().unwrap_or(0);
+            let conflicting: Vec<&DTGCredential> = self
+                .rcards
+                .iter()
+                .map(|(_, other)| other)
+                .filter(|other| {
+                    other.rcard_sequence() == Some(sequence)
+                        || (rcard.rcard_replaces().is_some()
+                            && other.rcard_replaces() == rcard.rcard_replaces())
+                })
+                .collect();
+
+            if conflicting.len() > 1 && !forks.iter().any(|f| f.sequence == sequence) {
+                forks.push(RCardFork {
+                    sequence,
+                    rcards: conflicting,
+                });
+            }
+        }
+        forks.sort_by_key(|f| f.sequence);
+        forks
+    }
+
+    /// The current RCard at a point in time: the highest sequence that is valid at that time
+    /// Returns an error if there is a fork at that sequence
+    pub fn current_at(
+        &self,
+        at: DateTime<Utc>,
+    ) -> Result<Option<&DTGCredential>, DTGCredentialError> {
+        let Some(current) = self
+            .rcards
+            .iter()
+            .map(|(_, r)| r)
+            .filter(|r| r.is_valid_at(at))
+            .max_by_key(|r| r.rcard_sequence())
+        else {
+            return Ok(None);
+        };
+
+        let sequence = current.rcard_sequence().unwrap_or(0);
+        if self.forks().iter().any(|f| f.sequence == sequence) {
+            return Err(rcard_error(format!("RCards fork at sequence {sequence}")));
+        }
+        Ok(Some(current))
+    }
+
+    /// The current RCard
+    pub fn current(&self) -> Result<Option<&DTGCredential>, DTGCredentialError> {
+        self.current_at(Utc::now())
+    }
+}
+
+/// A single field change between two versions of an RCard
+#[derive(Clone, Debug, PartialEq)]
+pub enum RCardChange {
+    Added(JCardProperty),
+    Removed(JCardProperty),
+    Changed {
+        old: JCardProperty,
+        new: JCardProperty,
+    },
+}
+
+impl RCardChange {
+    /// Name of the changed property
+    pub fn name(&self) -> &str {
+        match self {
+            RCardChange::Added(p) | RCardChange::Removed(p) => &p.name,
+            RCardChange::Changed { new, .. } => &new.name,
+        }
+    }
+}
+
+impl JCard {
+    /// Field level changes from this jCard to a newer jCard
+    /// A property that occurs once in both is reported as changed, otherwise properties are
+    /// reported as added or removed
+    pub fn diff(&self, newer: &JCard) -> Vec<RCardChange> {
+        let mut names: Vec<&str> = Vec::new();
+        for property in self.properties().iter().chain(newer.properties()) {
+            if !names.contains(&property.name.as_str()) {
+                names.push(&property.name);
+            }
+        }
+
+        let mut changes = Vec::new();
+        for name in names {
+            let old: Vec<&JCardProperty> = self.properties_named(name).collect();
+            let new: Vec<&JCardProperty> = newer.properties_named(name).collect();
+
+            if let ([old], [new]) = (old.as_slice(), new.as_slice()) {
+                if old != new {
+                    changes.push(RCardChange::Changed {
+                        old: (*old).clone(),
+                        new: (*new).clone(),
+                    });
+                }
+                continue;
+            }
+
+            changes.extend(
+                old.iter()
+                    .filter(|p| !new.contains(p))
+                    .map(|p| RCardChange::Removed((*p).clone())),
+            );
+            changes.extend(
+                new.iter()
+                    .filter(|p| !old.contains(p))
+                    .map(|p| RCardChange::Added((*p).clone())),
+            );
+        }
+        changes
+    }
+}
+
+impl DTGCredential {
+    /// Field level changes from this RCard to a newer RCard
+    pub fn rcard_diff(
+        &self,
+        newer: &DTGCredential,
+    ) -> Result<Vec<RCardChange>, DTGCredentialError> {
+        match (self.jcard()?, newer.jcard()?) {
+            (Some(old), Some(new)) => Ok(old.diff(&new)),
+            _ => Err(rcard_error("Both credentials must be RCards")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RCardChange, RCardHistory};
+    use crate::{DTGCredential, jcard::JCard};
+    use chrono::{Duration, Utc};
+
+    fn rcard() -> DTGCredential {
+        DTGCredential::new_rcard(
+            "did:example:alice".to_string(),
+            "did:example:alice".to_string(),
+            Utc::now() - Duration::days(10),
+            None,
+            JCard::builder("Alice Smith")
+                .email("alice@example.com")
+                .tel("tel:+1-555-555-0100")
+                .build(),
+        )
+    }
+
+    #[test]
+    fn test_supersede() {
+        let v0 = rcard();
+        let v1 = v0
+            .supersede_rcard(
+                Utc::now() - Duration::days(5),
+                None,
+                JCard::builder("Alice Smith")
+                    .email("alice@example.org")
+                    .build(),
+            )
+            .unwrap();
+        let v2 = v1
+            .supersede_rcard(
+                Utc::now() + Duration::days(5),
+                None,
+                JCard::builder("Alice Jones")
+                    .email("alice@example.org")
+                    .build(),
+            )
+            .unwrap();
+
+        assert_eq!(v0.rcard_sequence(), Some(0));
+        assert_eq!(v1.rcard_sequence(), Some(1));
+        assert_eq!(v1.rcard_replaces(), Some(v0.digest().unwrap().as_str()));
+        assert_eq!(v2.rcard_sequence(), Some(2));
+
+        // Survives serialization and CBOR
+        let json: DTGCredential =
+            serde_json::from_str(&serde_json::to_string(&v2).unwrap()).unwrap();
+        assert_eq!(json.digest().unwrap(), v2.digest().unwrap());
+        #[cfg(feature = "affinidi-signing")]
+        {
+            let cbor = DTGCredential::from_cbor(&v2.to_cbor().unwrap()).unwrap();
+            assert_eq!(cbor.rcard_replaces(), v2.rcard_replaces());
+        }
+
+        let history = RCardHistory::new(vec![v2.clone(), v0.clone(), v1.clone()]).unwrap();
+        assert!(history.forks().is_empty());
+        assert_eq!(history.rcards()[0].digest().unwrap(), v0.digest().unwrap());
+        assert!(history.get(v1.rcard_replaces().unwrap()).is_some());
+
+        // v2 isn't valid yet
+        let current = history.current().unwrap().unwrap();
+        assert_eq!(current.digest().unwrap(), v1.digest().unwrap());
+        let future = history
+            .current_at(Utc::now() + Duration::days(6))
+            .unwrap()
+            .unwrap();
+        assert_eq!(future.digest().unwrap(), v2.digest().unwrap());
+
+        let vrc = DTGCredential::new_vrc(
+            "did:example:alice".to_string(),
+            "did:example:bob".to_string(),
+            Utc::now(),
+            None,
+        );
+        assert!(
+            vrc.supersede_rcard(Utc::now(), None, JCard::default())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_fork() {
+        let v0 = rcard();
+        let a = v0
+            .supersede_rcard(Utc::now(), None, JCard::builder("Alice A").build())
+            .unwrap();
+        let b = v0
+            .supersede_rcard(Utc::now(), None, JCard::builder("Alice B").build())
+            .unwrap();
+
+        let history = RCardHistory::new(vec![v0, a, b]).unwrap();
+        let forks = history.forks();
+        assert_eq!(forks.len(), 1);
+        assert_eq!(forks[0].sequence, 1);
+        assert_eq!(forks[0].rcards.len(), 2);
+        assert!(history.current().is_err());
+    }
+
+    #[test]
+    fn test_history_rejects_mixed() {
+        let bob = DTGCredential::new_rcard(
+            "did:example:bob".to_string(),
+            "did:example:bob".to_string(),
+            Utc::now(),
+            None,
+            JCard::builder("Bob").build(),
+        );
+        assert!(RCardHistory::new(vec![rcard(), bob]).is_err());
+
+        let vrc = DTGCredential::new_vrc(
+            "did:example:alice".to_string(),
+            "did:example:alice".to_string(),
+            Utc::now(),
+            None,
+        );
+        assert!(RCardHistory::new(vec![vrc]).is_err());
+
+        // Duplicates are ignored
+        let v0 = rcard();
+        let history = RCardHistory::new(vec![v0.clone(), v0]).unwrap();
+        assert_eq!(history.rcards().len(), 1);
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = rcard();
+        let new = old
+            .supersede_rcard(
+                Utc::now(),
+                None,
+                JCard::builder("Alice Smith")
+                    .email("alice@example.org")
+                    .url("https://alice.example.com")
+                    .build(),
+            )
+            .unwrap();
+
+        let changes = old.rcard_diff(&new).unwrap();
+        assert_eq!(changes.len(), 3);
+        assert!(matches!(
+            &changes[0],
+            RCardChange::Changed { old, new }
+                if old.text() == Some("alice@example.com") && new.text() == Some("alice@example.org")
+        ));
+        assert!(matches!(&changes[1], RCardChange::Removed(p) if p.name == "tel"));
+        assert!(matches!(&changes[2], RCardChange::Added(p) if p.name == "url"));
+        assert_eq!(changes[2].name(), "url");
+
+        assert!(old.rcard_diff(&old).unwrap().is_empty());
+    }
+}
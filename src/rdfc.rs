@@ -0,0 +1,670 @@
+/*!
+*   RDF Dataset Canonicalization (RDFC-1.0) and the `eddsa-rdfc-2022` cryptosuite.
+*
+*   <https://www.w3.org/TR/rdf-canon/>
+*   <https://www.w3.org/TR/vc-di-eddsa/#eddsa-rdfc-2022>
+*
+*   Credentials are expanded to RDF using contexts from a [DocumentLoader], nothing is fetched
+*   over the network. The DTG context defines `endorsement` and `card` as `@json` so their content
+*   is signed as a canonical JSON literal.
+*/
+
+use crate::{
+    DTGCommon, DTGCredential, DTGCredentialError,
+    contexts::{DocumentLoader, document_loader},
+    proof::DataIntegrityProof,
+};
+#[cfg(feature = "signing")]
+use affinidi_rdf_encoding::{
+    Dataset, GraphLabel, Object, Quad, Subject,
+    rdfc1::{
+        hash_first_degree::hash_first_degree_quads, hash_ndegree::hash_ndegree_quads,
+        identifier_issuer::IdentifierIssuer,
+    },
+};
+use affinidi_rdf_encoding::{jsonld, rdfc1};
+use serde_json::{Map, Value, json};
+use sha2::{Digest, Sha256};
+#[cfg(feature = "signing")]
+use std::collections::{BTreeMap, HashMap};
+use std::{collections::HashSet, fmt::Display};
+
+/// Datatype of JSON literals
+const RDF_JSON: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#JSON";
+
+fn rdfc_error(msg: impl Display) -> DTGCredentialError {
+    DTGCredentialError::Rdfc(msg.to_string())
+}
+
+/// Terms defined as `@json` anywhere in a context definition
+fn json_terms(context: &Value, terms: &mut HashSet<String>) {
+    match context {
+        Value::Array(contexts) => contexts.iter().for_each(|c| json_terms(c, terms)),
+        Value::Object(definitions) => {
+            for (term, definition) in definitions {
+                if definition.get("@type") == Some(&json!("@json")) {
+                    terms.insert(term.clone());
+                }
+                if let Some(scoped) = definition.get("@context") {
+                    json_terms(scoped, terms);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Prepares a document for expansion
+/// Contexts are inlined from the loader and `@json` values are converted to canonical JSON
+/// literals
+fn prepare(
+    value: &Value,
+    loader: &DocumentLoader,
+    terms: &HashSet<String>,
+) -> Result<Value, DTGCredentialError> {
+    Ok(match value {
+        Value::Object(map) => {
+            let mut terms = terms.clone();
+            let context = map
+                .get("@context")
+                .map(|context| loader.inline(context))
+                .transpose()?;
+            if let Some(context) = &context {
+                json_terms(context, &mut terms);
+            }
+
+            let mut prepared = Map::new();
+            for (key, value) in map {
+                let value = if key == "@context" {
+                    context.clone().unwrap_or_default()
+                } else if terms.contains(key) {
+                    json!({
+                        "@value": serde_json_canonicalizer::to_string(value).map_err(rdfc_error)?,
+                        "@type": RDF_JSON
+                    })
+                } else {
+                    prepare(value, loader, &terms)?
+                };
+                prepared.insert(key.clone(), value);
+            }
+            Value::Object(prepared)
+        }
+        Value::Array(values) => Value::Array(
+            values
+                .iter()
+                .map(|value| prepare(value, loader, terms))
+                .collect::<Result<_, _>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+/// Canonical N-Quads of a JSON-LD document
+/// Contexts are resolved with the shared loader, see [crate::contexts::register_context]
+pub fn canonicalize(document: &Value) -> Result<String, DTGCredentialError> {
+    canonicalize_with_loader(document, &document_loader()?)
+}
+
+/// Canonical N-Quads of a JSON-LD document, resolving contexts with the given loader
+pub fn canonicalize_with_loader(
+    document: &Value,
+    loader: &DocumentLoader,
+) -> Result<String, DTGCredentialError> {
+    let prepared = prepare_document(document, loader)?;
+    let dataset = jsonld::expand_and_to_rdf(&prepared).map_err(rdfc_error)?;
+    rdfc1::canonicalize(&dataset).map_err(rdfc_error)
+}
+
+/// Inlines the contexts of a document and converts its `@json` values, ready for expansion
+pub(crate) fn prepare_document(
+    document: &Value,
+    loader: &DocumentLoader,
+) -> Result<Value, DTGCredentialError> {
+    prepare(document, loader, &HashSet::new())
+}
+
+#[cfg(feature = "signing")]
+/// Blank node labels in a quad
+fn blank_nodes(quad: &Quad) -> impl Iterator<Item = &str> {
+    let subject = match &quad.subject {
+        Subject::Blank(node) => Some(node.id.as_str()),
+        _ => None,
+    };
+    let object = match &quad.object {
+        Object::Blank(node) => Some(node.id.as_str()),
+        _ => None,
+    };
+    let graph = match &quad.graph {
+        GraphLabel::Blank(node) => Some(node.id.as_str()),
+        _ => None,
+    };
+    [subject, object, graph].into_iter().flatten()
+}
+
+#[cfg(feature = "signing")]
+/// Canonical label (`c14n0`, `c14n1`, ...) RDFC-1.0 issues to each blank node of a dataset
+/// Labels are without the `_:` prefix, the same as [rdfc1::canonicalize] outputs
+pub(crate) fn canonical_labels(
+    dataset: &Dataset,
+) -> Result<HashMap<String, String>, DTGCredentialError> {
+    let mut blank_node_to_quads: HashMap<String, Vec<&Quad>> = HashMap::new();
+    for quad in dataset.quads() {
+        for id in blank_nodes(quad) {
+            blank_node_to_quads
+                .entry(id.to_string())
+                .or_default()
+                .push(quad);
+        }
+    }
+
+    let mut hash_to_blank_nodes: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (id, quads) in &blank_node_to_quads {
+        hash_to_blank_nodes
+            .entry(hash_first_degree_quads(id, quads))
+            .or_default()
+            .push(id.clone());
+    }
+    let blank_node_to_hash: HashMap<String, String> = hash_to_blank_nodes
+        .iter()
+        .flat_map(|(hash, ids)| ids.iter().map(move |id| (id.clone(), hash.clone())))
+        .collect();
+
+    // Unique first degree hashes are labelled first, in hash order
+    let mut canonical = IdentifierIssuer::new("c14n");
+    for ids in hash_to_blank_nodes.values() {
+        if let [id] = ids.as_slice() {
+            canonical.issue(id);
+        }
+    }
+
+    for ids in hash_to_blank_nodes.values().filter(|ids| ids.len() > 1) {
+        let mut paths = Vec::new();
+        for id in ids {
+            if canonical.is_issued(id) {
+                continue;
+            }
+            let mut temporary = IdentifierIssuer::new("b");
+            temporary.issue(id);
+            paths.push(
+                hash_ndegree_quads(
+                    id,
+                    &blank_node_to_quads,
+                    &canonical,
+                    &temporary,
+                    &blank_node_to_hash,
+                )
+                .map_err(rdfc_error)?,
+            );
+        }
+        paths.sort_by(|a, b| a.0.cmp(&b.0));
+        for (_, issuer) in paths {
+            for id in issuer.issued_order() {
+                canonical.issue(id);
+            }
+        }
+    }
+
+    Ok(canonical
+        .issued_order()
+        .iter()
+        .filter_map(|id| Some((id.clone(), canonical.get(id)?.to_string())))
+        .collect())
+}
+
+impl DTGCredential {
+    /// RDFC-1.0 canonical N-Quads of the credential, excluding any proof
+    pub fn canonicalize_rdfc(&self) -> Result<String, DTGCredentialError> {
+        canonicalize(&unsigned_document(&self.credential)?)
+    }
+}
+
+fn unsigned_document(credential: &DTGCommon) -> Result<Value, DTGCredentialError> {
+    serde_json::to_value(DTGCommon {
+        proof: None,
+        ..credential.clone()
+    })
+    .map_err(|e| rdfc_error(format!("Couldn't serialize: {e}")))
+}
+
+/// SHA-256 of the canonical N-Quads of a JSON-LD document
+fn canonical_hash(document: &Value) -> Result<[u8; 32], DTGCredentialError> {
+    Ok(Sha256::digest(canonicalize(document)?.as_bytes()).into())
+}
+
+/// proofConfigHash || documentHash
+pub(crate) fn hash_data(
+    credential: &DTGCredential,
+    proof: &DataIntegrityProof,
+) -> Result<Vec<u8>, DTGCredentialError> {
+    hash_document(&unsigned_document(&credential.credential)?, proof)
+}
+
+/// proofConfigHash || documentHash
+/// document: The unsecured JSON-LD document, without a proof
+pub(crate) fn hash_document(
+    document: &Value,
+    proof: &DataIntegrityProof,
+) -> Result<Vec<u8>, DTGCredentialError> {
+    let mut data = proof_config_hash(document, proof)?.to_vec();
+    data.extend_from_slice(&canonical_hash(document)?);
+    Ok(data)
+}
+
+/// SHA-256 of the canonical proof configuration, using the `@context` of the document
+pub(crate) fn proof_config_hash(
+    document: &Value,
+    proof: &DataIntegrityProof,
+) -> Result<[u8; 32], DTGCredentialError> {
+    let mut config = serde_json::to_value(DataIntegrityProof {
+        proof_value: None,
+        context: None,
+        ..proof.clone()
+    })
+    .map_err(|e| rdfc_error(format!("Couldn't serialize proof: {e}")))?;
+    config["@context"] = document.get("@context").cloned().unwrap_or(Value::Null);
+    canonical_hash(&config)
+}
+
+#[cfg(feature = "signing")]
+/// Verifies an eddsa-rdfc-2022 proof
+pub(crate) fn verify_proof(
+    credential: &DTGCredential,
+    proof: &DataIntegrityProof,
+    public_key_bytes: &[u8],
+) -> Result<(), DTGCredentialError> {
+    verify_document(
+        &unsigned_document(&credential.credential)?,
+        proof,
+        public_key_bytes,
+    )
+}
+
+#[cfg(feature = "signing")]
+/// Verifies an eddsa-rdfc-2022 proof of a JSON-LD document
+/// document: The unsecured document, without a proof
+pub(crate) fn verify_document(
+    document: &Value,
+    proof: &DataIntegrityProof,
+    public_key_bytes: &[u8],
+) -> Result<(), DTGCredentialError> {
+    use crate::{crypto::Algorithm, proof::CryptoSuite};
+    use chrono::{DateTime, Utc};
+    use multibase::Base;
+
+    if proof.type_ != "DataIntegrityProof" {
+        return Err(rdfc_error(
+            "Invalid proof type, expected 'DataIntegrityProof'",
+        ));
+    }
+    if proof.cryptosuite != CryptoSuite::EddsaRdfc2022 {
+        return Err(rdfc_error(format!(
+            "Invalid cryptosuite {}, expected 'eddsa-rdfc-2022'",
+            proof.cryptosuite
+        )));
+    }
+    if proof.proof_purpose != "assertionMethod" {
+        return Err(rdfc_error(format!(
+            "Invalid proofPurpose {}, expected 'assertionMethod'",
+            proof.proof_purpose
+        )));
+    }
+    // The document context must start with the proof context
+    if let Some(context) = &proof.context {
+        let document_context: Vec<&str> = match document.get("@context") {
+            Some(Value::Array(contexts)) => contexts.iter().filter_map(Value::as_str).collect(),
+            Some(Value::String(context)) => vec![context],
+            _ => Vec::new(),
+        };
+        if !document_context.starts_with(&context.iter().map(String::as_str).collect::<Vec<_>>()) {
+            return Err(rdfc_error("Document context does not match proof context"));
+        }
+    }
+    if let Some(created) = &proof.created {
+        let created = created
+            .parse::<DateTime<Utc>>()
+            .map_err(|e| rdfc_error(format!("Invalid created date: {e}")))?;
+        if created > Utc::now() {
+            return Err(rdfc_error("Created date is in the future"));
+        }
+    }
+
+    let Some(proof_value) = &proof.proof_value else {
+        return Err(rdfc_error("Proof has no proofValue"));
+    };
+    let (base, signature) = multibase::decode(proof_value)
+        .map_err(|e| rdfc_error(format!("Invalid proofValue: {e}")))?;
+    if base != Base::Base58Btc {
+        return Err(rdfc_error("proofValue must be base58btc encoded"));
+    }
+
+    Algorithm::EdDSA
+        .verify(
+            public_key_bytes,
+            &hash_document(document, proof)?,
+            &signature,
+        )
+        .map_err(rdfc_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{canonicalize, canonicalize_with_loader, hash_document};
+    use crate::{
+        DTGCredential,
+        contexts::DocumentLoader,
+        proof::{CryptoSuite, DataIntegrityProof},
+    };
+    use chrono::{TimeZone, Utc};
+    use serde_json::{Value, json};
+
+    /// vc-di-eddsa B.1 public key
+    const B1_PUBLIC_KEY: &str = "z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2";
+
+    /// vc-di-eddsa B.1 credential
+    fn alumni_credential() -> Value {
+        json!({
+            "@context": [
+                "https://www.w3.org/ns/credentials/v2",
+                "https://www.w3.org/ns/credentials/examples/v2"
+            ],
+            "id": "urn:uuid:58172aac-d8ba-11ed-83dd-0b3aef56cc33",
+            "type": ["VerifiableCredential", "AlumniCredential"],
+            "name": "Alumni Credential",
+            "description": "A minimum viable example of an Alumni Credential.",
+            "issuer": "https://vc.example/issuers/5678",
+            "validFrom": "2023-01-01T00:00:00Z",
+            "credentialSubject": {
+                "id": "did:example:abcdefgh",
+                "alumniOf": "The School of Examples"
+            }
+        })
+    }
+
+    /// vc-di-eddsa B.1 proof options
+    fn alumni_proof() -> DataIntegrityProof {
+        DataIntegrityProof {
+            type_: "DataIntegrityProof".to_string(),
+            cryptosuite: CryptoSuite::EddsaRdfc2022,
+            created: Some("2023-02-24T23:36:38Z".to_string()),
+            verification_method: format!("did:key:{B1_PUBLIC_KEY}#{B1_PUBLIC_KEY}"),
+            proof_purpose: "assertionMethod".to_string(),
+            proof_value: None,
+            context: None,
+            challenge: None,
+            domain: None,
+        }
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    #[test]
+    fn test_w3c_test_vector() {
+        assert_eq!(
+            canonicalize(&alumni_credential()).unwrap(),
+            "\
+<did:example:abcdefgh> <https://www.w3.org/ns/credentials/examples#alumniOf> \"The School of Examples\" .
+<urn:uuid:58172aac-d8ba-11ed-83dd-0b3aef56cc33> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <https://www.w3.org/2018/credentials#VerifiableCredential> .
+<urn:uuid:58172aac-d8ba-11ed-83dd-0b3aef56cc33> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <https://www.w3.org/ns/credentials/examples#AlumniCredential> .
+<urn:uuid:58172aac-d8ba-11ed-83dd-0b3aef56cc33> <https://schema.org/description> \"A minimum viable example of an Alumni Credential.\" .
+<urn:uuid:58172aac-d8ba-11ed-83dd-0b3aef56cc33> <https://schema.org/name> \"Alumni Credential\" .
+<urn:uuid:58172aac-d8ba-11ed-83dd-0b3aef56cc33> <https://www.w3.org/2018/credentials#credentialSubject> <did:example:abcdefgh> .
+<urn:uuid:58172aac-d8ba-11ed-83dd-0b3aef56cc33> <https://www.w3.org/2018/credentials#issuer> <https://vc.example/issuers/5678> .
+<urn:uuid:58172aac-d8ba-11ed-83dd-0b3aef56cc33> <https://www.w3.org/2018/credentials#validFrom> \"2023-01-01T00:00:00Z\"^^<http://www.w3.org/2001/XMLSchema#dateTime> .
+"
+        );
+
+        // proofConfigHash || documentHash
+        let hash = hash_document(&alumni_credential(), &alumni_proof()).unwrap();
+        assert_eq!(
+            hex(&hash[..32]),
+            "bea7b7acfbad0126b135104024a5f1733e705108f42d59668b05c0c50004c6b0"
+        );
+        assert_eq!(
+            hex(&hash[32..]),
+            "517744132ae165a5349155bef0bb0cf2258fff99dfe1dbd914b938d775a36017"
+        );
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn test_w3c_test_vector_signature() {
+        use super::verify_document;
+        use ed25519_dalek::{Signer, SigningKey};
+        use multibase::Base;
+
+        const B1_PRIVATE_KEY: &str = "z3u2en7t5LR2WtQH5PfFqMqwVHBeXouLzo6haApm8XHqvjxq";
+        const B1_PROOF_VALUE: &str = "z2YwC8z3ap7yx1nZYCg4L3j3ApHsF8kgPdSb5xoS1VR7vPG3F561B52hYnQF9iseabecm3ijx4K1FBTQsCZahKZme";
+
+        // Multikeys are prefixed with their 2 byte multicodec
+        let private_key = multibase::decode(B1_PRIVATE_KEY).unwrap().1;
+        let public_key = multibase::decode(B1_PUBLIC_KEY).unwrap().1;
+        let signing_key = SigningKey::from_bytes(&private_key[2..].try_into().unwrap());
+        assert_eq!(signing_key.verifying_key().as_bytes(), &public_key[2..]);
+
+        let hash = hash_document(&alumni_credential(), &alumni_proof()).unwrap();
+        let proof = DataIntegrityProof {
+            proof_value: Some(multibase::encode(
+                Base::Base58Btc,
+                signing_key.sign(&hash).to_bytes(),
+            )),
+            ..alumni_proof()
+        };
+        assert_eq!(proof.proof_value.as_deref(), Some(B1_PROOF_VALUE));
+        assert!(verify_document(&alumni_credential(), &proof, &public_key[2..]).is_ok());
+
+        let mut tampered = alumni_credential();
+        tampered["credentialSubject"]["alumniOf"] = json!("The School of Counterexamples");
+        assert!(verify_document(&tampered, &proof, &public_key[2..]).is_err());
+
+        let wrong_purpose = DataIntegrityProof {
+            proof_purpose: "authentication".to_string(),
+            ..proof.clone()
+        };
+        assert!(verify_document(&alumni_credential(), &wrong_purpose, &public_key[2..]).is_err());
+
+        let future = DataIntegrityProof {
+            created: Some("2999-01-01T00:00:00Z".to_string()),
+            ..proof.clone()
+        };
+        assert!(verify_document(&alumni_credential(), &future, &public_key[2..]).is_err());
+
+        let wrong_type = DataIntegrityProof {
+            type_: "Ed25519Signature2020".to_string(),
+            ..proof.clone()
+        };
+        assert!(verify_document(&alumni_credential(), &wrong_type, &public_key[2..]).is_err());
+
+        let context = DataIntegrityProof {
+            context: Some(vec!["https://www.w3.org/ns/credentials/v2".to_string()]),
+            ..proof.clone()
+        };
+        assert!(verify_document(&alumni_credential(), &context, &public_key[2..]).is_ok());
+        let context = DataIntegrityProof {
+            context: Some(vec!["https://www.w3.org/2018/credentials/v1".to_string()]),
+            ..proof
+        };
+        assert!(verify_document(&alumni_credential(), &context, &public_key[2..]).is_err());
+    }
+
+    #[test]
+    fn test_dtg_types_expand() {
+        let vmc = DTGCredential::new_vmc(
+            "did:example:issuer".into(),
+            "did:example:subject".into(),
+            Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+            None,
+            true,
+        );
+        let canonical = vmc.canonicalize_rdfc().unwrap();
+
+        for type_ in [
+            "DTGCredential",
+            "MembershipCredential",
+            "PersonhoodCredential",
+        ] {
+            assert!(canonical.contains(&format!(
+                "<http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <https://firstperson.network/credentials/dtg#{type_}>"
+            )));
+        }
+    }
+
+    #[test]
+    fn test_json_literals() {
+        let rcard = DTGCredential::new_rcard(
+            "did:example:issuer".into(),
+            "did:example:subject".into(),
+            Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+            None,
+            json!(["vcard", [["fn", {}, "text", "Alice"]]]),
+        );
+        let canonical = rcard.canonicalize_rdfc().unwrap();
+
+        assert!(canonical.contains(
+            r#"<did:example:subject> <https://firstperson.network/credentials/dtg#card> "[\"vcard\",[[\"fn\",{},\"text\",\"Alice\"]]]"^^<http://www.w3.org/1999/02/22-rdf-syntax-ns#JSON> ."#
+        ));
+    }
+
+    #[test]
+    fn test_unknown_context() {
+        assert!(
+            canonicalize(&json!({
+                "@context": ["https://example.com/unknown/v1"],
+                "type": ["VerifiableCredential"]
+            }))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_v1_credential() {
+        let canonical = canonicalize(&json!({
+            "@context": [
+                "https://www.w3.org/2018/credentials/v1",
+                "https://firstperson.network/credentials/dtg/v1"
+            ],
+            "type": ["VerifiableCredential", "DTGCredential", "RelationshipCredential"],
+            "issuer": "did:example:issuer",
+            "issuanceDate": "2025-01-01T00:00:00Z",
+            "credentialSubject": {"id": "did:example:subject"}
+        }))
+        .unwrap();
+
+        assert!(canonical.contains(
+            "<https://www.w3.org/2018/credentials#issuanceDate> \"2025-01-01T00:00:00Z\"^^<http://www.w3.org/2001/XMLSchema#dateTime>"
+        ));
+    }
+
+    #[test]
+    fn test_registered_context() {
+        let mut loader = DocumentLoader::default();
+        let document = json!({
+            "@context": ["https://example.com/community/v1"],
+            "type": ["VerifiableCredential"],
+            "credentialSubject": {"id": "did:example:subject", "nickname": "Al"}
+        });
+        assert!(canonicalize_with_loader(&document, &loader).is_err());
+
+        loader
+            .register(
+                "https://example.com/community/v1",
+                json!({"@context": {
+                    "ex": "https://example.com/community#",
+                    "id": "@id",
+                    "type": "@type",
+                    "credentialSubject": {"@id": "ex:subject", "@type": "@id"},
+                    "nickname": "ex:nickname"
+                }}),
+                None,
+            )
+            .unwrap();
+        let canonical = canonicalize_with_loader(&document, &loader).unwrap();
+        assert!(
+            canonical.contains(
+                "<did:example:subject> <https://example.com/community#nickname> \"Al\" ."
+            )
+        );
+    }
+
+    #[cfg(feature = "affinidi-signing")]
+    #[test]
+    fn test_sign_and_verify() {
+        use crate::{CredentialSubject, proof::CryptoSuite};
+        use affinidi_secrets_resolver::secrets::Secret;
+
+        let secret = Secret::generate_ed25519(None, None);
+        let mut vec = DTGCredential::new_vec(
+            "did:example:issuer".into(),
+            "did:example:subject".into(),
+            Utc::now(),
+            None,
+            json!({"type": "SkillEndorsement", "name": "Rust"}),
+        );
+
+        let proof = vec
+            .sign_with_cryptosuite(&secret, CryptoSuite::EddsaRdfc2022, None)
+            .unwrap();
+        assert_eq!(proof.cryptosuite, CryptoSuite::EddsaRdfc2022);
+        assert!(proof.proof_value.unwrap().starts_with('z'));
+
+        // Round trip through JSON, the cryptosuite is detected from the proof
+        let received: DTGCredential =
+            serde_json::from_str(&serde_json::to_string(&vec).unwrap()).unwrap();
+        assert!(
+            received
+                .verify_proof_with_public_key(secret.get_public_bytes())
+                .is_ok()
+        );
+        assert!(
+            received
+                .verify_proof_with_public_key(
+                    Secret::generate_ed25519(None, None).get_public_bytes()
+                )
+                .is_err()
+        );
+
+        let mut tampered = received.clone();
+        if let CredentialSubject::Endorsement(subject) =
+            &mut tampered.credential_mut().credential_subject
+        {
+            subject.endorsement["name"] = json!("Go");
+        }
+        assert!(
+            tampered
+                .verify_proof_with_public_key(secret.get_public_bytes())
+                .is_err()
+        );
+    }
+
+    #[cfg(feature = "affinidi-signing")]
+    #[test]
+    fn test_sign_incompatible() {
+        use crate::{DTGCredentialError, proof::CryptoSuite};
+        use affinidi_secrets_resolver::secrets::Secret;
+
+        let mut vrc = DTGCredential::new_vrc(
+            "did:example:issuer".into(),
+            "did:example:subject".into(),
+            Utc::now(),
+            None,
+        );
+
+        assert!(
+            vrc.sign_with_cryptosuite(
+                &Secret::generate_x25519(None, None).unwrap(),
+                CryptoSuite::EddsaRdfc2022,
+                None
+            )
+            .is_err()
+        );
+        assert!(matches!(
+            vrc.sign_with_cryptosuite(
+                &Secret::generate_ed25519(None, None),
+                CryptoSuite::XDtgEddsaSdJcs,
+                None
+            ),
+            Err(DTGCredentialError::UnsupportedCryptoSuite(_))
+        ));
+    }
+}
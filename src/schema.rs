@@ -0,0 +1,396 @@
+/*!
+*   `credentialSchema` support and JSON Schema validation of DTG Credentials.
+*
+*   <https://www.w3.org/TR/vc-json-schema/>
+*
+*   Issuers attach one or more [CredentialSchema] references to a credential before signing.
+*   Verifiers resolve the references from a local [SchemaStore], nothing is fetched over the
+*   network, and validate the credential against them. Failures are reported with the JSON
+*   Pointer of the offending value, so communities can constrain endorsement and RCard payloads.
+*/
+
+use crate::{DTGCredential, DTGCredentialError};
+use jsonschema::Resource;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashMap, fmt::Display};
+
+/// credentialSchema type of a JSON Schema document
+pub const JSON_SCHEMA: &str = "JsonSchema";
+
+/// credentialSchema type of a credential wrapping a JSON Schema
+pub const JSON_SCHEMA_CREDENTIAL: &str = "JsonSchemaCredential";
+
+fn schema_error(msg: impl Display) -> DTGCredentialError {
+    DTGCredentialError::Schema(msg.to_string())
+}
+
+/// Reference to a schema the credential conforms to
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct CredentialSchema {
+    /// URL of the schema
+    pub id: String,
+
+    /// [JSON_SCHEMA] or [JSON_SCHEMA_CREDENTIAL]
+    #[serde(rename = "type")]
+    pub type_: String,
+}
+
+impl CredentialSchema {
+    /// Reference to a JSON Schema document
+    pub fn json_schema(id: &str) -> Self {
+        CredentialSchema {
+            id: id.to_string(),
+            type_: JSON_SCHEMA.to_string(),
+        }
+    }
+
+    /// Reference to a JsonSchemaCredential
+    pub fn json_schema_credential(id: &str) -> Self {
+        CredentialSchema {
+            id: id.to_string(),
+            type_: JSON_SCHEMA_CREDENTIAL.to_string(),
+        }
+    }
+}
+
+/// `credentialSchema` may be a single object or an array, the received form is preserved so
+/// proofs still verify
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum CredentialSchemas {
+    Single(CredentialSchema),
+    Multiple(Vec<CredentialSchema>),
+}
+
+impl CredentialSchemas {
+    pub fn iter(&self) -> std::slice::Iter<'_, CredentialSchema> {
+        match self {
+            CredentialSchemas::Single(schema) => std::slice::from_ref(schema).iter(),
+            CredentialSchemas::Multiple(schemas) => schemas.iter(),
+        }
+    }
+}
+
+/// A single validation failure
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchemaViolation {
+    /// id of the schema that failed
+    pub schema_id: String,
+
+    /// JSON Pointer to the failing value in the credential
+    pub instance_path: String,
+
+    pub message: String,
+}
+
+impl Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.instance_path, self.message)
+    }
+}
+
+/// Local store of schemas, keyed by the id used in `credentialSchema`
+/// Stored schemas can `$ref` each other by id
+#[derive(Clone, Debug, Default)]
+pub struct SchemaStore {
+    /// id => (credentialSchema type, JSON Schema)
+    schemas: HashMap<String, (String, Value)>,
+}
+
+impl SchemaStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a JSON Schema document
+    /// id: The id used in `credentialSchema`
+    pub fn add_schema(&mut self, id: &str, schema: Value) -> Result<(), DTGCredentialError> {
+        jsonschema::meta::validate(&schema)
+            .map_err(|e| schema_error(format!("Invalid JSON Schema {id}: {e}")))?;
+        self.schemas
+            .insert(id.to_string(), (JSON_SCHEMA.to_string(), schema));
+        Ok(())
+    }
+
+    /// Adds a JsonSchemaCredential, the schema is read from `credentialSubject.jsonSchema`
+    /// NOTE: The credential must be verified before it is added
+    /// id: The id used in `credentialSchema`
+    pub fn add_schema_credential(
+        &mut self,
+        id: &str,
+        credential: &Value,
+    ) -> Result<(), DTGCredentialError> {
+        let is_schema_credential = credential
+            .get("type")
+            .and_then(|t| t.as_array())
+            .is_some_and(|t| t.iter().any(|t| t == JSON_SCHEMA_CREDENTIAL));
+        if !is_schema_credential {
+            return Err(schema_error(format!("{id} is not a JsonSchemaCredential")));
+        }
+
+        let Some(schema) = credential.pointer("/credentialSubject/jsonSchema") else {
+            return Err(schema_error(format!(
+                "{id} is missing credentialSubject.jsonSchema"
+            )));
+        };
+        self.add_schema(id, schema.clone())?;
+        if let Some((type_, _)) = self.schemas.get_mut(id) {
+            *type_ = JSON_SCHEMA_CREDENTIAL.to_string();
+        }
+        Ok(())
+    }
+
+    /// The JSON Schema stored for an id
+    pub fn get(&self, id: &str) -> Option<&Value> {
+        self.schemas.get(id).map(|(_, schema)| schema)
+    }
+
+    /// Validates a credential against each of its `credentialSchema` references
+    /// Returns all violations, empty if the credential is valid or has no `credentialSchema`
+    /// Errors if a referenced schema isn't in the store
+    pub fn validate(
+        &self,
+        credential: &DTGCredential,
+    ) -> Result<Vec<SchemaViolation>, DTGCredentialError> {
+        let Some(schemas) = &credential.credential().credential_schema else {
+            return Ok(Vec::new());
+        };
+        let instance = serde_json::to_value(credential)
+            .map_err(|e| schema_error(format!("Couldn't serialize credential: {e}")))?;
+
+        let mut violations = Vec::new();
+        for reference in schemas.iter() {
+            let Some((type_, schema)) = self.schemas.get(&reference.id) else {
+                return Err(schema_error(format!("Unknown schema: {}", reference.id)));
+            };
+            if *type_ != reference.type_ {
+                return Err(schema_error(format!(
+                    "Schema {} is a {type_}, not a {}",
+                    reference.id, reference.type_
+                )));
+            }
+
+            let validator =
+                jsonschema::options()
+                    .with_resources(self.schemas.iter().map(|(id, (_, schema))| {
+                        (id.clone(), Resource::from_contents(schema.clone()))
+                    }))
+                    .build(schema)
+                    .map_err(|e| schema_error(format!("Invalid schema {}: {e}", reference.id)))?;
+
+            violations.extend(
+                validator
+                    .iter_errors(&instance)
+                    .map(|error| SchemaViolation {
+                        schema_id: reference.id.clone(),
+                        instance_path: error.instance_path().as_str().to_string(),
+                        message: error.to_string(),
+                    }),
+            );
+        }
+        Ok(violations)
+    }
+}
+
+impl DTGCredential {
+    /// Adds a `credentialSchema` reference, do this before signing
+    pub fn add_credential_schema(&mut self, schema: CredentialSchema) {
+        let schemas = &mut self.credential.credential_schema;
+        *schemas = Some(match schemas.take() {
+            None => CredentialSchemas::Single(schema),
+            Some(CredentialSchemas::Single(existing)) => {
+                CredentialSchemas::Multiple(vec![existing, schema])
+            }
+            Some(CredentialSchemas::Multiple(mut existing)) => {
+                existing.push(schema);
+                CredentialSchemas::Multiple(existing)
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CredentialSchema, SchemaStore};
+    use crate::DTGCredential;
+    use chrono::Utc;
+    use serde_json::json;
+
+    const SKILL_SCHEMA: &str = "https://example.com/schemas/skill.json";
+    const LEVEL_SCHEMA: &str = "https://example.com/schemas/level.json";
+
+    fn store() -> SchemaStore {
+        let mut store = SchemaStore::new();
+        store
+            .add_schema(
+                LEVEL_SCHEMA,
+                json!({"enum": ["beginner", "intermediate", "advanced", "expert"]}),
+            )
+            .unwrap();
+        store
+            .add_schema(
+                SKILL_SCHEMA,
+                json!({
+                    "$schema": "https://json-schema.org/draft/2020-12/schema",
+                    "type": "object",
+                    "required": ["credentialSubject"],
+                    "properties": {
+                        "credentialSubject": {
+                            "type": "object",
+                            "required": ["endorsement"],
+                            "properties": {
+                                "endorsement": {
+                                    "type": "object",
+                                    "required": ["type", "name"],
+                                    "properties": {
+                                        "type": {"const": "SkillEndorsement"},
+                                        "name": {"type": "string", "minLength": 1},
+                                        "competencyLevel": {"$ref": LEVEL_SCHEMA}
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }),
+            )
+            .unwrap();
+        store
+    }
+
+    fn vec(endorsement: serde_json::Value) -> DTGCredential {
+        let mut vec = DTGCredential::new_vec(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            Utc::now(),
+            None,
+            endorsement,
+        );
+        vec.add_credential_schema(CredentialSchema::json_schema(SKILL_SCHEMA));
+        vec
+    }
+
+    #[test]
+    fn test_valid() {
+        let vec =
+            vec(json!({"type": "SkillEndorsement", "name": "Rust", "competencyLevel": "expert"}));
+        assert!(store().validate(&vec).unwrap().is_empty());
+
+        let value = serde_json::to_value(&vec).unwrap();
+        assert_eq!(
+            value["credentialSchema"],
+            json!({"id": SKILL_SCHEMA, "type": "JsonSchema"})
+        );
+
+        // Preserved through serialization and CBOR
+        let parsed: DTGCredential = serde_json::from_value(value).unwrap();
+        assert_eq!(
+            parsed.credential().credential_schema,
+            vec.credential().credential_schema
+        );
+        #[cfg(feature = "affinidi-signing")]
+        {
+            let cbor = DTGCredential::from_cbor(&vec.to_cbor().unwrap()).unwrap();
+            assert_eq!(
+                cbor.credential().credential_schema,
+                vec.credential().credential_schema
+            );
+        }
+    }
+
+    #[test]
+    fn test_violations() {
+        let vec = vec(json!({"type": "SkillEndorsement", "name": "", "competencyLevel": "guru"}));
+        let violations = store().validate(&vec).unwrap();
+
+        let mut paths: Vec<&str> = violations
+            .iter()
+            .map(|v| v.instance_path.as_str())
+            .collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                "/credentialSubject/endorsement/competencyLevel",
+                "/credentialSubject/endorsement/name"
+            ]
+        );
+        assert!(violations.iter().all(|v| v.schema_id == SKILL_SCHEMA));
+    }
+
+    #[test]
+    fn test_missing_and_mismatched() {
+        let mut vec = vec(json!({"type": "SkillEndorsement", "name": "Rust"}));
+        assert!(SchemaStore::new().validate(&vec).is_err());
+
+        vec.add_credential_schema(CredentialSchema::json_schema_credential(LEVEL_SCHEMA));
+        assert!(store().validate(&vec).is_err());
+
+        let vrc = DTGCredential::new_vrc(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            Utc::now(),
+            None,
+        );
+        assert!(store().validate(&vrc).unwrap().is_empty());
+
+        assert!(
+            SchemaStore::new()
+                .add_schema("urn:bad", json!({"type": 5}))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_schema_credential() {
+        const RCARD_SCHEMA: &str = "https://example.com/credentials/rcard-schema";
+        let mut store = SchemaStore::new();
+        store
+            .add_schema_credential(
+                RCARD_SCHEMA,
+                &json!({
+                    "@context": ["https://www.w3.org/ns/credentials/v2"],
+                    "type": ["VerifiableCredential", "JsonSchemaCredential"],
+                    "issuer": "did:example:community",
+                    "credentialSubject": {
+                        "id": RCARD_SCHEMA,
+                        "type": "JsonSchema",
+                        "jsonSchema": {
+                            "$schema": "https://json-schema.org/draft/2020-12/schema",
+                            "properties": {
+                                "credentialSubject": {
+                                    "properties": {
+                                        "card": {
+                                            "prefixItems": [{"const": "vcard"}, {"minItems": 1}]
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }),
+            )
+            .unwrap();
+
+        let mut rcard = DTGCredential::new_rcard(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            Utc::now(),
+            None,
+            json!(["vcard", [["fn", {}, "text", "Alice"]]]),
+        );
+        rcard.add_credential_schema(CredentialSchema::json_schema_credential(RCARD_SCHEMA));
+        assert!(store.validate(&rcard).unwrap().is_empty());
+
+        rcard.add_credential_schema(CredentialSchema::json_schema(SKILL_SCHEMA));
+        assert!(store.validate(&rcard).is_err());
+
+        assert!(
+            store
+                .add_schema_credential("urn:not", &json!({"type": ["VerifiableCredential"]}))
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,537 @@
+/*!
+*   Weighted trust scores over the trust graph.
+*
+*   Scores are a personalized PageRank: trust starts at the seed DIDs (e.g. the DID of the
+*   person asking) and flows from issuer to subject along each valid credential, in proportion
+*   to the weight of the credential type. VWCs boost the weight of the credential they witness.
+*
+*   Scores are reproducible, DIDs and credentials are always visited in the same order, and
+*   explainable, see [TrustScorer::explain]. [TrustScorer] keeps the scores up to date as
+*   credentials are added or revoked, starting each update from the previous scores.
+*/
+
+use crate::{
+    DTGCredential, DTGCredentialError, DTGCredentialType,
+    graph::{TrustEdge, TrustGraph},
+};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// How trust flows through the graph
+#[derive(Clone, Debug)]
+pub struct ScoreConfig {
+    /// Weight of each credential type, types without a weight carry no trust
+    pub weights: BTreeMap<DTGCredentialType, f64>,
+
+    /// Each witness multiplies the weight of the credential it witnessed by (1 + boost)
+    pub witness_boost: f64,
+
+    /// Witnesses beyond this number don't boost a credential further
+    pub max_witnesses: usize,
+
+    /// Probability of following a credential rather than returning to a seed
+    pub damping: f64,
+
+    /// Scores have converged when the total change in an iteration is below this
+    pub tolerance: f64,
+
+    /// Upper limit on iterations for each update
+    pub max_iterations: usize,
+
+    /// Credentials must be valid at this time
+    pub at: DateTime<Utc>,
+}
+
+impl Default for ScoreConfig {
+    fn default() -> Self {
+        ScoreConfig {
+            weights: BTreeMap::from([
+                (DTGCredentialType::Relationship, 1.0),
+                (DTGCredentialType::Endorsement, 0.5),
+                (DTGCredentialType::Membership, 0.25),
+            ]),
+            witness_boost: 0.5,
+            max_witnesses: 3,
+            damping: 0.85,
+            tolerance: 1e-12,
+            max_iterations: 1000,
+            at: Utc::now(),
+        }
+    }
+}
+
+impl ScoreConfig {
+    /// VRC 1.0, VEC 0.5, VMC 0.25, each witness adds 50% up to 3 witnesses, valid now
+    pub fn new() -> Self {
+        ScoreConfig::default()
+    }
+
+    /// Sets the weight of a credential type
+    pub fn with_weight(mut self, type_: DTGCredentialType, weight: f64) -> Self {
+        self.weights.insert(type_, weight);
+        self
+    }
+
+    /// Sets the boost from each witness
+    pub fn with_witness_boost(mut self, boost: f64, max_witnesses: usize) -> Self {
+        self.witness_boost = boost;
+        self.max_witnesses = max_witnesses;
+        self
+    }
+
+    /// Sets the damping factor
+    pub fn with_damping(mut self, damping: f64) -> Self {
+        self.damping = damping;
+        self
+    }
+
+    /// Sets the time credentials must be valid at
+    pub fn with_time(mut self, at: DateTime<Utc>) -> Self {
+        self.at = at;
+        self
+    }
+}
+
+/// Trust one credential passed to its subject
+#[derive(Clone, Debug)]
+pub struct ScoreContribution {
+    /// Digest of the credential
+    pub digest: String,
+
+    /// DID passing on its trust
+    pub issuer: String,
+
+    pub type_: DTGCredentialType,
+
+    /// Share of the subject's score from this credential
+    pub amount: f64,
+
+    /// Digests of the VWCs that boosted this credential
+    pub witnesses: Vec<String>,
+}
+
+/// A DID's score and where it came from
+#[derive(Clone, Debug)]
+pub struct TrustScore {
+    pub did: String,
+
+    pub score: f64,
+
+    /// Share of the score from being a seed
+    pub seed: f64,
+
+    /// Credentials passing trust to the DID, largest first
+    pub contributions: Vec<ScoreContribution>,
+}
+
+/// A credential trust flows along
+struct WeightedEdge<'a> {
+    edge: &'a TrustEdge,
+    weight: f64,
+    witnesses: Vec<&'a TrustEdge>,
+}
+
+/// Keeps trust scores for a trust graph up to date
+#[derive(Clone, Debug)]
+pub struct TrustScorer {
+    graph: TrustGraph,
+    seeds: BTreeSet<String>,
+    config: ScoreConfig,
+    scores: BTreeMap<String, f64>,
+    iterations: usize,
+}
+
+impl TrustScorer {
+    /// graph: Verified credentials
+    /// seeds: DIDs trust starts from, every DID is a seed if empty
+    pub fn new(graph: TrustGraph, seeds: &[&str], config: ScoreConfig) -> Self {
+        let mut scorer = TrustScorer {
+            graph,
+            seeds: seeds.iter().map(|seed| seed.to_string()).collect(),
+            config,
+            scores: BTreeMap::new(),
+            iterations: 0,
+        };
+        scorer.update();
+        scorer
+    }
+
+    /// The scored trust graph
+    pub fn graph(&self) -> &TrustGraph {
+        &self.graph
+    }
+
+    /// Adds a verified credential and updates the scores
+    /// Returns the digest identifying the credential
+    pub fn add(&mut self, credential: DTGCredential) -> Result<String, DTGCredentialError> {
+        let digest = self.graph.add(credential)?;
+        self.update();
+        Ok(digest)
+    }
+
+    /// Adds verified credentials and updates the scores once
+    pub fn extend(
+        &mut self,
+        credentials: impl IntoIterator<Item = DTGCredential>,
+    ) -> Result<(), DTGCredentialError> {
+        let result = credentials
+            .into_iter()
+            .try_for_each(|credential| self.graph.add(credential).map(|_| ()));
+        self.update();
+        result
+    }
+
+    /// Removes a revoked credential and updates the scores
+    pub fn remove(&mut self, digest: &str) -> Option<DTGCredential> {
+        let credential = self.graph.remove(digest)?;
+        self.update();
+        Some(credential)
+    }
+
+    /// Score of a DID, 0 if it isn't reachable from a seed
+    pub fn score(&self, did: &str) -> f64 {
+        self.scores.get(did).copied().unwrap_or(0.0)
+    }
+
+    /// Scores of every DID, they add up to 1
+    pub fn scores(&self) -> &BTreeMap<String, f64> {
+        &self.scores
+    }
+
+    /// DIDs with a score, highest first, equal scores ordered by DID
+    pub fn ranking(&self) -> Vec<(&str, f64)> {
+        let mut ranking: Vec<(&str, f64)> = self
+            .scores
+            .iter()
+            .filter(|(_, score)| **score > 0.0)
+            .map(|(did, score)| (did.as_str(), *score))
+            .collect();
+        ranking.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(b.0)));
+        ranking
+    }
+
+    /// Iterations taken by the last update
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// Why a DID has its score
+    pub fn explain(&self, did: &str) -> TrustScore {
+        let edges = self.weighted_edges();
+        let out_weights = out_weights(&edges);
+        let damping = self.config.damping;
+
+        let mut contributions: Vec<ScoreContribution> = edges
+            .iter()
+            .filter(|e| e.edge.to() == did)
+            .map(|e| ScoreContribution {
+                digest: e.edge.digest().to_string(),
+                issuer: e.edge.from().to_string(),
+                type_: e.edge.type_(),
+                amount: damping * self.score(e.edge.from()) * e.weight / out_weights[e.edge.from()],
+                witnesses: e.witnesses.iter().map(|w| w.digest().to_string()).collect(),
+            })
+            .filter(|c| c.amount > 0.0)
+            .collect();
+        contributions.sort_by(|a, b| b.amount.total_cmp(&a.amount).then(a.digest.cmp(&b.digest)));
+
+        let score = self.score(did);
+        TrustScore {
+            did: did.to_string(),
+            score,
+            seed: score - contributions.iter().map(|c| c.amount).sum::<f64>(),
+            contributions,
+        }
+    }
+
+    /// Valid credentials with a weight, self-issued credentials carry no trust
+    fn weighted_edges(&self) -> Vec<WeightedEdge<'_>> {
+        let config = &self.config;
+        self.graph
+            .edges()
+            .filter(|edge| edge.from() != edge.to() && edge.is_valid_at(config.at))
+            .filter_map(|edge| {
+                let weight = *config.weights.get(&edge.type_())?;
+                let witnesses: Vec<&TrustEdge> = self
+                    .graph
+                    .witnesses(edge.digest(), config.at)
+                    .into_iter()
+                    .take(config.max_witnesses)
+                    .collect();
+                let weight = weight * (1.0 + config.witness_boost * witnesses.len() as f64);
+                (weight > 0.0).then_some(WeightedEdge {
+                    edge,
+                    weight,
+                    witnesses,
+                })
+            })
+            .collect()
+    }
+
+    /// Power iteration, starting from the current scores
+    fn update(&mut self) {
+        let edges = self.weighted_edges();
+        let out_weights = out_weights(&edges);
+
+        let mut nodes: BTreeSet<&str> = self.graph.nodes().into_iter().collect();
+        nodes.extend(self.seeds.iter().map(String::as_str));
+        if nodes.is_empty() {
+            self.scores.clear();
+            self.iterations = 0;
+            return;
+        }
+
+        let seeds: Vec<&str> = if self.seeds.is_empty() {
+            nodes.iter().copied().collect()
+        } else {
+            self.seeds.iter().map(String::as_str).collect()
+        };
+        let teleport = 1.0 / seeds.len() as f64;
+
+        // Warm start from the previous scores, new DIDs start at 0
+        let mut scores: BTreeMap<&str, f64> = nodes
+            .iter()
+            .map(|did| (*did, self.scores.get(*did).copied().unwrap_or(0.0)))
+            .collect();
+        let total: f64 = scores.values().sum();
+        if total > 0.0 {
+            scores.values_mut().for_each(|score| *score /= total);
+        } else {
+            seeds.iter().for_each(|seed| {
+                scores.insert(seed, teleport);
+            });
+        }
+
+        let damping = self.config.damping;
+        let mut iterations = 0;
+        while iterations < self.config.max_iterations {
+            iterations += 1;
+
+            // Trust from DIDs that issued no weighted credentials returns to the seeds
+            let dangling: f64 = scores
+                .iter()
+                .filter(|(did, _)| !out_weights.contains_key(**did))
+                .map(|(_, score)| score)
+                .sum();
+
+            let mut next: BTreeMap<&str, f64> = nodes.iter().map(|did| (*did, 0.0)).collect();
+            for seed in &seeds {
+                *next.get_mut(seed).expect("seeds are nodes") +=
+                    (1.0 - damping + damping * dangling) * teleport;
+            }
+            for e in &edges {
+                let from = e.edge.from();
+                *next.get_mut(e.edge.to()).expect("edge subjects are nodes") +=
+                    damping * scores[from] * e.weight / out_weights[from];
+            }
+
+            let change: f64 = nodes
+                .iter()
+                .map(|did| (next[did] - scores[did]).abs())
+                .sum();
+            scores = next;
+            if change < self.config.tolerance {
+                break;
+            }
+        }
+
+        self.iterations = iterations;
+        self.scores = scores
+            .into_iter()
+            .map(|(did, score)| (did.to_string(), score))
+            .collect();
+    }
+}
+
+/// Total weight of the credentials issued by each DID
+fn out_weights<'a>(edges: &[WeightedEdge<'a>]) -> BTreeMap<&'a str, f64> {
+    let mut out_weights: BTreeMap<&str, f64> = BTreeMap::new();
+    for e in edges {
+        *out_weights.entry(e.edge.from()).or_default() += e.weight;
+    }
+    out_weights
+}
+
+impl TrustGraph {
+    /// Trust scores of every DID, see [TrustScorer]
+    /// seeds: DIDs trust starts from, every DID is a seed if empty
+    pub fn trust_scores(&self, seeds: &[&str], config: ScoreConfig) -> BTreeMap<String, f64> {
+        TrustScorer::new(self.clone(), seeds, config).scores
+    }
+}
+
+#[cfg(all(test, feature = "affinidi-signing"))]
+mod tests {
+    use super::{ScoreConfig, TrustScorer};
+    use crate::{
+        DTGCredential, DTGCredentialType,
+        graph::TrustGraph,
+        test_utils::{did, vrc, vwc},
+    };
+    use chrono::Utc;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_trust_flows_from_seed() {
+        let graph = TrustGraph::from_credentials([
+            vrc("alice", "bob"),
+            vrc("bob", "carol"),
+            vrc("dave", "erin"),
+        ])
+        .unwrap();
+        let alice = did("alice");
+        let scorer = TrustScorer::new(graph, &[&alice], ScoreConfig::new());
+
+        let (alice, bob, carol) = (
+            scorer.score(&did("alice")),
+            scorer.score(&did("bob")),
+            scorer.score(&did("carol")),
+        );
+        assert!(alice > bob && bob > carol && carol > 0.0);
+        assert_eq!(scorer.score(&did("dave")), 0.0);
+        assert_eq!(scorer.score(&did("erin")), 0.0);
+        assert!(close(scorer.scores().values().sum(), 1.0));
+
+        let ranking = scorer.ranking();
+        assert_eq!(ranking.len(), 3);
+        assert_eq!(ranking[0].0, did("alice"));
+    }
+
+    #[test]
+    fn test_weights_and_witnesses() {
+        let mut graph =
+            TrustGraph::from_credentials([vrc("alice", "bob"), vrc("alice", "carol")]).unwrap();
+        let alice = did("alice");
+
+        let scores = graph.trust_scores(&[&alice], ScoreConfig::new());
+        assert!(close(scores[&did("bob")], scores[&did("carol")]));
+
+        let alice_bob = graph
+            .edges()
+            .find(|e| e.to() == did("bob"))
+            .unwrap()
+            .digest()
+            .to_string();
+        let witness = graph.add(vwc("witness", "bob", &alice_bob, None)).unwrap();
+
+        let scorer = TrustScorer::new(graph.clone(), &[&alice], ScoreConfig::new());
+        assert!(scorer.score(&did("bob")) > scorer.score(&did("carol")));
+
+        let explained = scorer.explain(&did("bob"));
+        assert_eq!(explained.contributions.len(), 1);
+        assert_eq!(explained.contributions[0].digest, alice_bob);
+        assert_eq!(explained.contributions[0].issuer, did("alice"));
+        assert_eq!(explained.contributions[0].witnesses, vec![witness]);
+        assert!(close(
+            explained.seed + explained.contributions[0].amount,
+            explained.score
+        ));
+
+        let explained = scorer.explain(&did("alice"));
+        assert!(explained.contributions.is_empty());
+        assert!(close(explained.seed, explained.score));
+
+        // Relationships carry no trust
+        let scores = graph.trust_scores(
+            &[&alice],
+            ScoreConfig::new().with_weight(DTGCredentialType::Relationship, 0.0),
+        );
+        assert!(close(scores[&did("alice")], 1.0));
+        assert_eq!(scores[&did("bob")], 0.0);
+    }
+
+    #[test]
+    fn test_incremental() {
+        let credentials = vec![
+            vrc("alice", "bob"),
+            vrc("bob", "carol"),
+            vrc("carol", "alice"),
+            vrc("carol", "dave"),
+        ];
+        let alice = did("alice");
+        let config = ScoreConfig::new();
+
+        let mut scorer = TrustScorer::new(TrustGraph::new(), &[&alice], config.clone());
+        scorer.extend(credentials[..2].to_vec()).unwrap();
+        let digest = scorer.add(credentials[2].clone()).unwrap();
+        scorer.add(credentials[3].clone()).unwrap();
+
+        let full = TrustGraph::from_credentials(credentials.clone())
+            .unwrap()
+            .trust_scores(&[&alice], config.clone());
+        assert_eq!(scorer.scores().len(), full.len());
+        for (did, score) in &full {
+            assert!(close(scorer.score(did), *score));
+        }
+
+        // Revoked
+        assert!(scorer.remove(&digest).is_some());
+        let without = TrustGraph::from_credentials(
+            credentials
+                .iter()
+                .filter(|c| c.digest().unwrap() != digest)
+                .cloned(),
+        )
+        .unwrap()
+        .trust_scores(&[&alice], config.clone());
+        for (did, score) in &without {
+            assert!(close(scorer.score(did), *score));
+        }
+    }
+
+    #[test]
+    fn test_warm_start() {
+        // A ring of 20 DIDs, then one more credential added to it
+        let names: Vec<String> = (0..20).map(|i| format!("did{i}")).collect();
+        let mut credentials: Vec<DTGCredential> = names
+            .iter()
+            .zip(names.iter().cycle().skip(1))
+            .map(|(from, to)| vrc(from, to))
+            .collect();
+        let seed = did("did0");
+        let config = ScoreConfig::new();
+
+        let mut scorer = TrustScorer::new(
+            TrustGraph::from_credentials(credentials.clone()).unwrap(),
+            &[&seed],
+            config.clone(),
+        );
+        let extra = vrc("did10", "did15");
+        scorer.add(extra.clone()).unwrap();
+        credentials.push(extra);
+
+        let cold = TrustScorer::new(
+            TrustGraph::from_credentials(credentials).unwrap(),
+            &[&seed],
+            config,
+        );
+        for (did, score) in cold.scores() {
+            assert!(close(scorer.score(did), *score));
+        }
+        assert!(
+            scorer.iterations() < cold.iterations(),
+            "{} >= {}",
+            scorer.iterations(),
+            cold.iterations()
+        );
+    }
+
+    #[test]
+    fn test_reproducible() {
+        let graph = TrustGraph::from_credentials([
+            vrc("alice", "bob"),
+            vrc("bob", "carol"),
+            vrc("carol", "alice"),
+        ])
+        .unwrap();
+        let config = ScoreConfig::new().with_time(Utc::now());
+
+        assert_eq!(
+            graph.trust_scores(&[], config.clone()),
+            graph.trust_scores(&[], config)
+        );
+    }
+}
@@ -0,0 +1,803 @@
+/*!
+*   SD-JWT VC encoding of DTG Credentials.
+*
+*   <https://datatracker.ietf.org/doc/draft-ietf-oauth-sd-jwt-vc/>
+*
+*   A DTG Credential is mapped to SD-JWT VC claims as follows:
+*   - `issuer` => `iss`
+*   - `credentialSubject.id` => `sub`
+*   - `validFrom` => `nbf`
+*   - `validUntil` => `exp`
+*   - The credential type => `vct` (e.g. `https://firstperson.network/credentials/dtg/v1/RCardCredential`)
+*   - `@context`, `type`, `credentialSchema` and the remaining `credentialSubject` attributes are
+*     kept as is
+*
+*   Selectively disclosable claims are chosen using JSON Pointers into the DTG Credential. A
+*   final `*` segment makes every child of the pointer selectively disclosable, so appending it
+*   to `/credentialSubject/card/1` discloses each jCard property individually.
+*/
+
+use crate::{
+    DTGCommon, DTGCredential, DTGCredentialError,
+    jws::{b64_decode, b64_encode, decode_compact, jwk_public_key, sign_compact},
+    signer::{Signer, SigningKey},
+};
+use chrono::{DateTime, Utc};
+use rand::{RngCore, rngs::OsRng};
+use serde_json::{Map, Value, json};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+/// Public JWK of a holder key, used for the `cnf` claim
+pub use crate::jws::public_jwk;
+
+/// Media type of the issuer signed JWT
+pub const SD_JWT_VC_TYPE: &str = "dc+sd-jwt";
+
+/// Media type of the Key Binding JWT
+pub const KB_JWT_TYPE: &str = "kb+jwt";
+
+/// Prefix of the `vct` claim, the DTG Credential type is appended
+pub const DTG_VCT_PREFIX: &str = "https://firstperson.network/credentials/dtg/v1/";
+
+/// An SD-JWT in compact serialization
+/// `<issuer-jwt>~<disclosure>~...~<optional kb-jwt>`
+#[derive(Clone, Debug)]
+pub struct SdJwt {
+    /// Issuer signed JWT
+    pub issuer_jwt: String,
+
+    /// Base64url encoded disclosures
+    pub disclosures: Vec<String>,
+
+    /// Optional Key Binding JWT created by the holder
+    pub key_binding_jwt: Option<String>,
+}
+
+/// What the verifier expects within the Key Binding JWT
+#[derive(Clone, Debug)]
+pub struct KeyBinding {
+    /// Verifier identifier (`aud`)
+    pub audience: String,
+
+    /// Verifier supplied nonce
+    pub nonce: String,
+}
+
+impl Display for SdJwt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            self.presentation(),
+            self.key_binding_jwt.as_deref().unwrap_or_default()
+        )
+    }
+}
+
+impl TryFrom<&str> for SdJwt {
+    type Error = DTGCredentialError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let mut parts: Vec<&str> = value.split('~').collect();
+        if parts.len() < 2 {
+            return Err(DTGCredentialError::SdJwt(
+                "SD-JWT must contain at least one '~'".to_string(),
+            ));
+        }
+
+        let key_binding_jwt = parts.pop().filter(|kb| !kb.is_empty()).map(String::from);
+        let issuer_jwt = parts.remove(0).to_string();
+        if parts.iter().any(|d| d.is_empty()) {
+            return Err(DTGCredentialError::SdJwt("Empty disclosure".to_string()));
+        }
+
+        Ok(SdJwt {
+            issuer_jwt,
+            disclosures: parts.into_iter().map(String::from).collect(),
+            key_binding_jwt,
+        })
+    }
+}
+
+/// Digest of a disclosure as referenced in `_sd` and `...`
+fn digest(disclosure: &str) -> String {
+    b64_encode(Sha256::digest(disclosure.as_bytes()))
+}
+
+fn random_salt() -> String {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    b64_encode(salt)
+}
+
+fn decode_disclosure(disclosure: &str) -> Result<Vec<Value>, DTGCredentialError> {
+    match serde_json::from_slice(&b64_decode(disclosure)?) {
+        Ok(Value::Array(items)) if items.len() == 2 || items.len() == 3 => Ok(items),
+        _ => Err(DTGCredentialError::SdJwt(format!(
+            "Invalid disclosure ({disclosure})"
+        ))),
+    }
+}
+
+/// The claim name a disclosure refers to
+/// Array element disclosures use the first string of the element (e.g. jCard property name)
+fn disclosure_name(disclosure: &[Value]) -> Option<&str> {
+    match disclosure {
+        [_, name, _] => name.as_str(),
+        [_, value] => value.get(0).and_then(|name| name.as_str()),
+        _ => None,
+    }
+}
+
+/// Expands wildcard pointers and checks each pointer is selectively disclosable
+fn expand_pointers(doc: &Value, pointers: &[&str]) -> Result<Vec<String>, DTGCredentialError> {
+    let mut expanded = Vec::new();
+    for pointer in pointers {
+        if !pointer.starts_with("/credentialSubject/") || *pointer == "/credentialSubject/id" {
+            return Err(DTGCredentialError::SdJwt(format!(
+                "Only credentialSubject attributes can be selectively disclosed ({pointer})"
+            )));
+        }
+
+        if let Some(parent) = pointer.strip_suffix("/*") {
+            match doc.pointer(parent) {
+                Some(Value::Array(items)) => {
+                    expanded.extend((0..items.len()).map(|i| format!("{parent}/{i}")))
+                }
+                Some(Value::Object(items)) => {
+                    expanded.extend(items.keys().map(|key| {
+                        format!("{parent}/{}", key.replace('~', "~0").replace('/', "~1"))
+                    }))
+                }
+                _ => {
+                    return Err(DTGCredentialError::SdJwt(format!(
+                        "Can't expand ({pointer})"
+                    )));
+                }
+            }
+        } else {
+            expanded.push(pointer.to_string());
+        }
+    }
+
+    // Deepest claims first, so nested disclosures are embedded in their parents
+    expanded.sort_by_key(|pointer| std::cmp::Reverse(pointer.matches('/').count()));
+    expanded.dedup();
+    Ok(expanded)
+}
+
+/// Replaces the claim at pointer with a digest, returning the encoded disclosure
+fn conceal(doc: &mut Value, pointer: &str) -> Result<String, DTGCredentialError> {
+    let missing = || DTGCredentialError::SdJwt(format!("Claim ({pointer}) doesn't exist"));
+    let (parent, last) = pointer.rsplit_once('/').ok_or_else(missing)?;
+
+    match doc.pointer_mut(parent).ok_or_else(missing)? {
+        Value::Object(items) => {
+            let key = last.replace("~1", "/").replace("~0", "~");
+            let value = items.remove(&key).ok_or_else(missing)?;
+            let disclosure = b64_encode(json!([random_salt(), key, value]).to_string());
+            match items.entry("_sd").or_insert_with(|| json!([])) {
+                Value::Array(digests) => digests.push(json!(digest(&disclosure))),
+                _ => return Err(DTGCredentialError::SdJwt("Invalid _sd claim".to_string())),
+            }
+            Ok(disclosure)
+        }
+        Value::Array(items) => {
+            let index: usize = last.parse().map_err(|_| missing())?;
+            let item = items.get_mut(index).ok_or_else(missing)?;
+            let disclosure = b64_encode(json!([random_salt(), item.take()]).to_string());
+            *item = json!({ "...": digest(&disclosure) });
+            Ok(disclosure)
+        }
+        _ => Err(missing()),
+    }
+}
+
+/// Sorts every `_sd` array so digests don't leak the original claim order
+fn sort_digests(value: &mut Value) {
+    match value {
+        Value::Object(items) => {
+            if let Some(Value::Array(digests)) = items.get_mut("_sd") {
+                digests.sort_by(|a, b| a.as_str().cmp(&b.as_str()));
+            }
+            items.values_mut().for_each(sort_digests);
+        }
+        Value::Array(items) => items.iter_mut().for_each(sort_digests),
+        _ => {}
+    }
+}
+
+/// Replaces digests with the disclosed claims, removing undisclosed digests
+fn reveal(
+    value: &mut Value,
+    disclosures: &HashMap<String, Vec<Value>>,
+    used: &mut HashSet<String>,
+) -> Result<(), DTGCredentialError> {
+    match value {
+        Value::Object(items) => {
+            if let Some(digests) = items.remove("_sd") {
+                let Value::Array(digests) = digests else {
+                    return Err(DTGCredentialError::SdJwt("Invalid _sd claim".to_string()));
+                };
+                for digest in digests.iter().filter_map(|d| d.as_str()) {
+                    let Some(disclosure) = disclosures.get(digest) else {
+                        continue;
+                    };
+                    if !used.insert(digest.to_string()) {
+                        return Err(DTGCredentialError::SdJwt(
+                            "Disclosure referenced more than once".to_string(),
+                        ));
+                    }
+                    let [_, Value::String(name), claim] = disclosure.as_slice() else {
+                        return Err(DTGCredentialError::SdJwt(
+                            "Expected an object property disclosure".to_string(),
+                        ));
+                    };
+                    if name == "_sd" || name == "..." || items.contains_key(name) {
+                        return Err(DTGCredentialError::SdJwt(format!(
+                            "Disclosed claim ({name}) conflicts with an existing claim"
+                        )));
+                    }
+                    items.insert(name.clone(), claim.clone());
+                }
+            }
+            for item in items.values_mut() {
+                reveal(item, disclosures, used)?;
+            }
+        }
+        Value::Array(items) => {
+            let mut revealed = Vec::with_capacity(items.len());
+            for item in items.drain(..) {
+                match item.get("...").and_then(|d| d.as_str()) {
+                    Some(digest) if item.as_object().is_some_and(|o| o.len() == 1) => {
+                        let Some(disclosure) = disclosures.get(digest) else {
+                            continue;
+                        };
+                        if !used.insert(digest.to_string()) {
+                            return Err(DTGCredentialError::SdJwt(
+                                "Disclosure referenced more than once".to_string(),
+                            ));
+                        }
+                        let [_, claim] = disclosure.as_slice() else {
+                            return Err(DTGCredentialError::SdJwt(
+                                "Expected an array element disclosure".to_string(),
+                            ));
+                        };
+                        revealed.push(claim.clone());
+                    }
+                    _ => revealed.push(item),
+                }
+            }
+            for item in revealed.iter_mut() {
+                reveal(item, disclosures, used)?;
+            }
+            *items = revealed;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn timestamp(
+    claims: &Map<String, Value>,
+    name: &str,
+) -> Result<Option<String>, DTGCredentialError> {
+    match claims.get(name) {
+        None => Ok(None),
+        Some(value) => value
+            .as_i64()
+            .and_then(|ts| DateTime::<Utc>::from_timestamp(ts, 0))
+            .map(|ts| Some(ts.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)))
+            .ok_or_else(|| DTGCredentialError::SdJwt(format!("Invalid {name} claim"))),
+    }
+}
+
+impl DTGCredential {
+    /// Export the credential as an SD-JWT VC signed by the issuer
+    /// signing_secret: The issuer key to sign the SD-JWT with, see [SigningKey]
+    /// disclosable: JSON Pointers of credentialSubject claims that are selectively disclosable
+    ///              e.g. `/credentialSubject/endorsement/name` or `/credentialSubject/card/1/*`
+    /// holder_jwk: Optional holder public JWK, enables Key Binding (`cnf` claim)
+    pub fn to_sd_jwt(
+        &self,
+        signing_secret: &impl SigningKey,
+        disclosable: &[&str],
+        holder_jwk: Option<Value>,
+    ) -> Result<SdJwt, DTGCredentialError> {
+        let mut doc = serde_json::to_value(DTGCommon {
+            proof: None,
+            ..self.credential.clone()
+        })
+        .map_err(|e| DTGCredentialError::SdJwt(format!("Couldn't serialize credential: {e}")))?;
+
+        let disclosures = expand_pointers(&doc, disclosable)?
+            .iter()
+            .map(|pointer| conceal(&mut doc, pointer))
+            .collect::<Result<Vec<String>, _>>()?;
+        sort_digests(&mut doc);
+
+        let mut subject = doc["credentialSubject"].take();
+        if let Value::Object(subject) = &mut subject {
+            subject.remove("id");
+        }
+
+        let mut claims = json!({
+            "iss": self.issuer(),
+            "sub": self.subject(),
+            "vct": format!("{DTG_VCT_PREFIX}{}", self.type_),
+            "iat": Utc::now().timestamp(),
+            "nbf": self.valid_from().timestamp(),
+            "_sd_alg": "sha-256",
+            "@context": doc["@context"].take(),
+            "type": doc["type"].take(),
+            "credentialSubject": subject,
+        });
+        if let Some(valid_until) = self.valid_until() {
+            claims["exp"] = json!(valid_until.timestamp());
+        }
+        if let Some(schemas) = doc.get_mut("credentialSchema") {
+            claims["credentialSchema"] = schemas.take();
+        }
+        if let Some(jwk) = holder_jwk {
+            claims["cnf"] = json!({ "jwk": jwk });
+        }
+
+        let signer = signing_secret.signer()?;
+        Ok(SdJwt {
+            issuer_jwt: sign_compact(
+                json!({"typ": SD_JWT_VC_TYPE, "kid": signer.verification_method()}),
+                &claims,
+                &signer,
+            )?,
+            disclosures,
+            key_binding_jwt: None,
+        })
+    }
+}
+
+impl SdJwt {
+    /// The SD-JWT without Key Binding JWT, this is what the KB-JWT `sd_hash` covers
+    pub fn presentation(&self) -> String {
+        let mut presentation = format!("{}~", self.issuer_jwt);
+        for disclosure in &self.disclosures {
+            presentation.push_str(disclosure);
+            presentation.push('~');
+        }
+        presentation
+    }
+
+    /// Holder selects which disclosures to present
+    /// claims: Claim names to disclose (attribute names or jCard property names)
+    pub fn select(&self, claims: &[&str]) -> Result<SdJwt, DTGCredentialError> {
+        let mut disclosures = Vec::new();
+        for disclosure in &self.disclosures {
+            if disclosure_name(&decode_disclosure(disclosure)?)
+                .is_some_and(|name| claims.contains(&name))
+            {
+                disclosures.push(disclosure.clone());
+            }
+        }
+
+        Ok(SdJwt {
+            issuer_jwt: self.issuer_jwt.clone(),
+            disclosures,
+            key_binding_jwt: None,
+        })
+    }
+
+    /// Holder binds the presentation to a verifier by adding a Key Binding JWT
+    /// holder_secret: Key matching the `cnf` key in the issuer JWT, see [SigningKey]
+    /// audience: The verifier identifier
+    /// nonce: The verifier supplied nonce
+    pub fn bind(
+        &mut self,
+        holder_secret: &impl SigningKey,
+        audience: &str,
+        nonce: &str,
+    ) -> Result<(), DTGCredentialError> {
+        let claims = json!({
+            "iat": Utc::now().timestamp(),
+            "aud": audience,
+            "nonce": nonce,
+            "sd_hash": digest(&self.presentation()),
+        });
+
+        self.key_binding_jwt = Some(sign_compact(
+            json!({"typ": KB_JWT_TYPE}),
+            &claims,
+            &holder_secret.signer()?,
+        )?);
+        Ok(())
+    }
+
+    /// Verifies the SD-JWT VC and converts the disclosed claims back to a DTG Credential
+    /// The returned credential has no Data Integrity proof attached
+    /// issuer_public_key: Raw public key bytes of the issuer
+    /// key_binding: If set, a valid Key Binding JWT for this verifier is required
+    pub fn verify(
+        &self,
+        issuer_public_key: &[u8],
+        key_binding: Option<&KeyBinding>,
+    ) -> Result<DTGCredential, DTGCredentialError> {
+        let jwt = decode_compact(&self.issuer_jwt)?;
+        match jwt.header.get("typ").and_then(|t| t.as_str()) {
+            Some(SD_JWT_VC_TYPE) | Some("vc+sd-jwt") => {}
+            _ => {
+                return Err(DTGCredentialError::SdJwt(
+                    "Issuer JWT is not an SD-JWT VC".to_string(),
+                ));
+            }
+        }
+        jwt.verify(issuer_public_key)?;
+
+        let Value::Object(mut claims) = jwt.payload else {
+            return Err(DTGCredentialError::SdJwt(
+                "Issuer JWT payload must be an object".to_string(),
+            ));
+        };
+        match claims.remove("_sd_alg") {
+            None => {}
+            Some(alg) if alg == "sha-256" => {}
+            Some(alg) => {
+                return Err(DTGCredentialError::SdJwt(format!(
+                    "Unsupported _sd_alg ({alg})"
+                )));
+            }
+        }
+
+        self.verify_key_binding(&claims, key_binding)?;
+
+        // Replace digests with the disclosed claims
+        let mut disclosures = HashMap::new();
+        for disclosure in &self.disclosures {
+            if disclosures
+                .insert(digest(disclosure), decode_disclosure(disclosure)?)
+                .is_some()
+            {
+                return Err(DTGCredentialError::SdJwt(
+                    "Duplicate disclosure".to_string(),
+                ));
+            }
+        }
+        let mut used = HashSet::new();
+        let mut payload = Value::Object(claims);
+        reveal(&mut payload, &disclosures, &mut used)?;
+        if used.len() != disclosures.len() {
+            return Err(DTGCredentialError::SdJwt(
+                "Disclosure is not referenced by the SD-JWT".to_string(),
+            ));
+        }
+        let Value::Object(claims) = payload else {
+            unreachable!("payload remains an object");
+        };
+
+        // Map the SD-JWT VC claims back to the DTG Credential
+        let type_ = match claims.get("type") {
+            Some(type_) => type_.clone(),
+            None => {
+                let vct = claims
+                    .get("vct")
+                    .and_then(|vct| vct.as_str())
+                    .ok_or_else(|| DTGCredentialError::SdJwt("Missing vct claim".to_string()))?;
+                json!([
+                    "VerifiableCredential",
+                    "DTGCredential",
+                    vct.strip_prefix(DTG_VCT_PREFIX).unwrap_or(vct)
+                ])
+            }
+        };
+        let mut subject = match claims.get("credentialSubject") {
+            Some(Value::Object(subject)) => subject.clone(),
+            None => Map::new(),
+            Some(_) => {
+                return Err(DTGCredentialError::SdJwt(
+                    "credentialSubject must be an object".to_string(),
+                ));
+            }
+        };
+        subject.insert(
+            "id".to_string(),
+            claims.get("sub").cloned().unwrap_or_default(),
+        );
+
+        let mut doc = json!({
+            "@context": claims.get("@context").cloned().unwrap_or_else(|| json!(DTGCommon::default().context)),
+            "type": type_,
+            "issuer": claims.get("iss").cloned().unwrap_or_default(),
+            "validFrom": timestamp(&claims, "nbf")?.or(timestamp(&claims, "iat")?),
+            "credentialSubject": subject,
+        });
+        if let Some(valid_until) = timestamp(&claims, "exp")? {
+            doc["validUntil"] = json!(valid_until);
+        }
+        if let Some(schemas) = claims.get("credentialSchema") {
+            doc["credentialSchema"] = schemas.clone();
+        }
+
+        serde_json::from_value(doc)
+            .map_err(|e| DTGCredentialError::SdJwt(format!("Not a valid DTG Credential: {e}")))
+    }
+
+    /// Checks the Key Binding JWT against the `cnf` key
+    fn verify_key_binding(
+        &self,
+        claims: &Map<String, Value>,
+        key_binding: Option<&KeyBinding>,
+    ) -> Result<(), DTGCredentialError> {
+        let Some(kb_jwt) = &self.key_binding_jwt else {
+            if key_binding.is_some() {
+                return Err(DTGCredentialError::SdJwt(
+                    "Key Binding JWT is required".to_string(),
+                ));
+            }
+            return Ok(());
+        };
+
+        let jwk = claims
+            .get("cnf")
+            .and_then(|cnf| cnf.get("jwk"))
+            .ok_or_else(|| DTGCredentialError::SdJwt("Missing cnf claim".to_string()))?;
+        let (alg, public_key) = jwk_public_key(jwk)?;
+
+        let kb = decode_compact(kb_jwt)?;
+        if kb.header.get("typ").and_then(|t| t.as_str()) != Some(KB_JWT_TYPE) {
+            return Err(DTGCredentialError::SdJwt(
+                "Key Binding JWT has the wrong typ".to_string(),
+            ));
+        }
+        if kb.header.get("alg").and_then(|a| a.as_str()) != Some(alg) {
+            return Err(DTGCredentialError::SdJwt(
+                "Key Binding JWT alg doesn't match the cnf key".to_string(),
+            ));
+        }
+        kb.verify(&public_key)?;
+
+        if kb.payload.get("sd_hash").and_then(|h| h.as_str())
+            != Some(digest(&self.presentation()).as_str())
+        {
+            return Err(DTGCredentialError::SdJwt(
+                "Key Binding JWT sd_hash mismatch".to_string(),
+            ));
+        }
+        if kb.payload.get("iat").and_then(|iat| iat.as_i64()).is_none() {
+            return Err(DTGCredentialError::SdJwt(
+                "Key Binding JWT is missing iat".to_string(),
+            ));
+        }
+
+        if let Some(key_binding) = key_binding {
+            if kb.payload.get("aud").and_then(|a| a.as_str()) != Some(&key_binding.audience) {
+                return Err(DTGCredentialError::SdJwt(
+                    "Key Binding JWT audience mismatch".to_string(),
+                ));
+            }
+            if kb.payload.get("nonce").and_then(|n| n.as_str()) != Some(&key_binding.nonce) {
+                return Err(DTGCredentialError::SdJwt(
+                    "Key Binding JWT nonce mismatch".to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "affinidi-signing"))]
+mod tests {
+    use super::{KeyBinding, SD_JWT_VC_TYPE, SdJwt, public_jwk};
+    use crate::{
+        CredentialSubject, DTGCredential, DTGCredentialError, DTGCredentialType,
+        signer::{Algorithm, SecretSigner},
+    };
+    use affinidi_secrets_resolver::secrets::Secret;
+    use chrono::{DateTime, Utc};
+    use serde_json::json;
+
+    fn rcard() -> DTGCredential {
+        DTGCredential::new_rcard(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            DateTime::parse_from_rfc3339("2025-12-11T00:00:00Z")
+                .unwrap()
+                .with_timezone(&Utc),
+            None,
+            json!([
+                "vcard",
+                [
+                    ["fn", {}, "text", "Alice Smith"],
+                    ["email", {}, "text", "alice@example.com"],
+                    ["tel", {}, "uri", "tel:+1-555-555-5555"]
+                ]
+            ]),
+        )
+    }
+
+    #[test]
+    fn test_rcard_round_trip() {
+        let issuer = Secret::generate_ed25519(None, None);
+        let sd_jwt = rcard()
+            .to_sd_jwt(&issuer, &["/credentialSubject/card/1/*"], None)
+            .unwrap();
+        assert_eq!(sd_jwt.disclosures.len(), 3);
+
+        let parsed = SdJwt::try_from(sd_jwt.to_string().as_str()).unwrap();
+        let presented = parsed.select(&["fn"]).unwrap();
+
+        let cred = presented.verify(issuer.get_public_bytes(), None).unwrap();
+        assert!(matches!(cred.type_(), DTGCredentialType::RCard));
+        assert_eq!(cred.issuer(), "did:example:issuer");
+        assert_eq!(cred.subject(), "did:example:subject");
+        assert_eq!(
+            cred.valid_from()
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            "2025-12-11T00:00:00Z"
+        );
+        if let CredentialSubject::RCard(subject) = &cred.credential().credential_subject {
+            assert_eq!(
+                subject.card,
+                json!(["vcard", [["fn", {}, "text", "Alice Smith"]]])
+            );
+        } else {
+            panic!("Expected an RCard subject");
+        }
+
+        let secret2 = Secret::generate_ed25519(None, None);
+        assert!(presented.verify(secret2.get_public_bytes(), None).is_err());
+    }
+
+    #[test]
+    fn test_endorsement_attributes() {
+        let issuer = Secret::generate_ed25519(None, None);
+        let vec = DTGCredential::new_vec(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            Utc::now(),
+            Some(Utc::now() + chrono::Duration::days(30)),
+            json!({
+              "type": "SkillEndorsement",
+              "name": "Software Development",
+              "competencyLevel": "expert"
+            }),
+        );
+
+        let sd_jwt = vec
+            .to_sd_jwt(
+                &issuer,
+                &["/credentialSubject/endorsement/competencyLevel"],
+                None,
+            )
+            .unwrap();
+
+        let hidden = sd_jwt.select(&[]).unwrap();
+        let cred = hidden.verify(issuer.get_public_bytes(), None).unwrap();
+        assert!(cred.valid_until().is_some());
+        if let CredentialSubject::Endorsement(subject) = &cred.credential().credential_subject {
+            assert_eq!(
+                subject.endorsement,
+                json!({"type": "SkillEndorsement", "name": "Software Development"})
+            );
+        } else {
+            panic!("Expected an Endorsement subject");
+        }
+
+        let cred = sd_jwt.verify(issuer.get_public_bytes(), None).unwrap();
+        if let CredentialSubject::Endorsement(subject) = &cred.credential().credential_subject {
+            assert_eq!(subject.endorsement["competencyLevel"], "expert");
+        }
+    }
+
+    #[test]
+    fn test_key_binding() {
+        let issuer = Secret::generate_ed25519(None, None);
+        let holder = Secret::generate_ed25519(None, None);
+
+        let sd_jwt = rcard()
+            .to_sd_jwt(
+                &issuer,
+                &["/credentialSubject/card/1/*"],
+                Some(public_jwk(Algorithm::EdDSA, holder.get_public_bytes()).unwrap()),
+            )
+            .unwrap();
+
+        let key_binding = KeyBinding {
+            audience: "did:example:verifier".to_string(),
+            nonce: "1234".to_string(),
+        };
+
+        // Key Binding is required by the verifier
+        assert!(
+            sd_jwt
+                .verify(issuer.get_public_bytes(), Some(&key_binding))
+                .is_err()
+        );
+
+        let mut presented = sd_jwt.select(&["email"]).unwrap();
+        presented
+            .bind(&holder, "did:example:verifier", "1234")
+            .unwrap();
+        let parsed = SdJwt::try_from(presented.to_string().as_str()).unwrap();
+        assert!(
+            parsed
+                .verify(issuer.get_public_bytes(), Some(&key_binding))
+                .is_ok()
+        );
+
+        // Wrong nonce
+        let key_binding = KeyBinding {
+            nonce: "5678".to_string(),
+            ..key_binding
+        };
+        assert!(
+            parsed
+                .verify(issuer.get_public_bytes(), Some(&key_binding))
+                .is_err()
+        );
+
+        // Bound by somebody else
+        let mut stolen = sd_jwt.select(&["email"]).unwrap();
+        stolen
+            .bind(
+                &Secret::generate_ed25519(None, None),
+                "did:example:verifier",
+                "1234",
+            )
+            .unwrap();
+        assert!(stolen.verify(issuer.get_public_bytes(), None).is_err());
+    }
+
+    #[test]
+    fn test_tampered_disclosure() {
+        let issuer = Secret::generate_ed25519(None, None);
+        let mut sd_jwt = rcard()
+            .to_sd_jwt(&issuer, &["/credentialSubject/card/1/1"], None)
+            .unwrap();
+
+        sd_jwt.disclosures[0] = crate::jws::b64_encode(
+            json!(["salt", ["email", {}, "text", "mallory@example.com"]]).to_string(),
+        );
+        assert!(sd_jwt.verify(issuer.get_public_bytes(), None).is_err());
+    }
+
+    #[test]
+    fn test_bad_pointer() {
+        let issuer = Secret::generate_ed25519(None, None);
+        assert!(rcard().to_sd_jwt(&issuer, &["/issuer"], None).is_err());
+        assert!(
+            rcard()
+                .to_sd_jwt(&issuer, &["/credentialSubject/id"], None)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_subject_not_an_object() {
+        let issuer = Secret::generate_ed25519(None, None);
+        let issuer_jwt = crate::jws::sign_compact(
+            json!({"typ": SD_JWT_VC_TYPE}),
+            &json!({
+                "iss": "did:example:issuer",
+                "sub": "did:example:subject",
+                "vct": "VerifiedRelationshipCredential",
+                "nbf": 1765411200,
+                "credentialSubject": "did:example:subject",
+            }),
+            &SecretSigner::new(&issuer).unwrap(),
+        )
+        .unwrap();
+        let sd_jwt = SdJwt {
+            issuer_jwt,
+            disclosures: Vec::new(),
+            key_binding_jwt: None,
+        };
+
+        assert!(matches!(
+            sd_jwt.verify(issuer.get_public_bytes(), None),
+            Err(DTGCredentialError::SdJwt(_))
+        ));
+    }
+}
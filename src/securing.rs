@@ -0,0 +1,122 @@
+/*!
+*   Detects how a received credential has been secured.
+*/
+
+use crate::{
+    DTGCredentialError, jose::VC_JWT_TYPE, jws::decode_compact, proof::CryptoSuite,
+    sd_jwt::SD_JWT_VC_TYPE,
+};
+use serde_json::Value;
+
+/// How a credential has been secured
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum SecuringMechanism {
+    /// JSON credential with an embedded Data Integrity proof
+    DataIntegrity(CryptoSuite),
+
+    /// VC-JOSE enveloped credential (`application/vc+jwt`)
+    Jose,
+
+    /// SD-JWT VC (`application/dc+sd-jwt`)
+    SdJwt,
+
+    /// COSE_Sign1 secured CBOR credential (`application/vc+cose`)
+    Cose,
+
+    /// JSON credential without any proof
+    Unsecured,
+}
+
+impl SecuringMechanism {
+    /// Inspect a received credential and return how it has been secured
+    /// NOTE: This does NOT verify the credential
+    pub fn detect<T: AsRef<[u8]>>(data: T) -> Result<Self, DTGCredentialError> {
+        // CBOR tag 18 (COSE_Sign1)
+        if data.as_ref().first() == Some(&0xd2) {
+            return Ok(SecuringMechanism::Cose);
+        }
+
+        let text = std::str::from_utf8(data.as_ref())
+            .map_err(|_| DTGCredentialError::UnknownSecuringMechanism)?
+            .trim();
+
+        if text.starts_with('{') {
+            let value: Value = serde_json::from_str(text)
+                .map_err(|_| DTGCredentialError::UnknownSecuringMechanism)?;
+            return match value.get("proof") {
+                None => Ok(SecuringMechanism::Unsecured),
+                Some(proof) => match proof.get("cryptosuite") {
+                    Some(suite) => Ok(SecuringMechanism::DataIntegrity(
+                        serde_json::from_value(suite.clone()).map_err(|_| {
+                            DTGCredentialError::UnsupportedCryptoSuite(suite.to_string())
+                        })?,
+                    )),
+                    None => Err(DTGCredentialError::UnknownSecuringMechanism),
+                },
+            };
+        }
+
+        if text.contains('~') {
+            return Ok(SecuringMechanism::SdJwt);
+        }
+
+        match decode_compact(text)?
+            .header
+            .get("typ")
+            .and_then(|typ| typ.as_str())
+        {
+            Some(VC_JWT_TYPE) => Ok(SecuringMechanism::Jose),
+            Some(SD_JWT_VC_TYPE) => Ok(SecuringMechanism::SdJwt),
+            _ => Err(DTGCredentialError::UnknownSecuringMechanism),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "affinidi-signing"))]
+mod tests {
+    use super::SecuringMechanism;
+    use crate::{DTGCredential, proof::CryptoSuite};
+    use affinidi_secrets_resolver::secrets::Secret;
+    use chrono::Utc;
+
+    #[test]
+    fn test_detect() {
+        let secret = Secret::generate_ed25519(None, None);
+        let mut vrc = DTGCredential::new_vrc(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            Utc::now(),
+            None,
+        );
+
+        assert_eq!(
+            SecuringMechanism::detect(serde_json::to_string(&vrc).unwrap()).unwrap(),
+            SecuringMechanism::Unsecured
+        );
+
+        assert_eq!(
+            SecuringMechanism::detect(vrc.to_vc_jwt(&secret).unwrap()).unwrap(),
+            SecuringMechanism::Jose
+        );
+
+        assert_eq!(
+            SecuringMechanism::detect(vrc.to_sd_jwt(&secret, &[], None).unwrap().to_string())
+                .unwrap(),
+            SecuringMechanism::SdJwt
+        );
+
+        vrc.sign(&secret, None).unwrap();
+        assert_eq!(
+            SecuringMechanism::detect(serde_json::to_string(&vrc).unwrap()).unwrap(),
+            SecuringMechanism::DataIntegrity(CryptoSuite::EddsaJcs2022)
+        );
+
+        assert!(SecuringMechanism::detect("not a credential").is_err());
+        assert_eq!(
+            SecuringMechanism::detect(vrc.to_vc_cose(&secret).unwrap()).unwrap(),
+            SecuringMechanism::Cose
+        );
+        assert!(SecuringMechanism::detect([0xa1, 0x01]).is_err());
+    }
+}
@@ -0,0 +1,794 @@
+/*!
+*   Selective disclosure of RCard and Endorsement credentials.
+*
+*   Follows the base/derived proof model of
+*   [ecdsa-sd-2023](https://www.w3.org/TR/vc-di-ecdsa/#ecdsa-sd-2023), with the following
+*   differences so that no RDF canonicalization is required:
+*   - Statements are JSON Pointer + value pairs canonicalized with JCS, instead of N-Quads
+*   - Signatures are EdDSA (Ed25519) for both the issuer and the per-credential ephemeral key
+*
+*   It is NOT interoperable with ecdsa-sd-2023 verifiers, or any other W3C cryptosuite, hence it
+*   uses the private cryptosuite identifier `x-dtg-eddsa-sd-jcs`. Only verifiers using this crate
+*   accept these proofs.
+*
+*   Selectively disclosable claims are:
+*   - RCard: each jCard property (e.g. `fn`, `email`, `tel`)
+*   - Endorsement: each top-level attribute of the endorsement object
+*
+*   1. Issuer signs the credential with [DTGCredential::sign_selective] creating a base proof
+*   2. Holder derives a credential revealing only chosen claims with
+*      [DTGCredential::derive_selective]
+*   3. Verifier checks the derived credential with [DTGCredential::verify_proof_with_public_key]
+*/
+
+use crate::{
+    DTGCommon, DTGCredential, DTGCredentialError, DTGCredentialType,
+    crypto::Algorithm,
+    proof::{CryptoSuite, DataIntegrityProof},
+    signer,
+};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use multibase::Base;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use serde_json_canonicalizer::to_string;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+
+/// Where the selectively disclosable claims live within the credential
+enum ClaimContainer {
+    /// Each array element is a claim (jCard properties)
+    Array(&'static str),
+
+    /// Each object attribute is a claim (Endorsement attributes)
+    Object(&'static str),
+}
+
+impl ClaimContainer {
+    fn for_type(type_: &DTGCredentialType) -> Result<Self, DTGCredentialError> {
+        match type_ {
+            DTGCredentialType::RCard => Ok(ClaimContainer::Array("/credentialSubject/card/1")),
+            DTGCredentialType::Endorsement => {
+                Ok(ClaimContainer::Object("/credentialSubject/endorsement"))
+            }
+            _ => Err(DTGCredentialError::SelectiveDisclosure(format!(
+                "{type_} does not support selective disclosure"
+            ))),
+        }
+    }
+
+    fn pointer(&self) -> &'static str {
+        match self {
+            ClaimContainer::Array(pointer) => pointer,
+            ClaimContainer::Object(pointer) => pointer,
+        }
+    }
+
+    /// Returns the child segment of a claim pointer if it belongs to this container
+    fn segment<'a>(&self, pointer: &'a str) -> Result<&'a str, DTGCredentialError> {
+        pointer
+            .strip_prefix(self.pointer())
+            .and_then(|p| p.strip_prefix('/'))
+            .ok_or_else(|| {
+                DTGCredentialError::SelectiveDisclosure(format!(
+                    "Claim ({pointer}) is outside of the disclosable container"
+                ))
+            })
+    }
+
+    fn index(&self, pointer: &str) -> Result<usize, DTGCredentialError> {
+        self.segment(pointer)?.parse().map_err(|_| {
+            DTGCredentialError::SelectiveDisclosure(format!("Invalid claim pointer ({pointer})"))
+        })
+    }
+
+    fn key(&self, pointer: &str) -> Result<String, DTGCredentialError> {
+        Ok(self.segment(pointer)?.replace("~1", "/").replace("~0", "~"))
+    }
+
+    /// Rejects pointers that refer to the same claim more than once
+    fn ensure_unique(&self, pointers: &[String]) -> Result<(), DTGCredentialError> {
+        let mut seen = BTreeSet::new();
+        for pointer in pointers {
+            let claim = match self {
+                ClaimContainer::Array(_) => self.index(pointer)?.to_string(),
+                ClaimContainer::Object(_) => self.key(pointer)?,
+            };
+            if !seen.insert(claim) {
+                return Err(DTGCredentialError::SelectiveDisclosure(format!(
+                    "Duplicate claim pointer ({pointer})"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns (pointer, claim name) for every claim in the container
+    fn claims(&self, doc: &Value) -> Result<Vec<(String, String)>, DTGCredentialError> {
+        let container = doc.pointer(self.pointer()).ok_or_else(|| {
+            DTGCredentialError::SelectiveDisclosure(format!(
+                "Credential is missing {}",
+                self.pointer()
+            ))
+        })?;
+
+        match (self, container) {
+            (ClaimContainer::Array(pointer), Value::Array(items)) => Ok(items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    (
+                        format!("{pointer}/{i}"),
+                        item.get(0)
+                            .and_then(|name| name.as_str())
+                            .unwrap_or_default()
+                            .to_string(),
+                    )
+                })
+                .collect()),
+            (ClaimContainer::Object(pointer), Value::Object(items)) => Ok(items
+                .keys()
+                .map(|key| {
+                    (
+                        format!("{pointer}/{}", key.replace('~', "~0").replace('/', "~1")),
+                        key.clone(),
+                    )
+                })
+                .collect()),
+            _ => Err(DTGCredentialError::SelectiveDisclosure(format!(
+                "{} has an unexpected structure",
+                self.pointer()
+            ))),
+        }
+    }
+
+    fn items_mut<'a>(&self, doc: &'a mut Value) -> Result<&'a mut Value, DTGCredentialError> {
+        doc.pointer_mut(self.pointer()).ok_or_else(|| {
+            DTGCredentialError::SelectiveDisclosure(format!(
+                "Credential is missing {}",
+                self.pointer()
+            ))
+        })
+    }
+
+    /// Removes the claims at their original positions, returning the claim values in order
+    fn remove_in_place(
+        &self,
+        doc: &mut Value,
+        pointers: &[String],
+    ) -> Result<Vec<Value>, DTGCredentialError> {
+        self.ensure_unique(pointers)?;
+        let values = pointers
+            .iter()
+            .map(|pointer| {
+                doc.pointer(pointer).cloned().ok_or_else(|| {
+                    DTGCredentialError::SelectiveDisclosure(format!(
+                        "Claim ({pointer}) is missing from the credential"
+                    ))
+                })
+            })
+            .collect::<Result<Vec<Value>, _>>()?;
+
+        match self {
+            ClaimContainer::Array(_) => {
+                let mut indexes = pointers
+                    .iter()
+                    .map(|pointer| self.index(pointer))
+                    .collect::<Result<Vec<usize>, _>>()?;
+                indexes.sort_unstable();
+                if let Value::Array(items) = self.items_mut(doc)? {
+                    for index in indexes.iter().rev() {
+                        items.remove(*index);
+                    }
+                }
+            }
+            ClaimContainer::Object(_) => {
+                let keys = pointers
+                    .iter()
+                    .map(|pointer| self.key(pointer))
+                    .collect::<Result<Vec<String>, _>>()?;
+                if let Value::Object(items) = self.items_mut(doc)? {
+                    for key in keys {
+                        items.remove(&key);
+                    }
+                }
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// Removes disclosed claims from a derived credential, returning the claim values in order
+    /// Derived arrays hold the mandatory claims first, followed by the disclosed claims
+    fn remove_derived(
+        &self,
+        doc: &mut Value,
+        pointers: &[String],
+    ) -> Result<Vec<Value>, DTGCredentialError> {
+        match self {
+            ClaimContainer::Array(_) => {
+                if let Value::Array(items) = self.items_mut(doc)? {
+                    if pointers.len() > items.len() {
+                        return Err(DTGCredentialError::SelectiveDisclosure(
+                            "More disclosed claims than credential claims".to_string(),
+                        ));
+                    }
+                    self.ensure_unique(pointers)?;
+                    Ok(items.split_off(items.len() - pointers.len()))
+                } else {
+                    Ok(Vec::new())
+                }
+            }
+            ClaimContainer::Object(_) => self.remove_in_place(doc, pointers),
+        }
+    }
+}
+
+/// Encoded proofValue components
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum ProofValue {
+    Base(ProofComponents),
+    Derived(ProofComponents),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProofComponents {
+    /// Issuer signature over the proof config, ephemeral public key and mandatory claims
+    base_signature: String,
+
+    /// Ephemeral public key used to sign each claim
+    public_key: String,
+
+    /// Ephemeral signature of each claim, in the same order as pointers
+    signatures: Vec<String>,
+
+    /// JSON Pointers (within the issued credential) of each selectively disclosable claim
+    pointers: Vec<String>,
+}
+
+impl ProofValue {
+    fn encode(&self) -> Result<String, DTGCredentialError> {
+        let bytes = serde_json::to_vec(self).map_err(|e| {
+            DTGCredentialError::SelectiveDisclosure(format!("Couldn't encode proofValue: {e}"))
+        })?;
+        Ok(multibase::encode(Base::Base64Url, bytes))
+    }
+
+    fn decode(proof: &DataIntegrityProof) -> Result<Self, DTGCredentialError> {
+        let Some(proof_value) = &proof.proof_value else {
+            return Err(DTGCredentialError::NotSigned);
+        };
+
+        let (_, bytes) = multibase::decode(proof_value).map_err(|e| {
+            DTGCredentialError::SelectiveDisclosure(format!("Invalid proofValue: {e}"))
+        })?;
+        serde_json::from_slice(&bytes).map_err(|e| {
+            DTGCredentialError::SelectiveDisclosure(format!("Invalid proofValue: {e}"))
+        })
+    }
+}
+
+fn decode_bytes(value: &str) -> Result<Vec<u8>, DTGCredentialError> {
+    Ok(multibase::decode(value)
+        .map_err(|e| DTGCredentialError::SelectiveDisclosure(format!("Invalid encoding: {e}")))?
+        .1)
+}
+
+fn jcs<S: Serialize>(value: &S) -> Result<String, DTGCredentialError> {
+    to_string(value)
+        .map_err(|e| DTGCredentialError::SelectiveDisclosure(format!("Couldn't canonicalize: {e}")))
+}
+
+/// Canonical bytes of a single claim statement
+fn statement(pointer: &str, value: &Value) -> Result<String, DTGCredentialError> {
+    jcs(&json!([pointer, value]))
+}
+
+/// Data signed by the issuer: proofHash || ephemeral public key || mandatoryHash
+fn base_sign_data(
+    proof_config: &DataIntegrityProof,
+    public_key: &[u8],
+    mandatory: &Value,
+) -> Result<Vec<u8>, DTGCredentialError> {
+    let proof_config = DataIntegrityProof {
+        proof_value: None,
+        ..proof_config.clone()
+    };
+
+    Ok([
+        Sha256::digest(jcs(&proof_config)?).to_vec(),
+        public_key.to_vec(),
+        Sha256::digest(jcs(mandatory)?).to_vec(),
+    ]
+    .concat())
+}
+
+fn unsigned_document(credential: &DTGCommon) -> Result<Value, DTGCredentialError> {
+    serde_json::to_value(DTGCommon {
+        proof: None,
+        ..credential.clone()
+    })
+    .map_err(|e| DTGCredentialError::SelectiveDisclosure(format!("Couldn't serialize: {e}")))
+}
+
+impl DTGCredential {
+    /// Sign the credential with a selective disclosure base proof (x-dtg-eddsa-sd-jcs)
+    /// NOTE: The cryptosuite is private to this crate, other verifiers will reject the proof
+    /// Only RCard and Endorsement credentials can be signed this way
+    /// signing_secret: The Ed25519 key to sign with, see [signer::SigningKey]
+    /// mandatory: Claim names that are always disclosed (jCard property or endorsement attribute)
+    /// create_time: Optional creation time for the proof, defaults to now if None
+    pub fn sign_selective(
+        &mut self,
+        signing_secret: &impl signer::SigningKey,
+        mandatory: &[&str],
+        create_time: Option<DateTime<Utc>>,
+    ) -> Result<DataIntegrityProof, DTGCredentialError> {
+        let issuer = signing_secret.signer()?;
+        if signer::Signer::algorithm(&issuer) != Algorithm::EdDSA {
+            return Err(DTGCredentialError::SelectiveDisclosure(format!(
+                "Unsupported key type: {}",
+                signer::Signer::algorithm(&issuer).key_type()
+            )));
+        }
+
+        let container = ClaimContainer::for_type(&self.type_)?;
+        let mut doc = unsigned_document(&self.credential)?;
+
+        let pointers: Vec<String> = container
+            .claims(&doc)?
+            .into_iter()
+            .filter(|(_, name)| !mandatory.contains(&name.as_str()))
+            .map(|(pointer, _)| pointer)
+            .collect();
+        let values = container.remove_in_place(&mut doc, &pointers)?;
+
+        // Each claim is signed by a key unique to this credential
+        let ephemeral = ephemeral_key();
+        let signatures = pointers
+            .iter()
+            .zip(values.iter())
+            .map(|(pointer, value)| {
+                Ok(multibase::encode(
+                    Base::Base64Url,
+                    ephemeral
+                        .sign(statement(pointer, value)?.as_bytes())
+                        .to_bytes(),
+                ))
+            })
+            .collect::<Result<Vec<String>, DTGCredentialError>>()?;
+
+        let mut proof = DataIntegrityProof {
+            type_: "DataIntegrityProof".to_string(),
+            cryptosuite: CryptoSuite::XDtgEddsaSdJcs,
+            created: Some(
+                create_time
+                    .unwrap_or_else(Utc::now)
+                    .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            ),
+            verification_method: signer::Signer::verification_method(&issuer),
+            proof_purpose: "assertionMethod".to_string(),
+            proof_value: None,
+            context: None,
+            challenge: None,
+            domain: None,
+        };
+
+        let public_key = ephemeral.verifying_key().to_bytes();
+        let base_signature =
+            signer::Signer::sign(&issuer, &base_sign_data(&proof, &public_key, &doc)?)?;
+
+        proof.proof_value = Some(
+            ProofValue::Base(ProofComponents {
+                base_signature: multibase::encode(Base::Base64Url, base_signature),
+                public_key: multibase::encode(Base::Base64Url, public_key),
+                signatures,
+                pointers,
+            })
+            .encode()?,
+        );
+
+        self.credential.proof = Some(proof.clone());
+        Ok(proof)
+    }
+
+    /// Derive a credential that only discloses the requested claims
+    /// The credential must hold a selective disclosure base proof, see [DTGCredential::sign_selective]
+    /// disclose: Claim names to reveal (jCard property or endorsement attribute)
+    ///           Mandatory claims are always revealed
+    ///
+    /// NOTE: Derived RCards list the mandatory jCard properties before the disclosed properties
+    pub fn derive_selective(&self, disclose: &[&str]) -> Result<DTGCredential, DTGCredentialError> {
+        let Some(proof) = &self.credential.proof else {
+            return Err(DTGCredentialError::NotSigned);
+        };
+        if proof.cryptosuite != CryptoSuite::XDtgEddsaSdJcs {
+            return Err(DTGCredentialError::UnsupportedCryptoSuite(
+                proof.cryptosuite.to_string(),
+            ));
+        }
+        let ProofValue::Base(base) = ProofValue::decode(proof)? else {
+            return Err(DTGCredentialError::SelectiveDisclosure(
+                "Can only derive from a base proof".to_string(),
+            ));
+        };
+        if base.pointers.len() != base.signatures.len() {
+            return Err(DTGCredentialError::SelectiveDisclosure(
+                "Mismatched claim signatures".to_string(),
+            ));
+        }
+
+        let container = ClaimContainer::for_type(&self.type_)?;
+        container.ensure_unique(&base.pointers)?;
+        let mut doc = unsigned_document(&self.credential)?;
+
+        let mut pointers = Vec::new();
+        let mut signatures = Vec::new();
+        let mut hidden = Vec::new();
+        for (pointer, signature) in base.pointers.iter().zip(base.signatures.iter()) {
+            let name = doc
+                .pointer(pointer)
+                .map(|claim| match &container {
+                    ClaimContainer::Array(_) => claim
+                        .get(0)
+                        .and_then(|name| name.as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                    ClaimContainer::Object(_) => container.key(pointer).unwrap_or_default(),
+                })
+                .ok_or_else(|| {
+                    DTGCredentialError::SelectiveDisclosure(format!(
+                        "Claim ({pointer}) is missing from the credential"
+                    ))
+                })?;
+
+            if disclose.contains(&name.as_str()) {
+                pointers.push(pointer.clone());
+                signatures.push(signature.clone());
+            } else {
+                hidden.push(pointer.clone());
+            }
+        }
+
+        // Strip every selectively disclosable claim, then append the disclosed claims
+        let all: Vec<String> = pointers.iter().chain(hidden.iter()).cloned().collect();
+        let mut values = container.remove_in_place(&mut doc, &all)?;
+        values.truncate(pointers.len());
+        match (container.items_mut(&mut doc)?, &container) {
+            (Value::Array(items), ClaimContainer::Array(_)) => items.extend(values),
+            (Value::Object(items), ClaimContainer::Object(_)) => {
+                for (pointer, value) in pointers.iter().zip(values) {
+                    items.insert(container.key(pointer)?, value);
+                }
+            }
+            _ => {
+                return Err(DTGCredentialError::SelectiveDisclosure(format!(
+                    "{} has an unexpected structure",
+                    container.pointer()
+                )));
+            }
+        }
+
+        let mut derived: DTGCredential = serde_json::from_value(doc).map_err(|e| {
+            DTGCredentialError::SelectiveDisclosure(format!("Couldn't derive credential: {e}"))
+        })?;
+        derived.credential.proof = Some(DataIntegrityProof {
+            proof_value: Some(
+                ProofValue::Derived(ProofComponents {
+                    base_signature: base.base_signature,
+                    public_key: base.public_key,
+                    signatures,
+                    pointers,
+                })
+                .encode()?,
+            ),
+            ..proof.clone()
+        });
+
+        Ok(derived)
+    }
+}
+
+/// Verifies a base or derived selective disclosure proof
+pub(crate) fn verify_proof(
+    credential: &DTGCredential,
+    proof: &DataIntegrityProof,
+    public_key_bytes: &[u8],
+) -> Result<(), DTGCredentialError> {
+    let container = ClaimContainer::for_type(&credential.type_)?;
+    let mut doc = unsigned_document(&credential.credential)?;
+
+    let (components, values) = match ProofValue::decode(proof)? {
+        ProofValue::Base(components) => {
+            let values = container.remove_in_place(&mut doc, &components.pointers)?;
+            (components, values)
+        }
+        ProofValue::Derived(components) => {
+            let values = container.remove_derived(&mut doc, &components.pointers)?;
+            (components, values)
+        }
+    };
+    if components.pointers.len() != components.signatures.len() {
+        return Err(DTGCredentialError::SelectiveDisclosure(
+            "Mismatched claim signatures".to_string(),
+        ));
+    }
+
+    let verification_error =
+        |e: String| DTGCredentialError::SelectiveDisclosure(format!("Verification failed: {e}"));
+
+    // Check the issuer signature over the mandatory claims
+    let public_key = decode_bytes(&components.public_key)?;
+    let issuer_key = VerifyingKey::try_from(public_key_bytes)
+        .map_err(|_| verification_error("Invalid public key bytes".to_string()))?;
+    let base_signature = Signature::from_slice(&decode_bytes(&components.base_signature)?)
+        .map_err(|_| verification_error("Invalid signature format".to_string()))?;
+    issuer_key
+        .verify_strict(&base_sign_data(proof, &public_key, &doc)?, &base_signature)
+        .map_err(|_| verification_error("Base signature is invalid".to_string()))?;
+
+    // Check each disclosed claim against the ephemeral key
+    let ephemeral = VerifyingKey::try_from(public_key.as_slice())
+        .map_err(|_| verification_error("Invalid ephemeral public key".to_string()))?;
+    for ((pointer, value), signature) in components
+        .pointers
+        .iter()
+        .zip(values.iter())
+        .zip(components.signatures.iter())
+    {
+        let signature = Signature::from_slice(&decode_bytes(signature)?)
+            .map_err(|_| verification_error("Invalid signature format".to_string()))?;
+        ephemeral
+            .verify_strict(statement(pointer, value)?.as_bytes(), &signature)
+            .map_err(|_| verification_error(format!("Claim ({pointer}) signature is invalid")))?;
+    }
+
+    Ok(())
+}
+
+/// Random Ed25519 key unique to a single credential
+fn ephemeral_key() -> SigningKey {
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    SigningKey::from_bytes(&bytes)
+}
+
+#[cfg(all(test, feature = "affinidi-signing"))]
+mod tests {
+    use super::{ProofComponents, ProofValue};
+    use crate::{CredentialSubject, DTGCredential, DTGCredentialError};
+    use affinidi_secrets_resolver::secrets::Secret;
+    use chrono::Utc;
+    use serde_json::json;
+
+    fn rcard() -> DTGCredential {
+        DTGCredential::new_rcard(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            Utc::now(),
+            None,
+            json!([
+                "vcard",
+                [
+                    ["version", {}, "text", "4.0"],
+                    ["fn", {}, "text", "Alice Smith"],
+                    ["email", {}, "text", "alice@example.com"],
+                    ["tel", {"type": "cell"}, "uri", "tel:+1-555-555-5555"]
+                ]
+            ]),
+        )
+    }
+
+    fn card(credential: &DTGCredential) -> serde_json::Value {
+        if let CredentialSubject::RCard(subject) = &credential.credential().credential_subject {
+            subject.card.clone()
+        } else {
+            panic!("Expected an RCard subject");
+        }
+    }
+
+    #[test]
+    fn test_rcard_disclose_name_only() {
+        let secret = Secret::generate_ed25519(None, None);
+        let mut rcard = rcard();
+
+        assert!(
+            rcard
+                .sign_selective(&secret, &["version"], Some(Utc::now()))
+                .is_ok()
+        );
+        // The base proof verifies as issued
+        assert!(
+            rcard
+                .verify_proof_with_public_key(secret.get_public_bytes())
+                .is_ok()
+        );
+
+        let derived = rcard.derive_selective(&["fn"]).unwrap();
+        assert_eq!(
+            card(&derived),
+            json!([
+                "vcard",
+                [
+                    ["version", {}, "text", "4.0"],
+                    ["fn", {}, "text", "Alice Smith"]
+                ]
+            ])
+        );
+        assert!(
+            derived
+                .verify_proof_with_public_key(secret.get_public_bytes())
+                .is_ok()
+        );
+
+        let derived = rcard.derive_selective(&["fn", "email"]).unwrap();
+        assert_eq!(card(&derived)[1].as_array().unwrap().len(), 3);
+        assert!(
+            derived
+                .verify_proof_with_public_key(secret.get_public_bytes())
+                .is_ok()
+        );
+
+        // Serialization round trip keeps the proof valid
+        let derived: DTGCredential =
+            serde_json::from_str(&serde_json::to_string(&derived).unwrap()).unwrap();
+        assert!(
+            derived
+                .verify_proof_with_public_key(secret.get_public_bytes())
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_rcard_tampered_claim() {
+        let secret = Secret::generate_ed25519(None, None);
+        let mut rcard = rcard();
+        rcard.sign_selective(&secret, &[], None).unwrap();
+
+        let mut derived = rcard.derive_selective(&["email"]).unwrap();
+        if let CredentialSubject::RCard(subject) = &mut derived.credential_mut().credential_subject
+        {
+            subject.card[1][0][3] = json!("mallory@example.com");
+        }
+
+        assert!(
+            derived
+                .verify_proof_with_public_key(secret.get_public_bytes())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_rcard_tampered_mandatory() {
+        let secret = Secret::generate_ed25519(None, None);
+        let mut rcard = rcard();
+        rcard.sign_selective(&secret, &["fn"], None).unwrap();
+
+        let mut derived = rcard.derive_selective(&[]).unwrap();
+        derived.credential_mut().issuer = "did:example:mallory".to_string();
+
+        assert!(
+            derived
+                .verify_proof_with_public_key(secret.get_public_bytes())
+                .is_err()
+        );
+    }
+
+    /// Repeats the first pointer and signature of the proof
+    fn duplicate_first_pointer(credential: &mut DTGCredential) {
+        let duplicate = |mut components: ProofComponents| {
+            components.pointers.push(components.pointers[0].clone());
+            components.signatures.push(components.signatures[0].clone());
+            components
+        };
+
+        let proof = credential.credential_mut().proof.as_mut().unwrap();
+        let proof_value = match ProofValue::decode(proof).unwrap() {
+            ProofValue::Base(components) => ProofValue::Base(duplicate(components)),
+            ProofValue::Derived(components) => ProofValue::Derived(duplicate(components)),
+        };
+        proof.proof_value = Some(proof_value.encode().unwrap());
+    }
+
+    #[test]
+    fn test_duplicate_pointers() {
+        let secret = Secret::generate_ed25519(None, None);
+        let mut rcard = rcard();
+        rcard.sign_selective(&secret, &["version"], None).unwrap();
+
+        // A disclosed claim repeated in a derived credential
+        let mut derived = rcard.derive_selective(&["fn"]).unwrap();
+        if let CredentialSubject::RCard(subject) = &mut derived.credential_mut().credential_subject
+        {
+            let claim = subject.card[1][1].clone();
+            subject.card[1].as_array_mut().unwrap().push(claim);
+        }
+        duplicate_first_pointer(&mut derived);
+        assert!(matches!(
+            derived.verify_proof_with_public_key(secret.get_public_bytes()),
+            Err(DTGCredentialError::SelectiveDisclosure(_))
+        ));
+
+        // A base proof listing a claim twice
+        duplicate_first_pointer(&mut rcard);
+        assert!(matches!(
+            rcard.derive_selective(&["fn"]),
+            Err(DTGCredentialError::SelectiveDisclosure(_))
+        ));
+        assert!(matches!(
+            rcard.verify_proof_with_public_key(secret.get_public_bytes()),
+            Err(DTGCredentialError::SelectiveDisclosure(_))
+        ));
+    }
+
+    #[test]
+    fn test_endorsement_selective() {
+        let secret = Secret::generate_ed25519(None, None);
+        let mut vec = DTGCredential::new_vec(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            Utc::now(),
+            None,
+            json!({
+              "type": "SkillEndorsement",
+              "name": "Software Development",
+              "competencyLevel": "expert"
+            }),
+        );
+        vec.sign_selective(&secret, &["type"], None).unwrap();
+
+        let derived = vec.derive_selective(&["name"]).unwrap();
+        if let CredentialSubject::Endorsement(subject) = &derived.credential().credential_subject {
+            assert_eq!(
+                subject.endorsement,
+                json!({"type": "SkillEndorsement", "name": "Software Development"})
+            );
+        } else {
+            panic!("Expected an Endorsement subject");
+        }
+        assert!(
+            derived
+                .verify_proof_with_public_key(secret.get_public_bytes())
+                .is_ok()
+        );
+
+        let secret2 = Secret::generate_ed25519(None, None);
+        assert!(
+            derived
+                .verify_proof_with_public_key(secret2.get_public_bytes())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_unsupported_credential_type() {
+        let secret = Secret::generate_ed25519(None, None);
+        let mut vrc = DTGCredential::new_vrc(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            Utc::now(),
+            None,
+        );
+
+        assert!(matches!(
+            vrc.sign_selective(&secret, &[], None),
+            Err(DTGCredentialError::SelectiveDisclosure(_))
+        ));
+    }
+
+    #[test]
+    fn test_derive_from_jcs_proof() {
+        let secret = Secret::generate_ed25519(None, None);
+        let mut rcard = rcard();
+        rcard.sign(&secret, None).unwrap();
+
+        assert!(matches!(
+            rcard.derive_selective(&["fn"]),
+            Err(DTGCredentialError::UnsupportedCryptoSuite(_))
+        ));
+    }
+}
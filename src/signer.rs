@@ -0,0 +1,516 @@
+/*!
+*   Signing DTG Credentials with keys held outside this process.
+*
+*   A [Signer] (or [AsyncSigner]) is handed the canonical bytes of a credential and returns the
+*   raw signature, the private key never has to be loaded into memory. This is how an HSM, a
+*   remote KMS or a mobile keystore is plugged in.
+*
+*   The verification method is part of the signed proof configuration, so it is asked for before
+*   signing and must identify the key that produces the signature.
+*
+*   Every [Signer] is also an [AsyncSigner].
+*
+*   [DTGCredential::sign] accepts any [SigningKey], which covers every [Signer] plus the in-memory
+*   keys of the enabled backend:
+*   - `affinidi-signing`: `affinidi_secrets_resolver::secrets::Secret`, see [SecretSigner]
+*   - `rust-crypto`: [KeyPair], built on ed25519-dalek, p256 and p384
+*/
+
+pub use crate::crypto::Algorithm;
+use crate::{
+    DTGCredential, DTGCredentialError, ecdsa, eddsa,
+    proof::{CryptoSuite, DataIntegrityProof},
+    rdfc,
+};
+#[cfg(feature = "affinidi-signing")]
+use affinidi_secrets_resolver::secrets::{KeyType, Secret};
+use chrono::{DateTime, Utc};
+use multibase::Base;
+use std::future::Future;
+
+/// Signs credentials with a key it holds
+pub trait Signer {
+    /// Signature algorithm of the key
+    fn algorithm(&self) -> Algorithm;
+
+    /// DID URL of the verification method for the key, written into the proof
+    fn verification_method(&self) -> String;
+
+    /// Sign the canonical bytes
+    /// Returns the raw signature, Ed25519 or the fixed size `r || s` ECDSA encoding
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>, DTGCredentialError>;
+}
+
+/// Signs credentials with a key it holds, for keys that are only reachable asynchronously
+pub trait AsyncSigner {
+    /// Signature algorithm of the key
+    fn algorithm(&self) -> Algorithm;
+
+    /// DID URL of the verification method for the key, written into the proof
+    fn verification_method(&self) -> String;
+
+    /// Sign the canonical bytes, see [Signer::sign]
+    fn sign(&self, data: &[u8])
+    -> impl Future<Output = Result<Vec<u8>, DTGCredentialError>> + Send;
+}
+
+impl<S: Signer + Sync> AsyncSigner for S {
+    fn algorithm(&self) -> Algorithm {
+        Signer::algorithm(self)
+    }
+
+    fn verification_method(&self) -> String {
+        Signer::verification_method(self)
+    }
+
+    fn sign(
+        &self,
+        data: &[u8],
+    ) -> impl Future<Output = Result<Vec<u8>, DTGCredentialError>> + Send {
+        std::future::ready(Signer::sign(self, data))
+    }
+}
+
+impl<S: Signer + ?Sized> Signer for &S {
+    fn algorithm(&self) -> Algorithm {
+        (**self).algorithm()
+    }
+
+    fn verification_method(&self) -> String {
+        (**self).verification_method()
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>, DTGCredentialError> {
+        (**self).sign(data)
+    }
+}
+
+/// Keys accepted by [DTGCredential::sign]
+pub trait SigningKey {
+    /// [Signer] for this key
+    /// Returns [DTGCredentialError::IncompatibleKeyType] if the key can't sign
+    fn signer(&self) -> Result<impl Signer + '_, DTGCredentialError>;
+}
+
+impl<S: Signer> SigningKey for S {
+    fn signer(&self) -> Result<impl Signer + '_, DTGCredentialError> {
+        Ok(self)
+    }
+}
+
+#[cfg(feature = "affinidi-signing")]
+impl SigningKey for Secret {
+    fn signer(&self) -> Result<impl Signer + '_, DTGCredentialError> {
+        SecretSigner::new(self)
+    }
+}
+
+/// [Signer] for an in-memory `Secret`
+#[cfg(feature = "affinidi-signing")]
+pub struct SecretSigner<'a> {
+    secret: &'a Secret,
+    algorithm: Algorithm,
+}
+
+#[cfg(feature = "affinidi-signing")]
+impl<'a> SecretSigner<'a> {
+    /// Ed25519, P-256 and P-384 secrets can sign
+    pub fn new(secret: &'a Secret) -> Result<Self, DTGCredentialError> {
+        let algorithm = match secret.get_key_type() {
+            KeyType::Ed25519 => Algorithm::EdDSA,
+            KeyType::P256 => Algorithm::ES256,
+            KeyType::P384 => Algorithm::ES384,
+            other => {
+                return Err(DTGCredentialError::IncompatibleKeyType(
+                    format!("{other:?}"),
+                    "any supported cryptosuite".to_string(),
+                ));
+            }
+        };
+        Ok(SecretSigner { secret, algorithm })
+    }
+}
+
+#[cfg(feature = "affinidi-signing")]
+impl Signer for SecretSigner<'_> {
+    fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    fn verification_method(&self) -> String {
+        self.secret.id.clone()
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>, DTGCredentialError> {
+        self.algorithm
+            .sign(self.secret.get_private_bytes(), data)
+            .map_err(DTGCredentialError::Signer)
+    }
+}
+
+/// In-memory key pair for the `rust-crypto` backend
+#[cfg(feature = "rust-crypto")]
+#[derive(Clone)]
+pub struct KeyPair {
+    id: String,
+    algorithm: Algorithm,
+    private_key: Vec<u8>,
+    public_key: Vec<u8>,
+}
+
+#[cfg(feature = "rust-crypto")]
+impl KeyPair {
+    /// Generate a random key pair
+    /// id: DID URL of the verification method for the key
+    pub fn generate(algorithm: Algorithm, id: &str) -> Self {
+        let (private_key, public_key) = algorithm.generate_key_pair();
+        KeyPair {
+            id: id.to_string(),
+            algorithm,
+            private_key,
+            public_key,
+        }
+    }
+
+    /// Key pair from the raw private key bytes
+    /// id: DID URL of the verification method for the key
+    pub fn from_private_bytes(
+        algorithm: Algorithm,
+        id: &str,
+        private_key: &[u8],
+    ) -> Result<Self, DTGCredentialError> {
+        let public_key = algorithm
+            .public_key(private_key)
+            .map_err(DTGCredentialError::Signer)?;
+        Ok(KeyPair {
+            id: id.to_string(),
+            algorithm,
+            private_key: private_key.to_vec(),
+            public_key,
+        })
+    }
+
+    /// DID URL of the verification method for the key
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Raw private key bytes
+    pub fn private_bytes(&self) -> &[u8] {
+        &self.private_key
+    }
+
+    /// Public key bytes, Ed25519 keys are 32 bytes, P-256/P-384 keys are SEC1 uncompressed
+    pub fn public_bytes(&self) -> &[u8] {
+        &self.public_key
+    }
+}
+
+#[cfg(feature = "rust-crypto")]
+impl std::fmt::Debug for KeyPair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyPair")
+            .field("id", &self.id)
+            .field("algorithm", &self.algorithm)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "rust-crypto")]
+impl Signer for KeyPair {
+    fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    fn verification_method(&self) -> String {
+        self.id.clone()
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>, DTGCredentialError> {
+        self.algorithm
+            .sign(&self.private_key, data)
+            .map_err(DTGCredentialError::Signer)
+    }
+}
+
+/// Cryptosuite used when the caller doesn't choose one
+pub(crate) fn default_cryptosuite(algorithm: Algorithm) -> CryptoSuite {
+    match algorithm {
+        Algorithm::EdDSA => CryptoSuite::EddsaJcs2022,
+        Algorithm::ES256 | Algorithm::ES384 => CryptoSuite::EcdsaJcs2019,
+    }
+}
+
+/// Builds the unsigned proof and the bytes the signer must sign
+fn signing_input(
+    credential: &DTGCredential,
+    algorithm: Algorithm,
+    verification_method: String,
+    cryptosuite: Option<CryptoSuite>,
+    create_time: Option<DateTime<Utc>>,
+) -> Result<(DataIntegrityProof, Vec<u8>), DTGCredentialError> {
+    let cryptosuite = cryptosuite.unwrap_or_else(|| default_cryptosuite(algorithm));
+    let compatible = match (&cryptosuite, algorithm) {
+        (CryptoSuite::EddsaJcs2022 | CryptoSuite::EddsaRdfc2022, Algorithm::EdDSA)
+        | (CryptoSuite::EcdsaJcs2019, Algorithm::ES256 | Algorithm::ES384) => true,
+        (CryptoSuite::EcdsaSd2023 | CryptoSuite::XDtgEddsaSdJcs, _) => {
+            return Err(DTGCredentialError::UnsupportedCryptoSuite(
+                cryptosuite.to_string(),
+            ));
+        }
+        _ => false,
+    };
+    if !compatible {
+        return Err(DTGCredentialError::IncompatibleKeyType(
+            algorithm.key_type().to_string(),
+            cryptosuite.to_string(),
+        ));
+    }
+
+    let proof = DataIntegrityProof {
+        type_: "DataIntegrityProof".to_string(),
+        cryptosuite,
+        created: Some(
+            create_time
+                .unwrap_or_else(Utc::now)
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        ),
+        verification_method,
+        proof_purpose: "assertionMethod".to_string(),
+        proof_value: None,
+        context: None,
+        challenge: None,
+        domain: None,
+    };
+
+    let data = match proof.cryptosuite {
+        CryptoSuite::EddsaJcs2022 => eddsa::jcs_hash_data(credential, &proof)?,
+        CryptoSuite::EddsaRdfc2022 => rdfc::hash_data(credential, &proof)?,
+        _ => ecdsa::jcs_hash_data(algorithm, credential, &proof)?,
+    };
+    Ok((proof, data))
+}
+
+impl DTGCredential {
+    /// Sign the credential using W3C Data Integrity Proof with an external key
+    /// signer: Signs the canonical bytes
+    /// cryptosuite: Defaults to eddsa-jcs-2022 for Ed25519 and ecdsa-jcs-2019 for P-256/P-384
+    /// create_time: Optional creation time for the proof, defaults to now if None
+    pub fn sign_with_signer(
+        &mut self,
+        signer: &impl Signer,
+        cryptosuite: Option<CryptoSuite>,
+        create_time: Option<DateTime<Utc>>,
+    ) -> Result<DataIntegrityProof, DTGCredentialError> {
+        let (mut proof, data) = signing_input(
+            self,
+            signer.algorithm(),
+            signer.verification_method(),
+            cryptosuite,
+            create_time,
+        )?;
+
+        proof.proof_value = Some(multibase::encode(Base::Base58Btc, signer.sign(&data)?));
+        self.credential.proof = Some(proof.clone());
+        Ok(proof)
+    }
+
+    /// Sign the credential using W3C Data Integrity Proof with an asynchronous external key
+    /// See [DTGCredential::sign_with_signer]
+    pub async fn sign_with_async_signer(
+        &mut self,
+        signer: &impl AsyncSigner,
+        cryptosuite: Option<CryptoSuite>,
+        create_time: Option<DateTime<Utc>>,
+    ) -> Result<DataIntegrityProof, DTGCredentialError> {
+        let (mut proof, data) = signing_input(
+            self,
+            signer.algorithm(),
+            signer.verification_method(),
+            cryptosuite,
+            create_time,
+        )?;
+
+        proof.proof_value = Some(multibase::encode(
+            Base::Base58Btc,
+            signer.sign(&data).await?,
+        ));
+        self.credential.proof = Some(proof.clone());
+        Ok(proof)
+    }
+}
+
+#[cfg(all(test, feature = "signing"))]
+mod tests {
+    use super::{Algorithm, Signer};
+    use crate::{DTGCredential, DTGCredentialError, proof::CryptoSuite};
+    use chrono::Utc;
+    use p256::ecdsa::{Signature, SigningKey, signature::Signer as _};
+
+    /// Stands in for a key held in an HSM
+    struct HsmSigner {
+        key: SigningKey,
+    }
+
+    impl HsmSigner {
+        fn new() -> Self {
+            HsmSigner {
+                key: SigningKey::random(&mut rand::rngs::OsRng),
+            }
+        }
+
+        fn public_key(&self) -> Vec<u8> {
+            self.key
+                .verifying_key()
+                .to_encoded_point(false)
+                .as_bytes()
+                .to_vec()
+        }
+    }
+
+    impl Signer for HsmSigner {
+        fn algorithm(&self) -> Algorithm {
+            Algorithm::ES256
+        }
+
+        fn verification_method(&self) -> String {
+            "did:example:issuer#hsm-1".to_string()
+        }
+
+        fn sign(&self, data: &[u8]) -> Result<Vec<u8>, DTGCredentialError> {
+            let signature: Signature = self.key.sign(data);
+            Ok(signature.to_vec())
+        }
+    }
+
+    fn vrc() -> DTGCredential {
+        DTGCredential::new_vrc(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            Utc::now(),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_sign_with_signer() {
+        let signer = HsmSigner::new();
+        let mut cred = vrc();
+        let proof = cred.sign_with_signer(&signer, None, None).unwrap();
+        assert_eq!(proof.cryptosuite, CryptoSuite::EcdsaJcs2019);
+        assert_eq!(proof.verification_method, "did:example:issuer#hsm-1");
+
+        assert!(
+            cred.verify_proof_with_public_key(&signer.public_key())
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sign_with_async_signer() {
+        let signer = HsmSigner::new();
+        let mut cred = vrc();
+        cred.sign_with_async_signer(&signer, None, None)
+            .await
+            .unwrap();
+
+        assert!(
+            cred.verify_proof_with_public_key(&signer.public_key())
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_envelopes_with_signer() {
+        let signer = HsmSigner::new();
+        let vrc = vrc();
+
+        let token = vrc.to_vc_jwt(&signer).unwrap();
+        assert_eq!(
+            DTGCredential::vc_jwt_key_id(&token).unwrap().as_deref(),
+            Some("did:example:issuer#hsm-1")
+        );
+        assert!(DTGCredential::from_vc_jwt(&token, &signer.public_key()).is_ok());
+
+        let cose = vrc.to_vc_cose(&signer).unwrap();
+        assert_eq!(
+            DTGCredential::vc_cose_key_id(&cose).unwrap().as_deref(),
+            Some("did:example:issuer#hsm-1")
+        );
+        assert!(DTGCredential::from_vc_cose(&cose, &signer.public_key()).is_ok());
+
+        let sd_jwt = vrc.to_sd_jwt(&signer, &[], None).unwrap();
+        assert!(sd_jwt.verify(&signer.public_key(), None).is_ok());
+    }
+
+    #[test]
+    fn test_incompatible_cryptosuite() {
+        let signer = HsmSigner::new();
+        let mut cred = vrc();
+
+        assert!(matches!(
+            cred.sign_with_signer(&signer, Some(CryptoSuite::EddsaRdfc2022), None),
+            Err(DTGCredentialError::IncompatibleKeyType(_, _))
+        ));
+        assert!(cred.credential.proof.is_none());
+    }
+
+    #[cfg(feature = "rust-crypto")]
+    #[test]
+    fn test_key_pair_sign_and_verify() {
+        use super::KeyPair;
+
+        for (algorithm, cryptosuites) in [
+            (
+                Algorithm::EdDSA,
+                vec![CryptoSuite::EddsaJcs2022, CryptoSuite::EddsaRdfc2022],
+            ),
+            (Algorithm::ES256, vec![CryptoSuite::EcdsaJcs2019]),
+            (Algorithm::ES384, vec![CryptoSuite::EcdsaJcs2019]),
+        ] {
+            let key = KeyPair::generate(algorithm, "did:example:issuer#key-1");
+            let other = KeyPair::generate(algorithm, "did:example:issuer#key-2");
+
+            for cryptosuite in cryptosuites {
+                let mut cred = vrc();
+                let proof = cred
+                    .sign_with_cryptosuite(&key, cryptosuite.clone(), None)
+                    .unwrap();
+                assert_eq!(proof.cryptosuite, cryptosuite);
+                assert_eq!(proof.verification_method, "did:example:issuer#key-1");
+
+                assert!(
+                    cred.verify_proof_with_public_key(key.public_bytes())
+                        .is_ok()
+                );
+                assert!(
+                    cred.verify_proof_with_public_key(other.public_bytes())
+                        .is_err()
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "rust-crypto")]
+    #[test]
+    fn test_key_pair_from_private_bytes() {
+        use super::KeyPair;
+
+        let key = KeyPair::generate(Algorithm::ES384, "did:example:issuer#key-1");
+        let restored =
+            KeyPair::from_private_bytes(Algorithm::ES384, key.id(), key.private_bytes()).unwrap();
+        assert_eq!(restored.public_bytes(), key.public_bytes());
+
+        let mut cred = vrc();
+        cred.sign(&restored, None).unwrap();
+        assert!(
+            cred.verify_proof_with_public_key(key.public_bytes())
+                .is_ok()
+        );
+
+        assert!(matches!(
+            KeyPair::from_private_bytes(Algorithm::EdDSA, key.id(), &[0u8; 4]),
+            Err(DTGCredentialError::Signer(_))
+        ));
+    }
+}
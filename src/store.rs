@@ -0,0 +1,615 @@
+/*!
+*   Storage for DTG Credentials.
+*
+*   [CredentialStore] is implemented by [MemoryStore] and, with the `sqlite` feature, by
+*   [SqliteStore]. Credentials are stored as the exact JSON they were received as, so proofs
+*   can still be verified when they are loaded again, and are identified by
+*   [DTGCredential::digest]. The optional `id` of a credential is read from
+*   [crate::DTGCommon::extra].
+*/
+
+use crate::{DTGCredential, DTGCredentialError, DTGCredentialType};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+fn store_error(msg: impl Into<String>) -> DTGCredentialError {
+    DTGCredentialError::Store(msg.into())
+}
+
+/// The `id` member of a credential, if it has one
+fn id(credential: &DTGCredential) -> Option<String> {
+    credential
+        .credential()
+        .extra
+        .get("id")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+/// A credential ready to be stored
+#[derive(Clone, Debug)]
+struct Record {
+    digest: String,
+    id: Option<String>,
+    json: String,
+    credential: DTGCredential,
+}
+
+impl Record {
+    fn from_json(json: &str) -> Result<Self, DTGCredentialError> {
+        let credential: DTGCredential = serde_json::from_str(json)
+            .map_err(|e| store_error(format!("Couldn't parse credential: {e}")))?;
+
+        Ok(Record {
+            digest: credential.digest()?,
+            id: id(&credential),
+            json: json.to_string(),
+            credential,
+        })
+    }
+
+    fn from_credential(credential: &DTGCredential) -> Result<Self, DTGCredentialError> {
+        Ok(Record {
+            digest: credential.digest()?,
+            id: id(credential),
+            json: serde_json::to_string(credential)
+                .map_err(|e| store_error(format!("Couldn't serialize credential: {e}")))?,
+            credential: credential.clone(),
+        })
+    }
+}
+
+/// Criteria for [CredentialStore::query], all must match
+#[derive(Clone, Debug, Default)]
+pub struct StoreQuery {
+    /// Issued by this DID
+    pub issuer: Option<String>,
+
+    /// Issued about this DID
+    pub subject: Option<String>,
+
+    /// Of this credential type
+    pub type_: Option<DTGCredentialType>,
+
+    /// Valid at some point from this time
+    pub from: Option<DateTime<Utc>>,
+
+    /// Valid at some point until this time
+    pub until: Option<DateTime<Utc>>,
+
+    /// Signed or unsigned, see [crate::DTGCommon::signed]
+    pub signed: Option<bool>,
+}
+
+impl StoreQuery {
+    /// Matches every credential
+    pub fn new() -> Self {
+        StoreQuery::default()
+    }
+
+    /// Only credentials issued by this DID
+    pub fn with_issuer(mut self, issuer: &str) -> Self {
+        self.issuer = Some(issuer.to_string());
+        self
+    }
+
+    /// Only credentials issued about this DID
+    pub fn with_subject(mut self, subject: &str) -> Self {
+        self.subject = Some(subject.to_string());
+        self
+    }
+
+    /// Only credentials of this type
+    pub fn with_type(mut self, type_: DTGCredentialType) -> Self {
+        self.type_ = Some(type_);
+        self
+    }
+
+    /// Only credentials valid at some point between from and until
+    pub fn with_window(mut self, from: DateTime<Utc>, until: DateTime<Utc>) -> Self {
+        self.from = Some(from);
+        self.until = Some(until);
+        self
+    }
+
+    /// Only credentials valid at this time
+    pub fn with_time(self, at: DateTime<Utc>) -> Self {
+        self.with_window(at, at)
+    }
+
+    /// Only signed, or unsigned, credentials
+    pub fn with_signed(mut self, signed: bool) -> Self {
+        self.signed = Some(signed);
+        self
+    }
+
+    /// Does the credential match the query?
+    pub fn matches(&self, credential: &DTGCredential) -> bool {
+        let common = credential.credential();
+        self.issuer
+            .as_deref()
+            .is_none_or(|i| i == credential.issuer())
+            && self
+                .subject
+                .as_deref()
+                .is_none_or(|s| s == credential.subject())
+            && self.type_.as_ref().is_none_or(|t| *t == credential.type_())
+            && self.until.is_none_or(|until| common.valid_from <= until)
+            && self.from.is_none_or(|from| {
+                common
+                    .valid_until
+                    .is_none_or(|valid_until| valid_until >= from)
+            })
+            && self.signed.is_none_or(|signed| signed == common.signed())
+    }
+}
+
+/// Storage for DTG Credentials, identified by [DTGCredential::digest]
+pub trait CredentialStore {
+    /// Stores a credential exactly as received, returns its digest
+    /// Storing a credential that is already stored does nothing
+    fn insert_json(&mut self, json: &str) -> Result<String, DTGCredentialError>;
+
+    /// Stores a credential serialized by this crate, returns its digest
+    /// Storing a credential that is already stored does nothing
+    fn insert(&mut self, credential: &DTGCredential) -> Result<String, DTGCredentialError>;
+
+    /// Credential with this digest
+    fn get(&self, digest: &str) -> Result<Option<DTGCredential>, DTGCredentialError>;
+
+    /// Credential with this `id`
+    fn get_by_id(&self, id: &str) -> Result<Option<DTGCredential>, DTGCredentialError>;
+
+    /// Stored JSON of the credential with this digest
+    fn get_json(&self, digest: &str) -> Result<Option<String>, DTGCredentialError>;
+
+    /// Credentials matching the query, ordered by digest
+    fn query(&self, query: &StoreQuery) -> Result<Vec<DTGCredential>, DTGCredentialError>;
+
+    /// Removes a credential, returns false if it wasn't stored
+    fn remove(&mut self, digest: &str) -> Result<bool, DTGCredentialError>;
+
+    /// Number of stored credentials
+    fn len(&self) -> Result<usize, DTGCredentialError>;
+
+    /// Are there no stored credentials?
+    fn is_empty(&self) -> Result<bool, DTGCredentialError> {
+        Ok(self.len()? == 0)
+    }
+}
+
+/// Credentials held in memory
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore {
+    records: BTreeMap<String, Record>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+
+    fn insert_record(&mut self, record: Record) -> String {
+        let digest = record.digest.clone();
+        self.records.entry(digest.clone()).or_insert(record);
+        digest
+    }
+}
+
+impl CredentialStore for MemoryStore {
+    fn insert_json(&mut self, json: &str) -> Result<String, DTGCredentialError> {
+        Ok(self.insert_record(Record::from_json(json)?))
+    }
+
+    fn insert(&mut self, credential: &DTGCredential) -> Result<String, DTGCredentialError> {
+        Ok(self.insert_record(Record::from_credential(credential)?))
+    }
+
+    fn get(&self, digest: &str) -> Result<Option<DTGCredential>, DTGCredentialError> {
+        Ok(self
+            .records
+            .get(digest)
+            .map(|record| record.credential.clone()))
+    }
+
+    fn get_by_id(&self, id: &str) -> Result<Option<DTGCredential>, DTGCredentialError> {
+        Ok(self
+            .records
+            .values()
+            .find(|record| record.id.as_deref() == Some(id))
+            .map(|record| record.credential.clone()))
+    }
+
+    fn get_json(&self, digest: &str) -> Result<Option<String>, DTGCredentialError> {
+        Ok(self.records.get(digest).map(|record| record.json.clone()))
+    }
+
+    fn query(&self, query: &StoreQuery) -> Result<Vec<DTGCredential>, DTGCredentialError> {
+        Ok(self
+            .records
+            .values()
+            .filter(|record| query.matches(&record.credential))
+            .map(|record| record.credential.clone())
+            .collect())
+    }
+
+    fn remove(&mut self, digest: &str) -> Result<bool, DTGCredentialError> {
+        Ok(self.records.remove(digest).is_some())
+    }
+
+    fn len(&self) -> Result<usize, DTGCredentialError> {
+        Ok(self.records.len())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::{CredentialStore, Record, StoreQuery, store_error};
+    use crate::{DTGCredential, DTGCredentialError};
+    use rusqlite::{Connection, OptionalExtension, params, params_from_iter, types::Value};
+    use std::path::Path;
+
+    const SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS dtg_credentials (
+            digest TEXT PRIMARY KEY NOT NULL,
+            id TEXT,
+            issuer TEXT NOT NULL,
+            subject TEXT NOT NULL,
+            type TEXT NOT NULL,
+            valid_from INTEGER NOT NULL,
+            valid_until INTEGER,
+            signed INTEGER NOT NULL,
+            json TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS dtg_credentials_id ON dtg_credentials (id);
+        CREATE INDEX IF NOT EXISTS dtg_credentials_issuer ON dtg_credentials (issuer);
+        CREATE INDEX IF NOT EXISTS dtg_credentials_subject ON dtg_credentials (subject);
+    ";
+
+    fn sqlite_error(e: rusqlite::Error) -> DTGCredentialError {
+        store_error(format!("SQLite: {e}"))
+    }
+
+    fn parse(json: String) -> Result<DTGCredential, DTGCredentialError> {
+        serde_json::from_str(&json)
+            .map_err(|e| store_error(format!("Couldn't parse stored credential: {e}")))
+    }
+
+    /// Credentials held in a SQLite database, in the `dtg_credentials` table
+    /// Validity timestamps are stored as microseconds since the Unix epoch
+    pub struct SqliteStore {
+        connection: Connection,
+    }
+
+    impl SqliteStore {
+        /// Opens, or creates, the database at path
+        pub fn open(path: impl AsRef<Path>) -> Result<Self, DTGCredentialError> {
+            SqliteStore::new(Connection::open(path).map_err(sqlite_error)?)
+        }
+
+        /// Database that only lives as long as the store
+        pub fn open_in_memory() -> Result<Self, DTGCredentialError> {
+            SqliteStore::new(Connection::open_in_memory().map_err(sqlite_error)?)
+        }
+
+        /// Uses an existing connection, creating the table if needed
+        pub fn new(connection: Connection) -> Result<Self, DTGCredentialError> {
+            connection.execute_batch(SCHEMA).map_err(sqlite_error)?;
+            Ok(SqliteStore { connection })
+        }
+
+        /// The underlying connection
+        pub fn connection(&self) -> &Connection {
+            &self.connection
+        }
+
+        fn insert_record(&mut self, record: Record) -> Result<String, DTGCredentialError> {
+            let common = record.credential.credential();
+            self.connection
+                .execute(
+                    "INSERT OR IGNORE INTO dtg_credentials
+                        (digest, id, issuer, subject, type, valid_from, valid_until, signed, json)
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    params![
+                        record.digest,
+                        record.id,
+                        common.issuer(),
+                        common.subject(),
+                        record.credential.type_().to_string(),
+                        common.valid_from.timestamp_micros(),
+                        common.valid_until.map(|until| until.timestamp_micros()),
+                        common.signed(),
+                        record.json,
+                    ],
+                )
+                .map_err(sqlite_error)?;
+            Ok(record.digest)
+        }
+
+        fn json_where(
+            &self,
+            column: &str,
+            value: &str,
+        ) -> Result<Option<String>, DTGCredentialError> {
+            self.connection
+                .query_row(
+                    &format!("SELECT json FROM dtg_credentials WHERE {column} = ?1 LIMIT 1"),
+                    [value],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(sqlite_error)
+        }
+    }
+
+    impl CredentialStore for SqliteStore {
+        fn insert_json(&mut self, json: &str) -> Result<String, DTGCredentialError> {
+            self.insert_record(Record::from_json(json)?)
+        }
+
+        fn insert(&mut self, credential: &DTGCredential) -> Result<String, DTGCredentialError> {
+            self.insert_record(Record::from_credential(credential)?)
+        }
+
+        fn get(&self, digest: &str) -> Result<Option<DTGCredential>, DTGCredentialError> {
+            self.get_json(digest)?.map(parse).transpose()
+        }
+
+        fn get_by_id(&self, id: &str) -> Result<Option<DTGCredential>, DTGCredentialError> {
+            self.json_where("id", id)?.map(parse).transpose()
+        }
+
+        fn get_json(&self, digest: &str) -> Result<Option<String>, DTGCredentialError> {
+            self.json_where("digest", digest)
+        }
+
+        fn query(&self, query: &StoreQuery) -> Result<Vec<DTGCredential>, DTGCredentialError> {
+            let mut conditions = Vec::new();
+            let mut values = Vec::new();
+            if let Some(issuer) = &query.issuer {
+                conditions.push("issuer = ?");
+                values.push(Value::Text(issuer.clone()));
+            }
+            if let Some(subject) = &query.subject {
+                conditions.push("subject = ?");
+                values.push(Value::Text(subject.clone()));
+            }
+            if let Some(type_) = &query.type_ {
+                conditions.push("type = ?");
+                values.push(Value::Text(type_.to_string()));
+            }
+            if let Some(until) = &query.until {
+                conditions.push("valid_from <= ?");
+                values.push(Value::Integer(until.timestamp_micros()));
+            }
+            if let Some(from) = &query.from {
+                conditions.push("(valid_until IS NULL OR valid_until >= ?)");
+                values.push(Value::Integer(from.timestamp_micros()));
+            }
+            if let Some(signed) = query.signed {
+                conditions.push("signed = ?");
+                values.push(Value::Integer(signed.into()));
+            }
+
+            let mut sql = String::from("SELECT json FROM dtg_credentials");
+            if !conditions.is_empty() {
+                sql.push_str(" WHERE ");
+                sql.push_str(&conditions.join(" AND "));
+            }
+            sql.push_str(" ORDER BY digest");
+
+            let mut statement = self.connection.prepare(&sql).map_err(sqlite_error)?;
+            let rows = statement
+                .query_map(params_from_iter(values), |row| row.get::<_, String>(0))
+                .map_err(sqlite_error)?;
+            rows.map(|json| parse(json.map_err(sqlite_error)?))
+                .collect()
+        }
+
+        fn remove(&mut self, digest: &str) -> Result<bool, DTGCredentialError> {
+            self.connection
+                .execute("DELETE FROM dtg_credentials WHERE digest = ?1", [digest])
+                .map(|deleted| deleted > 0)
+                .map_err(sqlite_error)
+        }
+
+        fn len(&self) -> Result<usize, DTGCredentialError> {
+            self.connection
+                .query_row("SELECT COUNT(*) FROM dtg_credentials", [], |row| {
+                    row.get::<_, i64>(0)
+                })
+                .map(|count| count as usize)
+                .map_err(sqlite_error)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "affinidi-signing"))]
+mod tests {
+    use super::{CredentialStore, MemoryStore, StoreQuery};
+    use crate::{DTGCredential, DTGCredentialType};
+    use affinidi_secrets_resolver::secrets::Secret;
+    use chrono::{Duration, Utc};
+    use serde_json::json;
+
+    fn vrc(from: &str, to: &str) -> DTGCredential {
+        DTGCredential::new_vrc(
+            format!("did:example:{from}"),
+            format!("did:example:{to}"),
+            Utc::now() - Duration::days(1),
+            None,
+        )
+    }
+
+    /// Exercises a store, shared by every implementation
+    fn exercise(store: &mut impl CredentialStore) {
+        assert!(store.is_empty().unwrap());
+
+        // Signed VRC with an id, received as pretty printed JSON
+        let secret = Secret::generate_ed25519(None, None);
+        let mut signed = vrc("alice", "bob");
+        signed
+            .credential
+            .extra
+            .insert("id".to_string(), json!("urn:uuid:alice-bob"));
+        signed.sign(&secret, None).unwrap();
+        let received = serde_json::to_string_pretty(&signed).unwrap();
+
+        let digest = store.insert_json(&received).unwrap();
+        assert_eq!(digest, signed.digest().unwrap());
+        assert_eq!(store.get_json(&digest).unwrap().unwrap(), received);
+        assert!(
+            store
+                .get(&digest)
+                .unwrap()
+                .unwrap()
+                .verify_proof_with_public_key(secret.get_public_bytes())
+                .is_ok()
+        );
+        assert_eq!(
+            store.get(&digest).unwrap().unwrap().credential().extra["id"],
+            "urn:uuid:alice-bob"
+        );
+        assert_eq!(
+            store
+                .get_by_id("urn:uuid:alice-bob")
+                .unwrap()
+                .unwrap()
+                .digest()
+                .unwrap(),
+            digest
+        );
+        assert!(store.get_by_id("urn:uuid:unknown").unwrap().is_none());
+
+        // Already stored
+        assert_eq!(store.insert(&signed).unwrap(), digest);
+        assert_eq!(store.len().unwrap(), 1);
+
+        let unsigned = store.insert(&vrc("bob", "alice")).unwrap();
+        let mut expired = vrc("carol", "alice");
+        expired.credential.valid_from = Utc::now() - Duration::days(365);
+        expired.credential.valid_until = Some(Utc::now() - Duration::days(30));
+        let expired = store.insert(&expired).unwrap();
+        let vmc = store
+            .insert(&DTGCredential::new_vmc(
+                "did:example:club".to_string(),
+                "did:example:alice".to_string(),
+                Utc::now() - Duration::days(1),
+                None,
+                false,
+            ))
+            .unwrap();
+        assert_eq!(store.len().unwrap(), 4);
+
+        let query = |query: StoreQuery| -> Vec<String> {
+            store
+                .query(&query)
+                .unwrap()
+                .iter()
+                .map(|credential| credential.digest().unwrap())
+                .collect()
+        };
+        let mut all = vec![
+            digest.clone(),
+            unsigned.clone(),
+            expired.clone(),
+            vmc.clone(),
+        ];
+        all.sort();
+        assert_eq!(query(StoreQuery::new()), all);
+        assert_eq!(
+            query(StoreQuery::new().with_issuer("did:example:alice")),
+            vec![digest.clone()]
+        );
+        assert_eq!(
+            query(StoreQuery::new().with_subject("did:example:bob")),
+            vec![digest.clone()]
+        );
+        assert_eq!(
+            query(StoreQuery::new().with_type(DTGCredentialType::Membership)),
+            vec![vmc.clone()]
+        );
+        assert_eq!(
+            query(StoreQuery::new().with_signed(true)),
+            vec![digest.clone()]
+        );
+        assert_eq!(query(StoreQuery::new().with_signed(false)).len(), 3);
+
+        let now = query(
+            StoreQuery::new()
+                .with_subject("did:example:alice")
+                .with_time(Utc::now()),
+        );
+        assert_eq!(now.len(), 2);
+        assert!(!now.contains(&expired));
+        assert_eq!(
+            query(StoreQuery::new().with_window(
+                Utc::now() - Duration::days(60),
+                Utc::now() - Duration::days(7)
+            )),
+            vec![expired.clone()]
+        );
+
+        assert!(store.remove(&expired).unwrap());
+        assert!(!store.remove(&expired).unwrap());
+        assert!(store.get(&expired).unwrap().is_none());
+        assert_eq!(store.len().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_memory_store() {
+        exercise(&mut MemoryStore::new());
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        let mut store = MemoryStore::new();
+        assert!(store.insert_json("{}").is_err());
+        assert!(store.insert_json("not json").is_err());
+        assert!(store.is_empty().unwrap());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_store() {
+        exercise(&mut super::SqliteStore::open_in_memory().unwrap());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_reload() {
+        use super::SqliteStore;
+
+        let path = std::env::temp_dir().join(format!(
+            "dtg-credentials-{}-{}.db",
+            std::process::id(),
+            Utc::now().timestamp_nanos_opt().unwrap()
+        ));
+        let secret = Secret::generate_ed25519(None, None);
+        let mut credential = vrc("alice", "bob");
+        credential.sign(&secret, None).unwrap();
+        let json = serde_json::to_string(&credential).unwrap();
+
+        let digest = SqliteStore::open(&path)
+            .unwrap()
+            .insert_json(&json)
+            .unwrap();
+
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.get_json(&digest).unwrap().unwrap(), json);
+        assert!(
+            store
+                .get(&digest)
+                .unwrap()
+                .unwrap()
+                .verify_proof_with_public_key(secret.get_public_bytes())
+                .is_ok()
+        );
+        drop(store);
+        std::fs::remove_file(path).unwrap();
+    }
+}
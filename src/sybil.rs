@@ -0,0 +1,694 @@
+/*!
+*   Sybil-resistance heuristics over the trust graph.
+*
+*   PHCs (VMCs flagged `PersonhoodCredential`) and VWCs are the DTG's defence against sybils,
+*   this module looks for the patterns that suggest they are being gamed:
+*   - Dense clusters: groups of DIDs with many VRCs between themselves but few VRCs to the rest
+*     of the graph that someone outside the group witnessed. VRCs only their issuer vouches for,
+*     neither reciprocated nor witnessed, don't connect DIDs
+*   - PHC issuers: a single issuer issuing PHCs to many subjects, and the subjects whose only
+*     PHCs come from such issuers
+*   - Session reuse: witnesses using one of their `session_id`s for VWCs covering more than one
+*     pair of DIDs
+*
+*   The result is a [SybilReport] with the flags raised for each DID and the credentials behind
+*   them. These are heuristics for moderation tooling, a flag is a reason to look closer rather
+*   than proof of a sybil.
+*/
+
+use crate::{
+    CredentialSubject, DTGCredentialType,
+    graph::{TrustEdge, TrustGraph},
+};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Thresholds for the heuristics
+#[derive(Clone, Debug)]
+pub struct SybilConfig {
+    /// Smallest group of DIDs reported as a cluster
+    pub min_cluster_size: usize,
+
+    /// Fraction of the pairs of members with a VRC between them for a cluster to be dense
+    pub min_density: f64,
+
+    /// Clusters need at least this many externally witnessed VRCs per member
+    pub min_external_witnessed: f64,
+
+    /// Issuers of PHCs to more subjects than this are flagged
+    pub max_personhood_credentials: usize,
+
+    /// PHC issuers that are never flagged, e.g. a government registry
+    pub trusted_issuers: BTreeSet<String>,
+
+    /// Credentials must be valid at this time
+    pub at: DateTime<Utc>,
+}
+
+impl Default for SybilConfig {
+    fn default() -> Self {
+        SybilConfig {
+            min_cluster_size: 3,
+            min_density: 0.5,
+            min_external_witnessed: 0.5,
+            max_personhood_credentials: 25,
+            trusted_issuers: BTreeSet::new(),
+            at: Utc::now(),
+        }
+    }
+}
+
+impl SybilConfig {
+    /// Clusters of 3 or more with half of the pairs connected and fewer than one externally
+    /// witnessed VRC per two members, issuers of more than 25 PHCs, valid now
+    pub fn new() -> Self {
+        SybilConfig::default()
+    }
+
+    /// Sets the smallest group of DIDs reported as a cluster
+    pub fn with_min_cluster_size(mut self, size: usize) -> Self {
+        self.min_cluster_size = size;
+        self
+    }
+
+    /// Sets the fraction of connected pairs for a cluster to be dense
+    pub fn with_min_density(mut self, density: f64) -> Self {
+        self.min_density = density;
+        self
+    }
+
+    /// Sets the number of externally witnessed VRCs per member a cluster needs
+    pub fn with_min_external_witnessed(mut self, per_member: f64) -> Self {
+        self.min_external_witnessed = per_member;
+        self
+    }
+
+    /// Sets the number of subjects an issuer can issue PHCs to before being flagged
+    pub fn with_max_personhood_credentials(mut self, max: usize) -> Self {
+        self.max_personhood_credentials = max;
+        self
+    }
+
+    /// Adds a PHC issuer that is never flagged
+    pub fn with_trusted_issuer(mut self, issuer: &str) -> Self {
+        self.trusted_issuers.insert(issuer.to_string());
+        self
+    }
+
+    /// Sets the time credentials must be valid at
+    pub fn with_time(mut self, at: DateTime<Utc>) -> Self {
+        self.at = at;
+        self
+    }
+}
+
+/// Why a DID was flagged
+#[derive(Clone, Debug, PartialEq)]
+pub enum SybilFlag {
+    /// Member of a dense cluster, index into [SybilReport::clusters]
+    DenseCluster { cluster: usize },
+
+    /// Issued PHCs to too many subjects, digests of the PHCs
+    PersonhoodIssuer { credentials: Vec<String> },
+
+    /// Only holds PHCs from flagged issuers, digests of the PHCs
+    FlaggedPersonhood { credentials: Vec<String> },
+
+    /// Used a session for VWCs covering more than one pair of DIDs, digests of the VWCs
+    SessionReuse {
+        session_id: String,
+        credentials: Vec<String>,
+    },
+}
+
+/// A dense group of DIDs with few externally witnessed VRCs
+#[derive(Clone, Debug)]
+pub struct SuspiciousCluster {
+    /// Member DIDs, sorted
+    pub members: Vec<String>,
+
+    /// Fraction of the pairs of members with a VRC between them
+    pub density: f64,
+
+    /// Digests of the VRCs between a member and a non-member witnessed by a non-member
+    pub external_witnessed: Vec<String>,
+
+    /// Digests of the VRCs between members
+    pub credentials: Vec<String>,
+}
+
+/// Flags raised for a DID
+#[derive(Clone, Debug)]
+pub struct DIDReport {
+    pub did: String,
+    pub flags: Vec<SybilFlag>,
+}
+
+/// Result of the sybil analysis of a [TrustGraph]
+#[derive(Clone, Debug, Default)]
+pub struct SybilReport {
+    /// Clusters that were flagged
+    pub clusters: Vec<SuspiciousCluster>,
+
+    /// Reports for every flagged DID, by DID
+    pub reports: BTreeMap<String, DIDReport>,
+}
+
+impl SybilReport {
+    /// Report for a DID, None if it wasn't flagged
+    pub fn get(&self, did: &str) -> Option<&DIDReport> {
+        self.reports.get(did)
+    }
+
+    /// Was this DID flagged?
+    pub fn is_flagged(&self, did: &str) -> bool {
+        self.reports.contains_key(did)
+    }
+
+    /// Reports for every flagged DID, ordered by DID
+    pub fn flagged(&self) -> impl Iterator<Item = &DIDReport> {
+        self.reports.values()
+    }
+
+    fn flag(&mut self, did: &str, flag: SybilFlag) {
+        self.reports
+            .entry(did.to_string())
+            .or_insert_with(|| DIDReport {
+                did: did.to_string(),
+                flags: Vec::new(),
+            })
+            .flags
+            .push(flag);
+    }
+}
+
+/// VRCs between two DIDs
+#[derive(Default)]
+struct Pair<'a> {
+    /// A VRC from the lower to the higher DID
+    forward: bool,
+
+    /// A VRC from the higher to the lower DID
+    backward: bool,
+
+    /// Issuers of VWCs of the VRCs, other than the pair themselves
+    witnesses: BTreeSet<&'a str>,
+}
+
+impl Pair<'_> {
+    /// Do both DIDs stand behind the relationship? Reciprocated or witnessed by someone else
+    fn vouched(&self) -> bool {
+        (self.forward && self.backward) || !self.witnesses.is_empty()
+    }
+}
+
+/// Densest group of the DIDs, by pairs per member
+/// Peels the DID with the fewest pairs until one remains and keeps the densest group seen, the
+/// largest on ties. Greedy peeling is within a factor of two of the densest group.
+fn densest<'a>(
+    dids: &BTreeSet<&'a str>,
+    pairs: &[(&(&'a str, &'a str), &Pair)],
+) -> BTreeSet<&'a str> {
+    let mut neighbours: BTreeMap<&str, BTreeSet<&str>> =
+        dids.iter().map(|did| (*did, BTreeSet::new())).collect();
+    let mut edges = 0;
+    for ((a, b), _) in pairs {
+        if dids.contains(a) && dids.contains(b) {
+            neighbours.entry(a).or_default().insert(b);
+            neighbours.entry(b).or_default().insert(a);
+            edges += 1;
+        }
+    }
+
+    let mut current = dids.clone();
+    let mut best = (edges as f64 / current.len() as f64, current.clone());
+    while current.len() > 1 {
+        let Some(peeled) = current
+            .iter()
+            .min_by_key(|did| (neighbours[*did].len(), **did))
+            .copied()
+        else {
+            break;
+        };
+        current.remove(peeled);
+        for neighbour in neighbours.remove(peeled).unwrap_or_default() {
+            if let Some(set) = neighbours.get_mut(neighbour) {
+                set.remove(peeled);
+            }
+            edges -= 1;
+        }
+
+        let density = edges as f64 / current.len() as f64;
+        if density > best.0 {
+            best = (density, current.clone());
+        }
+    }
+    best.1
+}
+
+/// Minimal union-find over DIDs
+struct Components<'a> {
+    index: BTreeMap<&'a str, usize>,
+    parent: Vec<usize>,
+}
+
+impl<'a> Components<'a> {
+    fn new(nodes: Vec<&'a str>) -> Self {
+        Components {
+            parent: (0..nodes.len()).collect(),
+            index: nodes.into_iter().enumerate().map(|(i, n)| (n, i)).collect(),
+        }
+    }
+
+    fn find(&mut self, did: &str) -> usize {
+        let mut node = self.index[did];
+        while self.parent[node] != node {
+            self.parent[node] = self.parent[self.parent[node]];
+            node = self.parent[node];
+        }
+        node
+    }
+
+    fn union(&mut self, a: &str, b: &str) {
+        let (a, b) = (self.find(a), self.find(b));
+        // Lowest index is the root so components are found in the same order every time
+        self.parent[a.max(b)] = a.min(b);
+    }
+}
+
+impl TrustGraph {
+    /// Runs the sybil heuristics over the graph
+    pub fn sybil_report(&self, config: &SybilConfig) -> SybilReport {
+        let mut report = SybilReport::default();
+        self.flag_clusters(config, &mut report);
+        self.flag_personhood(config, &mut report);
+        self.flag_sessions(config, &mut report);
+        report
+    }
+
+    /// VWCs of a credential issued by someone other than its issuer and subject
+    fn third_party_witnesses(&self, edge: &TrustEdge, at: DateTime<Utc>) -> Vec<&TrustEdge> {
+        self.witnesses(edge.digest(), at)
+            .into_iter()
+            .filter(|witness| witness.from() != edge.from() && witness.from() != edge.to())
+            .collect()
+    }
+
+    /// VRCs are grouped by pair of DIDs. A pair joins its DIDs into one component unless a VRC
+    /// between them is witnessed from outside the components of both, components grow until
+    /// every pair between two of them is witnessed from outside so members can't split a
+    /// component by witnessing each other. Pairs only one side vouches for, neither reciprocated
+    /// nor witnessed, never join components: anyone can issue a VRC about an honest DID.
+    ///
+    /// An honest DID vouching for a sybil still joins both components, so clusters are peeled
+    /// out of each component: its densest group of members is checked, removed, and the rest of
+    /// the component checked again.
+    fn flag_clusters(&self, config: &SybilConfig, report: &mut SybilReport) {
+        let vrcs: Vec<&TrustEdge> = self
+            .edges()
+            .filter(|edge| {
+                edge.type_() == DTGCredentialType::Relationship
+                    && edge.from() != edge.to()
+                    && edge.is_valid_at(config.at)
+            })
+            .collect();
+
+        let mut pairs: BTreeMap<(&str, &str), Pair> = BTreeMap::new();
+        for vrc in &vrcs {
+            let key = (vrc.from().min(vrc.to()), vrc.from().max(vrc.to()));
+            let pair = pairs.entry(key).or_default();
+            if vrc.from() == key.0 {
+                pair.forward = true;
+            } else {
+                pair.backward = true;
+            }
+            pair.witnesses.extend(
+                self.third_party_witnesses(vrc, config.at)
+                    .into_iter()
+                    .map(|witness| witness.from()),
+            );
+        }
+        let vouched: Vec<(&(&str, &str), &Pair)> =
+            pairs.iter().filter(|(_, pair)| pair.vouched()).collect();
+
+        let mut components = Components::new(self.nodes());
+        loop {
+            let mut changed = false;
+            for ((a, b), pair) in &vouched {
+                let (a_root, b_root) = (components.find(a), components.find(b));
+                if a_root == b_root {
+                    continue;
+                }
+                let external = pair.witnesses.iter().any(|witness| {
+                    let witness = components.find(witness);
+                    witness != a_root && witness != b_root
+                });
+                if !external {
+                    components.union(a, b);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let mut grouped: BTreeMap<usize, BTreeSet<&str>> = BTreeMap::new();
+        for ((a, b), _) in &vouched {
+            for did in [*a, *b] {
+                grouped.entry(components.find(did)).or_default().insert(did);
+            }
+        }
+
+        let min_size = config.min_cluster_size.max(2);
+        for mut remaining in grouped.into_values() {
+            while remaining.len() >= min_size {
+                let members = densest(&remaining, &vouched);
+                remaining.retain(|did| !members.contains(did));
+                if members.len() >= min_size {
+                    self.check_cluster(members, &vrcs, config, report);
+                }
+            }
+        }
+    }
+
+    /// Flags the members if they are dense and few of their VRCs to non-members are witnessed
+    /// by non-members
+    fn check_cluster(
+        &self,
+        members: BTreeSet<&str>,
+        vrcs: &[&TrustEdge],
+        config: &SybilConfig,
+        report: &mut SybilReport,
+    ) {
+        let size = members.len();
+        let mut pairs = BTreeSet::new();
+        let mut credentials = Vec::new();
+        let mut external_witnessed = Vec::new();
+        for vrc in vrcs {
+            match (members.contains(vrc.from()), members.contains(vrc.to())) {
+                (true, true) => {
+                    pairs.insert((vrc.from().min(vrc.to()), vrc.from().max(vrc.to())));
+                    credentials.push(vrc.digest().to_string());
+                }
+                (true, false) | (false, true) => {
+                    if self
+                        .third_party_witnesses(vrc, config.at)
+                        .iter()
+                        .any(|witness| !members.contains(witness.from()))
+                    {
+                        external_witnessed.push(vrc.digest().to_string());
+                    }
+                }
+                (false, false) => {}
+            }
+        }
+
+        let density = pairs.len() as f64 / (size * (size - 1) / 2) as f64;
+        if density < config.min_density
+            || external_witnessed.len() as f64 >= config.min_external_witnessed * size as f64
+        {
+            return;
+        }
+
+        let cluster = report.clusters.len();
+        for member in &members {
+            report.flag(member, SybilFlag::DenseCluster { cluster });
+        }
+        report.clusters.push(SuspiciousCluster {
+            members: members.into_iter().map(str::to_string).collect(),
+            density,
+            external_witnessed,
+            credentials,
+        });
+    }
+
+    fn flag_personhood(&self, config: &SybilConfig, report: &mut SybilReport) {
+        let mut issued: BTreeMap<&str, Vec<&TrustEdge>> = BTreeMap::new();
+        for edge in self.edges().filter(|edge| {
+            edge.credential().is_personhood_credential()
+                && edge.from() != edge.to()
+                && edge.is_valid_at(config.at)
+        }) {
+            issued.entry(edge.from()).or_default().push(edge);
+        }
+
+        let flagged: BTreeSet<&str> = issued
+            .iter()
+            .filter(|(issuer, phcs)| {
+                !config.trusted_issuers.contains(**issuer)
+                    && phcs
+                        .iter()
+                        .map(|phc| phc.to())
+                        .collect::<BTreeSet<_>>()
+                        .len()
+                        > config.max_personhood_credentials
+            })
+            .map(|(issuer, _)| *issuer)
+            .collect();
+
+        let mut held: BTreeMap<&str, Vec<&TrustEdge>> = BTreeMap::new();
+        for (issuer, phcs) in &issued {
+            if flagged.contains(issuer) {
+                report.flag(
+                    issuer,
+                    SybilFlag::PersonhoodIssuer {
+                        credentials: phcs.iter().map(|phc| phc.digest().to_string()).collect(),
+                    },
+                );
+            }
+            for phc in phcs {
+                held.entry(phc.to()).or_default().push(phc);
+            }
+        }
+
+        for (subject, phcs) in held {
+            if phcs.iter().all(|phc| flagged.contains(phc.from())) {
+                report.flag(
+                    subject,
+                    SybilFlag::FlaggedPersonhood {
+                        credentials: phcs.iter().map(|phc| phc.digest().to_string()).collect(),
+                    },
+                );
+            }
+        }
+    }
+
+    /// A session is one meeting, the VWCs a witness issued for it should cover a single pair of
+    /// DIDs: the subjects of the VWCs and the issuer and subject of the credentials they
+    /// witnessed. Sessions are per witness, other witnesses may use the same id.
+    fn flag_sessions(&self, config: &SybilConfig, report: &mut SybilReport) {
+        let mut sessions: BTreeMap<(&str, &str), Vec<&TrustEdge>> = BTreeMap::new();
+        for edge in self.edges().filter(|edge| edge.is_valid_at(config.at)) {
+            if let CredentialSubject::Witness(subject) =
+                &edge.credential().credential().credential_subject
+                && let Some(session_id) = subject
+                    .witness_context
+                    .as_ref()
+                    .and_then(|context| context.session_id.as_deref())
+            {
+                sessions
+                    .entry((edge.from(), session_id))
+                    .or_default()
+                    .push(edge);
+            }
+        }
+
+        for ((witness, session_id), vwcs) in sessions {
+            let mut participants = BTreeSet::new();
+            for vwc in &vwcs {
+                participants.insert(vwc.to());
+                if let Some(witnessed) = vwc.witnessed().and_then(|digest| self.get(digest)) {
+                    participants.insert(witnessed.from());
+                    participants.insert(witnessed.to());
+                }
+            }
+            if participants.len() <= 2 {
+                continue;
+            }
+
+            report.flag(
+                witness,
+                SybilFlag::SessionReuse {
+                    session_id: session_id.to_string(),
+                    credentials: vwcs.iter().map(|vwc| vwc.digest().to_string()).collect(),
+                },
+            );
+        }
+    }
+}
+
+#[cfg(all(test, feature = "affinidi-signing"))]
+mod tests {
+    use super::{SybilConfig, SybilFlag};
+    use crate::{
+        graph::TrustGraph,
+        test_utils::{did, vmc, vrc, vwc},
+    };
+
+    /// A ring of four honest DIDs witnessing each other's VRCs, attached to a fully connected
+    /// group of four sybils by a single VRC that bob witnessed
+    fn graph() -> TrustGraph {
+        let mut graph = TrustGraph::new();
+        for (from, to, witness) in [
+            ("alice", "bob", "carol"),
+            ("bob", "carol", "dave"),
+            ("carol", "dave", "alice"),
+            ("dave", "alice", "bob"),
+            ("sybil1", "alice", "bob"),
+        ] {
+            let digest = graph.add(vrc(from, to)).unwrap();
+            graph.add(vwc(witness, to, &digest, None)).unwrap();
+        }
+
+        let sybils = ["sybil1", "sybil2", "sybil3", "sybil4"];
+        for (i, from) in sybils.iter().enumerate() {
+            for to in &sybils[i + 1..] {
+                graph.add(vrc(to, from)).unwrap();
+                let digest = graph.add(vrc(from, to)).unwrap();
+                // Sybils witnessing each other stay in the same cluster
+                if *from == "sybil1" && *to == "sybil2" {
+                    graph.add(vwc("sybil3", to, &digest, None)).unwrap();
+                }
+            }
+        }
+        graph
+    }
+
+    #[test]
+    fn test_dense_clusters() {
+        let graph = graph();
+        let report = graph.sybil_report(&SybilConfig::new());
+
+        assert_eq!(report.clusters.len(), 1);
+        let cluster = &report.clusters[0];
+        assert_eq!(
+            cluster.members,
+            vec![did("sybil1"), did("sybil2"), did("sybil3"), did("sybil4")]
+        );
+        assert_eq!(cluster.density, 1.0);
+        assert_eq!(cluster.credentials.len(), 12);
+        assert_eq!(cluster.external_witnessed.len(), 1);
+
+        assert_eq!(
+            report.get(&did("sybil3")).unwrap().flags,
+            vec![SybilFlag::DenseCluster { cluster: 0 }]
+        );
+        for honest in ["alice", "bob", "carol", "dave"] {
+            assert!(!report.is_flagged(&did(honest)));
+        }
+        assert_eq!(report.flagged().count(), 4);
+
+        // One externally witnessed VRC for every four members is enough
+        let report = graph.sybil_report(&SybilConfig::new().with_min_external_witnessed(0.25));
+        assert!(report.clusters.is_empty());
+    }
+
+    #[test]
+    fn test_cluster_bridged_to_honest_region() {
+        // Unwitnessed friendships along a chain of five honest DIDs
+        let mut graph = TrustGraph::new();
+        let honest = ["alice", "bob", "carol", "dave", "erin"];
+        for pair in honest.windows(2) {
+            graph.add(vrc(pair[0], pair[1])).unwrap();
+            graph.add(vrc(pair[1], pair[0])).unwrap();
+        }
+        let sybils = ["sybil1", "sybil2", "sybil3", "sybil4"];
+        for (i, from) in sybils.iter().enumerate() {
+            for to in &sybils[i + 1..] {
+                graph.add(vrc(from, to)).unwrap();
+                graph.add(vrc(to, from)).unwrap();
+            }
+        }
+        let sybil_members: Vec<String> = sybils.iter().map(|sybil| did(sybil)).collect();
+        let report = graph.sybil_report(&SybilConfig::new());
+        assert_eq!(report.clusters.len(), 1);
+        assert_eq!(report.clusters[0].members, sybil_members);
+
+        // A VRC only the sybil vouches for doesn't pull alice into the cluster
+        graph.add(vrc("sybil1", "alice")).unwrap();
+        let report = graph.sybil_report(&SybilConfig::new());
+        assert_eq!(report.clusters.len(), 1);
+        assert_eq!(report.clusters[0].members, sybil_members);
+
+        // Neither does alice befriending a sybil, the cluster is peeled out of the component
+        graph.add(vrc("alice", "sybil2")).unwrap();
+        graph.add(vrc("sybil2", "alice")).unwrap();
+        let report = graph.sybil_report(&SybilConfig::new());
+        assert_eq!(report.clusters.len(), 1);
+        assert_eq!(report.clusters[0].members, sybil_members);
+        for name in honest {
+            assert!(!report.is_flagged(&did(name)));
+        }
+    }
+
+    #[test]
+    fn test_personhood_issuers() {
+        let mut graph = TrustGraph::new();
+        for subject in ["sybil1", "sybil2", "sybil3", "sybil4"] {
+            graph.add(vmc("factory", subject, true)).unwrap();
+        }
+        graph.add(vmc("registry", "alice", true)).unwrap();
+        graph.add(vmc("registry", "sybil1", true)).unwrap();
+
+        let report = graph.sybil_report(&SybilConfig::new().with_max_personhood_credentials(3));
+        assert!(matches!(
+            &report.get(&did("factory")).unwrap().flags[..],
+            [SybilFlag::PersonhoodIssuer { credentials }] if credentials.len() == 4
+        ));
+        for subject in ["sybil2", "sybil3", "sybil4"] {
+            assert!(matches!(
+                &report.get(&did(subject)).unwrap().flags[..],
+                [SybilFlag::FlaggedPersonhood { credentials }] if credentials.len() == 1
+            ));
+        }
+        // Also vouched for by the registry
+        assert!(!report.is_flagged(&did("sybil1")));
+        assert!(!report.is_flagged(&did("registry")));
+        assert!(!report.is_flagged(&did("alice")));
+
+        let report = graph.sybil_report(
+            &SybilConfig::new()
+                .with_max_personhood_credentials(3)
+                .with_trusted_issuer(&did("factory")),
+        );
+        assert!(report.reports.is_empty());
+    }
+
+    #[test]
+    fn test_session_reuse() {
+        let mut graph = TrustGraph::new();
+        let alice_bob = graph.add(vrc("alice", "bob")).unwrap();
+        let bob_alice = graph.add(vrc("bob", "alice")).unwrap();
+        let carol_dave = graph.add(vrc("carol", "dave")).unwrap();
+
+        // One meeting witnessed by two witnesses in both directions
+        graph
+            .add(vwc("erin", "bob", &alice_bob, Some("meeting")))
+            .unwrap();
+        graph
+            .add(vwc("frank", "alice", &bob_alice, Some("meeting")))
+            .unwrap();
+        assert!(graph.sybil_report(&SybilConfig::new()).reports.is_empty());
+
+        // Erin reuses the session for another pair
+        let reused = graph
+            .add(vwc("erin", "dave", &carol_dave, Some("meeting")))
+            .unwrap();
+        let report = graph.sybil_report(&SybilConfig::new());
+        let SybilFlag::SessionReuse {
+            session_id,
+            credentials,
+        } = &report.get(&did("erin")).unwrap().flags[0]
+        else {
+            panic!("expected session reuse");
+        };
+        assert_eq!(session_id, "meeting");
+        assert_eq!(credentials.len(), 2);
+        assert!(credentials.contains(&reused));
+        assert!(!report.is_flagged(&did("alice")));
+
+        // Frank used the same id for a single meeting
+        assert!(!report.is_flagged(&did("frank")));
+    }
+}
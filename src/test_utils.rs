@@ -0,0 +1,62 @@
+/*!
+*   Signed credentials shared by the tests.
+*/
+
+use crate::{DTGCredential, WitnessContext};
+use affinidi_secrets_resolver::secrets::Secret;
+use chrono::{Duration, Utc};
+
+/// did:example DID for a name
+pub(crate) fn did(name: &str) -> String {
+    format!("did:example:{name}")
+}
+
+/// Signs a credential with a new Ed25519 key
+pub(crate) fn signed(mut credential: DTGCredential) -> DTGCredential {
+    credential
+        .sign(&Secret::generate_ed25519(None, None), None)
+        .unwrap();
+    credential
+}
+
+/// Signed VRC valid since yesterday
+pub(crate) fn vrc(from: &str, to: &str) -> DTGCredential {
+    signed(DTGCredential::new_vrc(
+        did(from),
+        did(to),
+        Utc::now() - Duration::days(1),
+        None,
+    ))
+}
+
+/// Signed VMC valid since yesterday
+pub(crate) fn vmc(community: &str, member: &str, personhood: bool) -> DTGCredential {
+    signed(DTGCredential::new_vmc(
+        did(community),
+        did(member),
+        Utc::now() - Duration::days(1),
+        None,
+        personhood,
+    ))
+}
+
+/// Signed VWC of the credential with this digest, valid since yesterday
+pub(crate) fn vwc(
+    witness: &str,
+    subject: &str,
+    digest: &str,
+    session_id: Option<&str>,
+) -> DTGCredential {
+    signed(DTGCredential::new_vwc(
+        did(witness),
+        did(subject),
+        Utc::now() - Duration::days(1),
+        None,
+        Some(digest.to_string()),
+        session_id.map(|session_id| WitnessContext {
+            event: None,
+            session_id: Some(session_id.to_string()),
+            method: None,
+        }),
+    ))
+}
@@ -0,0 +1,736 @@
+/*!
+*   Conversion between jCard and vCard 4.0 text (RFC 6350), following the mapping in RFC 7095.
+*
+*   <https://www.rfc-editor.org/rfc/rfc6350>
+*
+*   Handles line folding, text escaping, property groups, parameters (including RFC 6868 caret
+*   encoding) and the basic/extended date and time formats. Comma separated parameter values,
+*   quoted or not, become arrays, e.g. `TYPE="work,voice"` is `["work", "voice"]`. A `VALUE`
+*   parameter is written only when a property doesn't use its default value type, so
+*   conversions round trip.
+*/
+
+use crate::{
+    CredentialSubject, DTGCredential, DTGCredentialError,
+    jcard::{JCard, JCardProperty, JCardValueType},
+};
+use serde_json::{Map, Number, Value};
+
+/// Maximum line length in octets before folding
+const MAX_LINE_OCTETS: usize = 75;
+
+/// Properties with structured values, components are separated by `;`
+const STRUCTURED: [&str; 5] = ["n", "adr", "org", "gender", "clientpidmap"];
+
+/// Properties that hold a list of text values, separated by `,`
+const MULTI_VALUED: [&str; 2] = ["nickname", "categories"];
+
+fn vcard_error(msg: impl Into<String>) -> DTGCredentialError {
+    DTGCredentialError::VCard(msg.into())
+}
+
+/// Value type of a property without a `VALUE` parameter
+fn default_value_type(name: &str) -> JCardValueType {
+    match name {
+        "version" | "fn" | "n" | "nickname" | "gender" | "adr" | "tel" | "email" | "tz"
+        | "title" | "role" | "org" | "categories" | "note" | "prodid" | "kind" | "xml"
+        | "clientpidmap" => JCardValueType::Text,
+        "source" | "photo" | "impp" | "geo" | "logo" | "member" | "sound" | "uid" | "url"
+        | "key" | "fburl" | "caladruri" | "caluri" | "related" => JCardValueType::Uri,
+        "bday" | "anniversary" => JCardValueType::DateAndOrTime,
+        "rev" => JCardValueType::Timestamp,
+        "lang" => JCardValueType::LanguageTag,
+        _ => JCardValueType::Unknown,
+    }
+}
+
+/// Splits on sep, ignoring separators escaped with a backslash
+fn split_escaped(value: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (index, c) in value.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == sep {
+            parts.push(&value[start..index]);
+            start = index + 1;
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+/// Splits on sep, ignoring separators inside double quotes or escaped with a backslash
+fn split_unquoted(value: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (index, c) in value.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            quoted = !quoted;
+        } else if c == sep && !quoted {
+            parts.push(&value[start..index]);
+            start = index + 1;
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') | Some('N') => result.push('\n'),
+                Some(c) => result.push(c),
+                None => result.push('\\'),
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+fn escape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            ',' => result.push_str("\\,"),
+            ';' => result.push_str("\\;"),
+            '\n' => result.push_str("\\n"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// RFC 6868 parameter value decoding
+fn decode_parameter(value: &str) -> String {
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value);
+
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '^' {
+            match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('\'') => result.push('"'),
+                Some('^') => result.push('^'),
+                Some(c) => {
+                    result.push('^');
+                    result.push(c);
+                }
+                None => result.push('^'),
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// RFC 6868 parameter value encoding, quoted when required
+/// Commas are escaped with a backslash as they separate the values of a parameter
+fn encode_parameter(value: &str) -> String {
+    let encoded = value
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace('^', "^^")
+        .replace('\n', "^n")
+        .replace('"', "^'");
+    if encoded.contains([':', ';']) {
+        format!("\"{encoded}\"")
+    } else {
+        encoded
+    }
+}
+
+/// Values of a parameter, split on commas that aren't escaped, quoted or not
+fn parse_parameter(value: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    for part in split_unquoted(value, ',') {
+        let decoded = decode_parameter(part);
+        for value in split_escaped(&decoded, ',') {
+            let mut result = String::with_capacity(value.len());
+            let mut chars = value.chars();
+            while let Some(c) = chars.next() {
+                if c != '\\' {
+                    result.push(c);
+                    continue;
+                }
+                // Other backslashes are kept as they are
+                match chars.next() {
+                    Some(c @ ('\\' | ',')) => result.push(c),
+                    Some(c) => {
+                        result.push('\\');
+                        result.push(c);
+                    }
+                    None => result.push('\\'),
+                }
+            }
+            values.push(result);
+        }
+    }
+    values
+}
+
+fn is_date_type(value_type: &JCardValueType) -> bool {
+    matches!(
+        value_type,
+        JCardValueType::Date
+            | JCardValueType::Time
+            | JCardValueType::DateTime
+            | JCardValueType::DateAndOrTime
+            | JCardValueType::Timestamp
+    )
+}
+
+/// Basic format date (vCard) to extended format (jCard)
+fn date_to_extended(date: &str) -> String {
+    let digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if date.starts_with("---") {
+        date.to_string()
+    } else if let Some(month_day) = date.strip_prefix("--") {
+        if month_day.len() == 4 && digits(month_day) {
+            format!("--{}-{}", &month_day[..2], &month_day[2..])
+        } else {
+            date.to_string()
+        }
+    } else if date.len() == 8 && digits(date) {
+        format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..])
+    } else {
+        date.to_string()
+    }
+}
+
+/// Basic format time (vCard) to extended format (jCard)
+/// Returns None if the UTC offset isn't `Z`, `+hh` or `+hhmm`
+fn time_to_extended(time: &str) -> Option<String> {
+    let leading = time.chars().take_while(|c| *c == '-').count();
+    let rest = &time[leading..];
+    let (digits, zone) = rest.split_at(rest.find(['Z', '+', '-']).unwrap_or(rest.len()));
+
+    let valid_zone = match zone.strip_prefix(['+', '-']) {
+        Some(offset) => matches!(offset.len(), 2 | 4) && offset.chars().all(|c| c.is_ascii_digit()),
+        None => zone.is_empty() || zone == "Z",
+    };
+    if !valid_zone {
+        return None;
+    }
+
+    if digits.is_empty() || digits.len() % 2 != 0 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Some(time.to_string());
+    }
+
+    let pairs = digits
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| String::from_utf8_lossy(pair).to_string())
+        .collect::<Vec<String>>()
+        .join(":");
+    let zone = if zone.len() == 5 {
+        format!("{}:{}", &zone[..3], &zone[3..])
+    } else {
+        zone.to_string()
+    };
+
+    Some(format!("{}{pairs}{zone}", "-".repeat(leading)))
+}
+
+fn to_extended(value: &str, value_type: &JCardValueType) -> Option<String> {
+    if *value_type == JCardValueType::Time {
+        return time_to_extended(value);
+    }
+    match value.split_once('T') {
+        Some((date, time)) => Some(format!(
+            "{}T{}",
+            date_to_extended(date),
+            time_to_extended(time)?
+        )),
+        None => Some(date_to_extended(value)),
+    }
+}
+
+fn to_basic(value: &str, value_type: &JCardValueType) -> String {
+    let date_to_basic = |date: &str| {
+        // year-month is the same in both formats
+        if date.len() == 7 && date.as_bytes()[4] == b'-' {
+            return date.to_string();
+        }
+        let leading = date.chars().take_while(|c| *c == '-').count();
+        format!(
+            "{}{}",
+            "-".repeat(leading),
+            date[leading..].replace('-', "")
+        )
+    };
+
+    if *value_type == JCardValueType::Time {
+        return value.replace(':', "");
+    }
+    match value.split_once('T') {
+        Some((date, time)) => format!("{}T{}", date_to_basic(date), time.replace(':', "")),
+        None => date_to_basic(value),
+    }
+}
+
+/// Parses a text value, splitting structured and multi-valued properties
+fn parse_text(name: &str, raw: &str) -> Vec<Value> {
+    if STRUCTURED.contains(&name) {
+        let mut components: Vec<Value> = split_escaped(raw, ';')
+            .into_iter()
+            .map(|component| match split_escaped(component, ',').as_slice() {
+                [item] => Value::String(unescape(item)),
+                items => Value::Array(
+                    items
+                        .iter()
+                        .map(|item| Value::String(unescape(item)))
+                        .collect(),
+                ),
+            })
+            .collect();
+        if components.len() == 1 && components[0].is_string() {
+            vec![components.remove(0)]
+        } else {
+            vec![Value::Array(components)]
+        }
+    } else if MULTI_VALUED.contains(&name) {
+        split_escaped(raw, ',')
+            .into_iter()
+            .map(|value| Value::String(unescape(value)))
+            .collect()
+    } else {
+        vec![Value::String(unescape(raw))]
+    }
+}
+
+fn write_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => escape(text),
+        Value::Array(components) => components
+            .iter()
+            .map(|component| match component {
+                Value::Array(items) => items.iter().map(write_text).collect::<Vec<_>>().join(","),
+                component => write_text(component),
+            })
+            .collect::<Vec<String>>()
+            .join(";"),
+        other => other.to_string(),
+    }
+}
+
+fn parse_values(
+    name: &str,
+    value_type: &JCardValueType,
+    raw: &str,
+) -> Result<Vec<Value>, DTGCredentialError> {
+    let invalid = || vcard_error(format!("Invalid {value_type} value for {name}: {raw}"));
+
+    Ok(match value_type {
+        JCardValueType::Text => parse_text(name, raw),
+        JCardValueType::Boolean => match raw.to_lowercase().as_str() {
+            "true" => vec![Value::Bool(true)],
+            "false" => vec![Value::Bool(false)],
+            _ => return Err(invalid()),
+        },
+        JCardValueType::Integer => raw
+            .split(',')
+            .map(|v| v.parse::<i64>().map(Value::from).map_err(|_| invalid()))
+            .collect::<Result<Vec<Value>, _>>()?,
+        JCardValueType::Float => raw
+            .split(',')
+            .map(|v| {
+                v.parse::<f64>()
+                    .ok()
+                    .and_then(Number::from_f64)
+                    .map(Value::Number)
+                    .ok_or_else(invalid)
+            })
+            .collect::<Result<Vec<Value>, _>>()?,
+        JCardValueType::UtcOffset => {
+            vec![Value::String(time_to_extended(raw).ok_or_else(invalid)?)]
+        }
+        value_type if is_date_type(value_type) => raw
+            .split(',')
+            .map(|v| {
+                to_extended(v, value_type)
+                    .map(Value::String)
+                    .ok_or_else(invalid)
+            })
+            .collect::<Result<Vec<Value>, _>>()?,
+        _ => vec![Value::String(raw.to_string())],
+    })
+}
+
+fn write_values(property: &JCardProperty) -> String {
+    let value_type = &property.value_type;
+    property
+        .values
+        .iter()
+        .map(|value| match (value_type, value) {
+            (JCardValueType::Text, value) => write_text(value),
+            (JCardValueType::Boolean, Value::Bool(b)) => {
+                if *b { "TRUE" } else { "FALSE" }.to_string()
+            }
+            (JCardValueType::UtcOffset, Value::String(offset)) => offset.replace(':', ""),
+            (value_type, Value::String(value)) if is_date_type(value_type) => {
+                to_basic(value, value_type)
+            }
+            (_, Value::String(value)) => value.clone(),
+            (_, value) => value.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+fn parse_line(line: &str) -> Result<JCardProperty, DTGCredentialError> {
+    let parts = split_unquoted(line, ':');
+    if parts.len() < 2 {
+        return Err(vcard_error(format!("Invalid content line: {line}")));
+    }
+    let head = parts[0];
+    // Any further colons belong to the value
+    let raw = &line[head.len() + 1..];
+
+    let mut head = split_unquoted(head, ';').into_iter();
+    let name = head.next().unwrap_or_default().to_lowercase();
+    let (group, name) = match name.split_once('.') {
+        Some((group, name)) => (Some(group.to_string()), name.to_string()),
+        None => (None, name),
+    };
+    if name.is_empty() {
+        return Err(vcard_error(format!("Missing property name: {line}")));
+    }
+
+    let mut parameters = Map::new();
+    if let Some(group) = group {
+        parameters.insert("group".to_string(), Value::String(group));
+    }
+    let mut value_type = default_value_type(&name);
+    for parameter in head {
+        let Some((key, value)) = parameter.split_once('=') else {
+            return Err(vcard_error(format!("Invalid parameter: {parameter}")));
+        };
+        let key = key.to_lowercase();
+        if key == "value" {
+            value_type = JCardValueType::from(value.to_lowercase().as_str());
+            continue;
+        }
+        let mut values: Vec<Value> = parse_parameter(value)
+            .into_iter()
+            .map(Value::String)
+            .collect();
+        parameters.insert(
+            key,
+            if values.len() == 1 {
+                values.remove(0)
+            } else {
+                Value::Array(values)
+            },
+        );
+    }
+
+    Ok(JCardProperty {
+        values: parse_values(&name, &value_type, raw)?,
+        name,
+        parameters,
+        value_type,
+    })
+}
+
+fn write_line(property: &JCardProperty) -> String {
+    let mut line = String::new();
+    if let Some(group) = property.parameters.get("group").and_then(|g| g.as_str()) {
+        line.push_str(group);
+        line.push('.');
+    }
+    line.push_str(&property.name.to_uppercase());
+
+    if property.value_type != default_value_type(&property.name) {
+        line.push_str(";VALUE=");
+        line.push_str(&property.value_type.to_string().to_uppercase());
+    }
+    for (key, value) in &property.parameters {
+        if key == "group" {
+            continue;
+        }
+        let values = match value {
+            Value::Array(values) => values
+                .iter()
+                .map(|v| encode_parameter(v.as_str().unwrap_or_default()))
+                .collect::<Vec<String>>(),
+            Value::String(value) => vec![encode_parameter(value)],
+            value => vec![encode_parameter(&value.to_string())],
+        };
+        line.push(';');
+        line.push_str(&key.to_uppercase());
+        line.push('=');
+        line.push_str(&values.join(","));
+    }
+
+    line.push(':');
+    line.push_str(&write_values(property));
+    line
+}
+
+/// Folds a content line at 75 octets without splitting UTF-8 characters
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 3);
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+impl JCard {
+    /// Serialize as vCard 4.0 text
+    /// VERSION:4.0 is always written first, as required by RFC 6350
+    pub fn to_vcard(&self) -> String {
+        let mut vcard = String::from("BEGIN:VCARD\r\nVERSION:4.0\r\n");
+        for property in self.properties().iter().filter(|p| p.name != "version") {
+            vcard.push_str(&fold(&write_line(property)));
+        }
+        vcard.push_str("END:VCARD\r\n");
+        vcard
+    }
+
+    /// Parse vCard 4.0 text containing a single vCard
+    pub fn from_vcard(text: &str) -> Result<JCard, DTGCredentialError> {
+        let mut cards = JCard::from_vcards(text)?;
+        if cards.len() != 1 {
+            return Err(vcard_error(format!(
+                "Expected a single vCard, found {}",
+                cards.len()
+            )));
+        }
+        Ok(cards.remove(0))
+    }
+
+    /// Parse vCard 4.0 text containing any number of vCards, e.g. a `.vcf` export
+    pub fn from_vcards(text: &str) -> Result<Vec<JCard>, DTGCredentialError> {
+        let unfolded = text
+            .replace("\r\n", "\n")
+            .replace("\n ", "")
+            .replace("\n\t", "");
+
+        let mut cards = Vec::new();
+        let mut current: Option<JCard> = None;
+        for line in unfolded.lines().filter(|l| !l.trim().is_empty()) {
+            if line.eq_ignore_ascii_case("BEGIN:VCARD") {
+                if current.is_some() {
+                    return Err(vcard_error("Nested BEGIN:VCARD"));
+                }
+                current = Some(JCard::default());
+            } else if line.eq_ignore_ascii_case("END:VCARD") {
+                let card = current
+                    .take()
+                    .ok_or_else(|| vcard_error("END:VCARD without BEGIN:VCARD"))?;
+                // Validates the RFC 7095 structure and version
+                cards.push(JCard::try_from(&card.to_value())?);
+            } else {
+                current
+                    .as_mut()
+                    .ok_or_else(|| vcard_error("Content outside of BEGIN:VCARD"))?
+                    .add_property(parse_line(line)?);
+            }
+        }
+
+        if current.is_some() {
+            return Err(vcard_error("Missing END:VCARD"));
+        }
+        Ok(cards)
+    }
+}
+
+impl DTGCredential {
+    /// Export an RCard as vCard 4.0 text
+    /// Returns None if this is not an RCard
+    pub fn to_vcard(&self) -> Result<Option<String>, DTGCredentialError> {
+        match &self.credential.credential_subject {
+            CredentialSubject::RCard(subject) => {
+                Ok(Some(JCard::try_from(&subject.card)?.to_vcard()))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DTGCredential, jcard::JCard};
+    use chrono::Utc;
+    use serde_json::json;
+
+    const RFC6350_EXAMPLE: &str = "BEGIN:VCARD\r\n\
+        VERSION:4.0\r\n\
+        FN:Simon Perreault\r\n\
+        N:Perreault;Simon;;;ing. jr,M.Sc.\r\n\
+        BDAY:--0203\r\n\
+        ANNIVERSARY:20090808T1430-0500\r\n\
+        GENDER:M\r\n\
+        LANG;PREF=1:fr\r\n\
+        LANG;PREF=2:en\r\n\
+        ORG;TYPE=work:Viagenie\r\n\
+        ADR;TYPE=work:;Suite D2-630;2875 Laurier;Quebec;QC;G1V 2M2;Canada\r\n\
+        TEL;VALUE=URI;TYPE=\"work,voice\";PREF=1:tel:+1-418-656-9254;ext=102\r\n\
+        EMAIL;TYPE=work:simon.perreault@viagenie.ca\r\n\
+        GEO;TYPE=work:geo:46.772673,-71.282945\r\n\
+        KEY;TYPE=work:http://www.viagenie.ca/simon.perreault/simon.asc\r\n\
+        TZ:-0500\r\n\
+        URL;TYPE=home:http://nomis80.org\r\n\
+        END:VCARD\r\n";
+
+    #[test]
+    fn test_from_vcard() {
+        let card = JCard::from_vcard(RFC6350_EXAMPLE).unwrap();
+
+        assert_eq!(card.full_name(), Some("Simon Perreault"));
+        assert_eq!(card.email(), Some("simon.perreault@viagenie.ca"));
+        assert_eq!(card.tel(), Some("tel:+1-418-656-9254;ext=102"));
+        assert_eq!(card.org(), Some(vec!["Viagenie"]));
+
+        let value = card.to_value();
+        assert_eq!(
+            value[1][2],
+            json!([
+                "n",
+                {},
+                "text",
+                ["Perreault", "Simon", "", "", ["ing. jr", "M.Sc."]]
+            ])
+        );
+        assert_eq!(
+            value[1][3],
+            json!(["bday", {}, "date-and-or-time", "--02-03"])
+        );
+        assert_eq!(
+            value[1][4],
+            json!([
+                "anniversary",
+                {},
+                "date-and-or-time",
+                "2009-08-08T14:30-05:00"
+            ])
+        );
+        assert_eq!(
+            value[1][10],
+            json!(["tel", {"type": ["work", "voice"], "pref": "1"}, "uri", "tel:+1-418-656-9254;ext=102"])
+        );
+        assert_eq!(
+            value[1][9],
+            json!(["adr", {"type": "work"}, "text",
+                ["", "Suite D2-630", "2875 Laurier", "Quebec", "QC", "G1V 2M2", "Canada"]])
+        );
+
+        // Lossless round trip
+        assert_eq!(JCard::from_vcard(&card.to_vcard()).unwrap(), card);
+    }
+
+    #[test]
+    fn test_escaping_and_folding() {
+        let value = json!(["vcard", [
+            ["version", {}, "text", "4.0"],
+            ["fn", {}, "text", "Smith, Alice; PhD \\ MD"],
+            ["note", {}, "text", "Line one\nLine two. ".repeat(10) + "Ünïcödé ✓ 日本語 ".repeat(5).as_str()],
+            ["nickname", {}, "text", "Ali", "Al,ice"],
+            ["x-custom", {"group": "item1", "x-param": "a:b\"c"}, "unknown", "raw;value,kept"],
+            ["x-list", {"x-param": ["one", "t,wo", "th\\ree"]}, "unknown", "list"],
+            ["x-flag", {}, "boolean", true],
+            ["x-count", {}, "integer", 42]
+        ]]);
+        let card = JCard::try_from(&value).unwrap();
+        let vcard = card.to_vcard();
+
+        assert!(vcard.contains("FN:Smith\\, Alice\\; PhD \\\\ MD\r\n"));
+        assert!(vcard.contains("NICKNAME:Ali,Al\\,ice\r\n"));
+        assert!(vcard.contains("item1.X-CUSTOM;X-PARAM=\"a:b^'c\":raw;value,kept\r\n"));
+        assert!(vcard.contains("X-LIST;X-PARAM=one,t\\,wo,th\\\\ree:list\r\n"));
+        assert!(vcard.contains("X-FLAG;VALUE=BOOLEAN:TRUE\r\n"));
+        assert!(vcard.contains("X-COUNT;VALUE=INTEGER:42\r\n"));
+        assert!(vcard.contains("Line one\\nLine two"));
+        for line in vcard.split("\r\n") {
+            assert!(line.len() <= 75, "{line}");
+        }
+
+        assert_eq!(JCard::from_vcard(&vcard).unwrap().to_value(), value);
+    }
+
+    #[test]
+    fn test_rcard_vcard() {
+        let card = JCard::from_vcard(
+            "BEGIN:VCARD\nVERSION:4.0\nFN:Alice Smith\nEMAIL:alice@example.com\nEND:VCARD\n",
+        )
+        .unwrap();
+        let rcard = DTGCredential::new_rcard(
+            "did:example:issuer".to_string(),
+            "did:example:subject".to_string(),
+            Utc::now(),
+            None,
+            card,
+        );
+
+        let vcard = rcard.to_vcard().unwrap().unwrap();
+        assert_eq!(
+            vcard,
+            "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:Alice Smith\r\nEMAIL:alice@example.com\r\nEND:VCARD\r\n"
+        );
+    }
+
+    #[test]
+    fn test_multiple_and_invalid() {
+        let cards = JCard::from_vcards(&RFC6350_EXAMPLE.repeat(2)).unwrap();
+        assert_eq!(cards.len(), 2);
+        assert!(JCard::from_vcard(&RFC6350_EXAMPLE.repeat(2)).is_err());
+
+        assert!(JCard::from_vcard("FN:No begin\r\n").is_err());
+        assert!(JCard::from_vcard("BEGIN:VCARD\r\nFN:No end\r\n").is_err());
+        assert!(JCard::from_vcard("BEGIN:VCARD\r\nVERSION:3.0\r\nEND:VCARD\r\n").is_err());
+        assert!(JCard::from_vcard("BEGIN:VCARD\r\nNO COLON\r\nEND:VCARD\r\n").is_err());
+        assert!(
+            JCard::from_vcard("BEGIN:VCARD\r\nX-FLAG;VALUE=BOOLEAN:maybe\r\nEND:VCARD\r\n")
+                .is_err()
+        );
+
+        // UTC offsets that aren't Z, +hh or +hhmm
+        assert!(
+            JCard::from_vcard(
+                "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:A\r\nANNIVERSARY:20090808T1430+1é1\r\nEND:VCARD\r\n"
+            )
+            .is_err()
+        );
+        assert!(
+            JCard::from_vcard(
+                "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:A\r\nTZ;VALUE=UTC-OFFSET:+05:00\r\nEND:VCARD\r\n"
+            )
+            .is_err()
+        );
+        assert!(
+            JCard::from_vcard(
+                "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:A\r\nANNIVERSARY:20090808T1430+0100\r\nEND:VCARD\r\n"
+            )
+            .is_ok()
+        );
+    }
+}
@@ -0,0 +1,598 @@
+/*!
+*   Holder wallet over stored DTG Credentials.
+*
+*   A [Wallet] holds the credentials of one DID in any [CredentialStore], selects those that
+*   satisfy a verifier's [PresentationRequest] and assembles them into a signed
+*   [DTGPresentation].
+*
+*   Only signed credentials valid at the time of the request are selected. When more credentials
+*   satisfy a requirement than were asked for, the wallet prefers:
+*   1. Plain VMCs over PHCs (VMCs flagged `PersonhoodCredential`) unless personhood is required
+*   2. Credentials witnessed by more VWCs, counting witnesses other than the issuer and subject
+*   3. The most recently issued
+*
+*   Requirements with the fewest candidates are filled first, and the wallet backtracks when a
+*   credential picked for one requirement is needed by another.
+*/
+
+use crate::{
+    DTGCredential, DTGCredentialError, DTGCredentialType,
+    graph::witnessed_digest,
+    presentation::{DTGPresentation, presentation_error},
+    signer::SigningKey,
+    store::{CredentialStore, MemoryStore, StoreQuery},
+};
+use chrono::{DateTime, Utc};
+use std::{cmp::Reverse, collections::BTreeSet};
+
+/// Credentials a verifier asks for
+#[derive(Clone, Debug)]
+pub struct CredentialRequirement {
+    /// Credential type
+    pub type_: DTGCredentialType,
+
+    /// Issued by one of these DIDs, any issuer if empty
+    pub issuers: Vec<String>,
+
+    /// DID the credentials are about, defaults to the holder
+    pub subject: Option<String>,
+
+    /// Must be a PHC
+    pub personhood: bool,
+
+    /// Must be witnessed, the VWCs are presented with the credentials
+    pub require_witness: bool,
+
+    /// Number of distinct credentials
+    pub count: usize,
+}
+
+impl CredentialRequirement {
+    /// One credential of this type about the holder
+    pub fn new(type_: DTGCredentialType) -> Self {
+        CredentialRequirement {
+            type_,
+            issuers: Vec::new(),
+            subject: None,
+            personhood: false,
+            require_witness: false,
+            count: 1,
+        }
+    }
+
+    /// Sets the DIDs that can issue the credentials
+    pub fn with_issuers(mut self, issuers: &[&str]) -> Self {
+        self.issuers = issuers.iter().map(|issuer| issuer.to_string()).collect();
+        self
+    }
+
+    /// Sets the DID the credentials are about
+    pub fn with_subject(mut self, subject: &str) -> Self {
+        self.subject = Some(subject.to_string());
+        self
+    }
+
+    /// Requires PHCs
+    pub fn with_personhood(mut self) -> Self {
+        self.personhood = true;
+        self
+    }
+
+    /// Requires witnessed credentials
+    pub fn with_witness_required(mut self) -> Self {
+        self.require_witness = true;
+        self
+    }
+
+    /// Sets the number of distinct credentials
+    pub fn with_count(mut self, count: usize) -> Self {
+        self.count = count;
+        self
+    }
+
+    /// Does the credential satisfy the requirement for this holder?
+    /// Validity, signatures and witnesses are checked by the [Wallet]
+    pub fn matches(&self, holder: &str, credential: &DTGCredential) -> bool {
+        credential.type_() == self.type_
+            && (self.issuers.is_empty() || self.issuers.iter().any(|i| i == credential.issuer()))
+            && credential.subject() == self.subject.as_deref().unwrap_or(holder)
+            && (!self.personhood || credential.is_personhood_credential())
+    }
+}
+
+/// A verifier's request for credentials
+#[derive(Clone, Debug)]
+pub struct PresentationRequest {
+    /// Every requirement must be satisfied
+    pub requirements: Vec<CredentialRequirement>,
+
+    /// Challenge the presentation proof must carry
+    pub challenge: Option<String>,
+
+    /// Domain the presentation proof must carry
+    pub domain: Option<String>,
+
+    /// Credentials must be valid at this time
+    pub at: DateTime<Utc>,
+}
+
+impl Default for PresentationRequest {
+    fn default() -> Self {
+        PresentationRequest {
+            requirements: Vec::new(),
+            challenge: None,
+            domain: None,
+            at: Utc::now(),
+        }
+    }
+}
+
+impl PresentationRequest {
+    /// Request without requirements, valid now
+    pub fn new() -> Self {
+        PresentationRequest::default()
+    }
+
+    /// Adds a requirement
+    pub fn with_requirement(mut self, requirement: CredentialRequirement) -> Self {
+        self.requirements.push(requirement);
+        self
+    }
+
+    /// Sets the challenge
+    pub fn with_challenge(mut self, challenge: &str) -> Self {
+        self.challenge = Some(challenge.to_string());
+        self
+    }
+
+    /// Sets the domain
+    pub fn with_domain(mut self, domain: &str) -> Self {
+        self.domain = Some(domain.to_string());
+        self
+    }
+
+    /// Sets the time credentials must be valid at
+    pub fn with_time(mut self, at: DateTime<Utc>) -> Self {
+        self.at = at;
+        self
+    }
+}
+
+/// Credentials held by a DID
+#[derive(Clone, Debug)]
+pub struct Wallet<S: CredentialStore = MemoryStore> {
+    holder: String,
+    store: S,
+}
+
+impl<S: CredentialStore> Wallet<S> {
+    /// holder: DID of the holder
+    /// store: Where the credentials are kept
+    pub fn new(holder: &str, store: S) -> Self {
+        Wallet {
+            holder: holder.to_string(),
+            store,
+        }
+    }
+
+    /// Returns the holder DID
+    pub fn holder(&self) -> &str {
+        &self.holder
+    }
+
+    /// The underlying store
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// The underlying store as mutable
+    pub fn store_mut(&mut self) -> &mut S {
+        &mut self.store
+    }
+
+    /// Adds a credential, returns its digest
+    pub fn add(&mut self, credential: &DTGCredential) -> Result<String, DTGCredentialError> {
+        self.store.insert(credential)
+    }
+
+    /// Adds a credential exactly as received, returns its digest
+    pub fn add_json(&mut self, json: &str) -> Result<String, DTGCredentialError> {
+        self.store.insert_json(json)
+    }
+
+    /// Credentials matching the query, ordered by digest
+    pub fn list(&self, query: &StoreQuery) -> Result<Vec<DTGCredential>, DTGCredentialError> {
+        self.store.query(query)
+    }
+
+    /// Valid VWCs of a credential, issued by someone other than its issuer and subject
+    pub fn witnesses(
+        &self,
+        credential: &DTGCredential,
+        at: DateTime<Utc>,
+    ) -> Result<Vec<DTGCredential>, DTGCredentialError> {
+        let digest = credential.digest()?;
+        Ok(self
+            .store
+            .query(
+                &StoreQuery::new()
+                    .with_type(DTGCredentialType::Witness)
+                    .with_signed(true),
+            )?
+            .into_iter()
+            .filter(|witness| {
+                witnessed_digest(witness) == Some(&digest)
+                    && witness.is_valid_at(at)
+                    && witness.issuer() != credential.issuer()
+                    && witness.issuer() != credential.subject()
+            })
+            .collect())
+    }
+
+    /// Picks the credentials that satisfy the request
+    /// Returns the credentials for each requirement in order, followed by the VWCs of
+    /// requirements that need a witness. A credential is only used once.
+    pub fn select(
+        &self,
+        request: &PresentationRequest,
+    ) -> Result<Vec<DTGCredential>, DTGCredentialError> {
+        let mut candidates = Vec::with_capacity(request.requirements.len());
+        for (index, requirement) in request.requirements.iter().enumerate() {
+            let mut matching = Vec::new();
+            for credential in self.store.query(
+                &StoreQuery::new()
+                    .with_type(requirement.type_.clone())
+                    .with_signed(true),
+            )? {
+                if !credential.is_valid_at(request.at)
+                    || !requirement.matches(&self.holder, &credential)
+                {
+                    continue;
+                }
+                let witnesses = self.witnesses(&credential, request.at)?;
+                if requirement.require_witness && witnesses.is_empty() {
+                    continue;
+                }
+                matching.push(Candidate {
+                    digest: credential.digest()?,
+                    credential,
+                    witnesses,
+                });
+            }
+
+            matching.sort_by_key(|candidate| {
+                (
+                    candidate.credential.is_personhood_credential(),
+                    Reverse(candidate.witnesses.len()),
+                    Reverse(candidate.credential.valid_from()),
+                    candidate.digest.clone(),
+                )
+            });
+            if matching.len() < requirement.count {
+                return Err(presentation_error(format!(
+                    "Requirement {index} needs {} {} credential(s), only {} available",
+                    requirement.count,
+                    requirement.type_,
+                    matching.len()
+                )));
+            }
+            candidates.push(matching);
+        }
+
+        let mut order: Vec<usize> = (0..candidates.len()).collect();
+        order.sort_by_key(|&index| candidates[index].len() - request.requirements[index].count);
+        let mut chosen = vec![Vec::new(); candidates.len()];
+        if !fill(
+            &request.requirements,
+            &candidates,
+            &order,
+            &mut BTreeSet::new(),
+            &mut chosen,
+        ) {
+            return Err(presentation_error(
+                "Not enough distinct credentials to satisfy every requirement",
+            ));
+        }
+
+        let mut selected = Vec::new();
+        let mut evidence = Vec::new();
+        let mut used = BTreeSet::new();
+        for ((requirement, matching), positions) in
+            request.requirements.iter().zip(candidates).zip(&chosen)
+        {
+            // Positions are increasing, the preferred credentials come first
+            for (_, candidate) in matching
+                .into_iter()
+                .enumerate()
+                .filter(|(position, _)| positions.contains(position))
+            {
+                used.insert(candidate.digest);
+                selected.push(candidate.credential);
+                if requirement.require_witness {
+                    evidence.extend(candidate.witnesses);
+                }
+            }
+        }
+
+        for witness in evidence {
+            if used.insert(witness.digest()?) {
+                selected.push(witness);
+            }
+        }
+        Ok(selected)
+    }
+
+    /// Selects the credentials that satisfy the request and presents them, signed by the holder
+    /// with the challenge and domain of the request
+    /// signing_secret: The holder's key, see [crate::signer::SigningKey]
+    pub fn present(
+        &self,
+        request: &PresentationRequest,
+        signing_secret: &impl SigningKey,
+    ) -> Result<DTGPresentation, DTGCredentialError> {
+        let mut presentation = DTGPresentation::new(&self.holder, self.select(request)?);
+        presentation.sign(
+            signing_secret,
+            request.challenge.as_deref(),
+            request.domain.as_deref(),
+            None,
+        )?;
+        Ok(presentation)
+    }
+}
+
+/// A credential that satisfies a requirement, with the VWCs witnessing it
+struct Candidate {
+    digest: String,
+    credential: DTGCredential,
+    witnesses: Vec<DTGCredential>,
+}
+
+/// Picks the credentials of the requirements in `order`, `chosen` holds the positions of the
+/// picked candidates. Backtracks when a later requirement can't be filled with the credentials
+/// left over, returns false if no assignment uses each credential only once
+fn fill(
+    requirements: &[CredentialRequirement],
+    candidates: &[Vec<Candidate>],
+    order: &[usize],
+    used: &mut BTreeSet<String>,
+    chosen: &mut [Vec<usize>],
+) -> bool {
+    let Some((&index, rest)) = order.split_first() else {
+        return true;
+    };
+    if chosen[index].len() == requirements[index].count {
+        return fill(requirements, candidates, rest, used, chosen);
+    }
+
+    // Positions are picked in increasing order so each combination is only tried once
+    let start = chosen[index].last().map_or(0, |last| last + 1);
+    for (position, candidate) in candidates[index].iter().enumerate().skip(start) {
+        if !used.insert(candidate.digest.clone()) {
+            continue;
+        }
+        chosen[index].push(position);
+        if fill(requirements, candidates, order, used, chosen) {
+            return true;
+        }
+        chosen[index].pop();
+        used.remove(&candidate.digest);
+    }
+    false
+}
+
+#[cfg(all(test, feature = "affinidi-signing"))]
+mod tests {
+    use super::{CredentialRequirement, PresentationRequest, Wallet};
+    use crate::{
+        DTGCredential, DTGCredentialError, DTGCredentialType,
+        store::{MemoryStore, StoreQuery},
+        test_utils::{did, signed, vmc, vwc},
+    };
+    use affinidi_secrets_resolver::secrets::Secret;
+    use chrono::{Duration, Utc};
+
+    const ALICE: &str = "did:example:alice";
+
+    /// Signed VRC about alice, valid since some days ago
+    fn vrc(from: &str, days_ago: i64) -> DTGCredential {
+        signed(DTGCredential::new_vrc(
+            did(from),
+            ALICE.to_string(),
+            Utc::now() - Duration::days(days_ago),
+            None,
+        ))
+    }
+
+    /// Alice holds:
+    /// - VRC from bob witnessed by carol, and a newer VRC from dave that isn't witnessed
+    /// - An expired VRC from erin and an unsigned VRC from frank
+    /// - VMCs from a club and a PHC from a registry
+    fn wallet() -> (Wallet, DTGCredential) {
+        let mut wallet = Wallet::new(ALICE, MemoryStore::new());
+
+        let bob = vrc("bob", 10);
+        wallet.add(&bob).unwrap();
+        wallet
+            .add(&vwc("carol", "alice", &bob.digest().unwrap(), None))
+            .unwrap();
+        wallet.add(&vrc("dave", 1)).unwrap();
+
+        wallet
+            .add(&signed(DTGCredential::new_vrc(
+                did("erin"),
+                ALICE.to_string(),
+                Utc::now() - Duration::days(365),
+                Some(Utc::now() - Duration::days(30)),
+            )))
+            .unwrap();
+        wallet
+            .add(&DTGCredential::new_vrc(
+                did("frank"),
+                ALICE.to_string(),
+                Utc::now(),
+                None,
+            ))
+            .unwrap();
+
+        wallet.add(&vmc("club", "alice", false)).unwrap();
+        wallet.add(&vmc("registry", "alice", true)).unwrap();
+        (wallet, bob)
+    }
+
+    fn issuers(credentials: &[DTGCredential]) -> Vec<&str> {
+        credentials.iter().map(|c| c.issuer()).collect()
+    }
+
+    #[test]
+    fn test_list() {
+        let (wallet, _) = wallet();
+        assert_eq!(
+            wallet
+                .list(&StoreQuery::new().with_type(DTGCredentialType::Relationship))
+                .unwrap()
+                .len(),
+            4
+        );
+        assert_eq!(
+            wallet
+                .list(
+                    &StoreQuery::new()
+                        .with_type(DTGCredentialType::Relationship)
+                        .with_time(Utc::now())
+                        .with_signed(true)
+                )
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_prefers_witnessed() {
+        let (wallet, bob) = wallet();
+
+        // PHCs are only preferred when personhood is required
+        let request = PresentationRequest::new()
+            .with_requirement(CredentialRequirement::new(DTGCredentialType::Relationship))
+            .with_requirement(CredentialRequirement::new(DTGCredentialType::Membership));
+        let selected = wallet.select(&request).unwrap();
+        assert_eq!(
+            issuers(&selected),
+            vec!["did:example:bob", "did:example:club"]
+        );
+
+        // The VWC is presented when a witness is required
+        let request = PresentationRequest::new().with_requirement(
+            CredentialRequirement::new(DTGCredentialType::Relationship).with_witness_required(),
+        );
+        let selected = wallet.select(&request).unwrap();
+        assert_eq!(
+            issuers(&selected),
+            vec!["did:example:bob", "did:example:carol"]
+        );
+        assert_eq!(selected[0].digest().unwrap(), bob.digest().unwrap());
+        assert_eq!(
+            wallet.witnesses(&bob, Utc::now()).unwrap()[0]
+                .digest()
+                .unwrap(),
+            selected[1].digest().unwrap()
+        );
+
+        let request = PresentationRequest::new().with_requirement(
+            CredentialRequirement::new(DTGCredentialType::Relationship)
+                .with_issuers(&["did:example:dave", "did:example:erin"]),
+        );
+        assert_eq!(
+            issuers(&wallet.select(&request).unwrap()),
+            vec!["did:example:dave"]
+        );
+
+        // Two VMCs, only one is a PHC
+        let request = PresentationRequest::new().with_requirement(
+            CredentialRequirement::new(DTGCredentialType::Membership)
+                .with_personhood()
+                .with_count(2),
+        );
+        assert!(matches!(
+            wallet.select(&request),
+            Err(DTGCredentialError::Presentation(_))
+        ));
+    }
+
+    #[test]
+    fn test_distinct_credentials() {
+        let (wallet, _) = wallet();
+
+        let request = PresentationRequest::new()
+            .with_requirement(CredentialRequirement::new(DTGCredentialType::Relationship))
+            .with_requirement(CredentialRequirement::new(DTGCredentialType::Relationship));
+        assert_eq!(
+            issuers(&wallet.select(&request).unwrap()),
+            vec!["did:example:bob", "did:example:dave"]
+        );
+
+        // Expired and unsigned VRCs are never selected
+        let request =
+            request.with_requirement(CredentialRequirement::new(DTGCredentialType::Relationship));
+        assert!(wallet.select(&request).is_err());
+
+        let request = PresentationRequest::new().with_requirement(
+            CredentialRequirement::new(DTGCredentialType::Relationship)
+                .with_subject("did:example:bob"),
+        );
+        assert!(wallet.select(&request).is_err());
+    }
+
+    #[test]
+    fn test_fills_constrained_requirements_first() {
+        let (wallet, _) = wallet();
+
+        let request = PresentationRequest::new()
+            .with_requirement(CredentialRequirement::new(DTGCredentialType::Membership))
+            .with_requirement(
+                CredentialRequirement::new(DTGCredentialType::Membership).with_personhood(),
+            );
+        assert_eq!(
+            issuers(&wallet.select(&request).unwrap()),
+            vec!["did:example:club", "did:example:registry"]
+        );
+
+        // Only bob's VRC is witnessed, dave's has to fill the first requirement
+        let request = PresentationRequest::new()
+            .with_requirement(CredentialRequirement::new(DTGCredentialType::Relationship))
+            .with_requirement(
+                CredentialRequirement::new(DTGCredentialType::Relationship).with_witness_required(),
+            );
+        assert_eq!(
+            issuers(&wallet.select(&request).unwrap()),
+            vec!["did:example:dave", "did:example:bob", "did:example:carol"]
+        );
+    }
+
+    #[test]
+    fn test_present() {
+        let (wallet, _) = wallet();
+        let holder = Secret::generate_ed25519(None, None);
+
+        let request = PresentationRequest::new()
+            .with_requirement(
+                CredentialRequirement::new(DTGCredentialType::Membership).with_personhood(),
+            )
+            .with_challenge("1234")
+            .with_domain("verifier.example");
+        let presentation = wallet.present(&request, &holder).unwrap();
+
+        assert_eq!(presentation.holder(), ALICE);
+        assert!(presentation.credentials()[0].is_personhood_credential());
+        assert!(
+            presentation
+                .verify_proof_with_public_key(
+                    holder.get_public_bytes(),
+                    Some("1234"),
+                    Some("verifier.example")
+                )
+                .is_ok()
+        );
+    }
+}